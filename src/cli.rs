@@ -1,117 +1,571 @@
-/// Options parsed from command line arguments
-#[derive(Debug, Clone)]
+use crate::config::{
+    Config, InstructionsMode, RestartPolicy, SinkConfig, ToolTimeout, UnmatchedCall,
+    parse_http_path, parse_protocol_version, parse_tool_timeout,
+};
+use crate::logging::LogEntryType;
+use clap::{CommandFactory, Parser, error::ErrorKind};
+use std::path::PathBuf;
+
+/// Options parsed from command line arguments.
+///
+/// Every setting mirrors an environment variable and a config file key and takes
+/// precedence over both.
+#[derive(Debug, Clone, Parser)]
+#[command(
+    name = "wrap-mcp",
+    version,
+    about = "Transparent MCP proxy that logs the traffic to a wrapped MCP server",
+    override_usage = "wrap-mcp [OPTIONS] [--] <COMMAND> [ARGS]...\n       wrap-mcp [OPTIONS] [--] <URL>\n       wrap-mcp [OPTIONS] --replay <FILE>",
+    after_help = "Settings not given on the command line are taken from WRAP_MCP_* environment \
+                  variables, then from the config file, then from the defaults."
+)]
 pub struct CliOptions {
-    pub preserve_ansi: bool,
-    pub watch_binary: bool,
-    pub command: String,
-    pub args: Vec<String>,
+    /// Preserve ANSI escape sequences in stderr logs
+    #[arg(long = "ansi", value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub preserve_ansi: Option<bool>,
+
+    /// Watch the wrappee binary for changes and restart it (requires an absolute path)
+    #[arg(short = 'w', long = "watch", value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub watch_binary: Option<bool>,
+
+    /// Connect to a URL wrappee with the legacy HTTP+SSE transport
+    #[arg(long = "sse", value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub legacy_sse: Option<bool>,
+
+    /// Replay a recorded log (`show_log format=json` output, JSON Lines or a log
+    /// directory) as the wrappee instead of running one
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+
+    /// How a replayed wrappee answers unrecorded tool calls: error, same-tool or empty
+    #[arg(long, value_name = "MODE")]
+    pub replay_unmatched: Option<UnmatchedCall>,
+
+    /// Read settings from a TOML config file
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Transport for client connections [env: WRAP_MCP_TRANSPORT]
+    #[arg(long, value_name = "TRANSPORT", value_parser = ["stdio", "streamable-http", "http"])]
+    pub transport: Option<String>,
+
+    /// Host the HTTP transport binds to [env: WRAP_MCP_HOST]
+    #[arg(long, value_name = "HOST", help_heading = "HTTP transport")]
+    pub host: Option<String>,
+
+    /// Port the HTTP transport binds to, 0 to pick a free one [env: WRAP_MCP_PORT]
+    #[arg(long, value_name = "PORT", help_heading = "HTTP transport")]
+    pub port: Option<u16>,
+
+    /// Path the MCP endpoint is mounted at [env: WRAP_MCP_PATH]
+    #[arg(long, value_name = "PATH", value_parser = parse_http_path, help_heading = "HTTP transport")]
+    pub path: Option<String>,
+
+    /// Unix domain socket to bind instead of host and port [env: WRAP_MCP_UNIX_SOCKET]
+    #[arg(long, value_name = "SOCKET", help_heading = "HTTP transport")]
+    pub unix_socket: Option<PathBuf>,
+
+    /// Maximum number of log entries to retain [env: WRAP_MCP_LOGSIZE]
+    #[arg(long, value_name = "N", value_parser = parse_positive_usize, help_heading = "Logging")]
+    pub log_size: Option<usize>,
+
+    /// Enable ANSI colors in Wrap-MCP's own output [env: WRAP_MCP_LOG_COLORS]
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", help_heading = "Logging")]
+    pub log_colors: Option<bool>,
+
+    /// Record wrappee progress notifications in the log [env: WRAP_MCP_LOG_PROGRESS]
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", help_heading = "Logging")]
+    pub log_progress: Option<bool>,
+
+    /// Capture every JSON-RPC frame to and from the client and the wrappee [env: WRAP_MCP_LOG_RAW]
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", help_heading = "Logging")]
+    pub log_raw: Option<bool>,
+
+    /// Log filter for Wrap-MCP's own output, e.g. `debug` [env: RUST_LOG]
+    #[arg(long, value_name = "FILTER", help_heading = "Logging")]
+    pub log_level: Option<String>,
+
+    /// Directory to persist log entries to as JSON Lines [env: WRAP_MCP_LOG_DIR]
+    #[arg(long, value_name = "DIR", help_heading = "Logging")]
+    pub log_dir: Option<PathBuf>,
+
+    /// Size in bytes after which a new log segment is started [env: WRAP_MCP_LOG_ROTATE_BYTES]
+    #[arg(long, value_name = "BYTES", value_parser = clap::value_parser!(u64).range(1..), help_heading = "Logging")]
+    pub log_rotate_bytes: Option<u64>,
+
+    /// Age in seconds after which a new log segment is started, 0 for never [env: WRAP_MCP_LOG_ROTATE_AGE]
+    #[arg(long, value_name = "SECS", help_heading = "Logging")]
+    pub log_rotate_age: Option<u64>,
+
+    /// Number of log segments kept [env: WRAP_MCP_LOG_RETAIN]
+    #[arg(long, value_name = "N", value_parser = parse_positive_usize, help_heading = "Logging")]
+    pub log_retain: Option<usize>,
+
+    /// Load the most recent persisted entries at startup [env: WRAP_MCP_LOG_RELOAD]
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", help_heading = "Logging")]
+    pub log_reload: Option<bool>,
+
+    /// SQLite database to keep log entries in instead of memory [env: WRAP_MCP_LOG_DB]
+    #[arg(long, value_name = "FILE", help_heading = "Logging")]
+    pub log_db: Option<PathBuf>,
+
+    /// Entry types kept in the log, comma-separated [env: WRAP_MCP_LOG_ENTRY_TYPES]
+    #[arg(
+        long,
+        value_name = "TYPE",
+        value_delimiter = ',',
+        help_heading = "Logging"
+    )]
+    pub log_entry_types: Vec<LogEntryType>,
+
+    /// Further destination for log entries, `stderr`, `file:PATH`, `tcp:HOST:PORT` or `unix:PATH`,
+    /// optionally followed by `@TYPE,...`; repeatable [env: WRAP_MCP_LOG_SINKS]
+    #[arg(long = "log-sink", value_name = "SINK", help_heading = "Logging")]
+    pub log_sinks: Vec<SinkConfig>,
+
+    /// Timeout for tool calls in seconds [env: WRAP_MCP_TOOL_TIMEOUT]
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..), help_heading = "Wrappee")]
+    pub tool_timeout: Option<u64>,
+
+    /// Per-tool timeout overrides, comma-separated or repeated [env: WRAP_MCP_TOOL_TIMEOUTS]
+    #[arg(long, value_name = "PATTERN=SECS", value_delimiter = ',', value_parser = parse_tool_timeout, help_heading = "Wrappee")]
+    pub tool_timeouts: Vec<ToolTimeout>,
+
+    /// Timeout for connecting to and initializing the wrappee in seconds [env: WRAP_MCP_STARTUP_TIMEOUT]
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..), help_heading = "Wrappee")]
+    pub startup_timeout: Option<u64>,
+
+    /// Timeout for listing the wrappee's tools in seconds [env: WRAP_MCP_DISCOVERY_TIMEOUT]
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..), help_heading = "Wrappee")]
+    pub discovery_timeout: Option<u64>,
+
+    /// Protocol version offered to the wrappee when the client's is unknown [env: WRAP_MCP_PROTOCOL_VERSION]
+    #[arg(long, value_name = "YYYY-MM-DD", value_parser = parse_protocol_version, help_heading = "Wrappee")]
+    pub protocol_version: Option<String>,
+
+    /// Maximum number of requests in flight to the wrappee [env: WRAP_MCP_MAX_CONCURRENCY]
+    #[arg(long, value_name = "N", value_parser = parse_positive_usize, help_heading = "Wrappee")]
+    pub max_concurrency: Option<usize>,
+
+    /// How the wrappee's instructions are presented: passthrough, append or proxy [env: WRAP_MCP_INSTRUCTIONS]
+    #[arg(long, value_name = "MODE", help_heading = "Wrappee")]
+    pub instructions: Option<InstructionsMode>,
+
+    /// Report the wrappee's name and version instead of Wrap-MCP's [env: WRAP_MCP_PASSTHROUGH_SERVER_INFO]
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", help_heading = "Wrappee")]
+    pub passthrough_server_info: Option<bool>,
+
+    /// How long each shutdown stage waits for the wrappee to exit [env: WRAP_MCP_SHUTDOWN_GRACE_MS]
+    #[arg(long, value_name = "MS", help_heading = "Wrappee")]
+    pub shutdown_grace_ms: Option<u64>,
+
+    /// How long requests wait for a starting wrappee in seconds [env: WRAP_MCP_READY_TIMEOUT]
+    #[arg(long, value_name = "SECS", help_heading = "Wrappee")]
+    pub ready_timeout: Option<u64>,
+
+    /// When to restart a wrappee that went away: never, on-failure or always [env: WRAP_MCP_RESTART]
+    #[arg(long, value_name = "POLICY", help_heading = "Restart")]
+    pub restart: Option<RestartPolicy>,
+
+    /// Consecutive restart attempts before giving up [env: WRAP_MCP_RESTART_MAX_ATTEMPTS]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..), help_heading = "Restart")]
+    pub restart_max_attempts: Option<u32>,
+
+    /// Delay before the first restart attempt [env: WRAP_MCP_RESTART_BACKOFF_MS]
+    #[arg(long, value_name = "MS", help_heading = "Restart")]
+    pub restart_backoff_ms: Option<u64>,
+
+    /// Upper bound for the restart delay [env: WRAP_MCP_RESTART_BACKOFF_MAX_MS]
+    #[arg(long, value_name = "MS", help_heading = "Restart")]
+    pub restart_backoff_max_ms: Option<u64>,
+
+    /// Seconds between health check pings, 0 to disable [env: WRAP_MCP_PING_INTERVAL]
+    #[arg(long, value_name = "SECS", help_heading = "Health")]
+    pub ping_interval: Option<u64>,
+
+    /// Failed pings after which the wrappee is unhealthy [env: WRAP_MCP_PING_FAILURES]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..), help_heading = "Health")]
+    pub ping_failures: Option<u32>,
+
+    /// Restart the wrappee once it is unhealthy [env: WRAP_MCP_PING_RESTART]
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", help_heading = "Health")]
+    pub ping_restart: Option<bool>,
+
+    /// Wrappee command and its arguments, or the URL of a wrappee
+    #[arg(value_name = "COMMAND", trailing_var_arg = true)]
+    pub wrappee: Vec<String>,
 }
 
 impl CliOptions {
-    /// Parse command line arguments
-    /// Format: wrap-mcp [options] -- <command> [args...]
-    /// Options:
-    ///   --ansi  Preserve ANSI escape sequences
-    ///   -w      Watch binary file for changes
+    /// Parse command line arguments, exiting with usage help on errors
     pub fn from_args() -> Self {
-        let args: Vec<String> = std::env::args().collect();
-        Self::parse(&args)
-    }
-
-    /// Parse from a given argument list
-    pub fn parse(args: &[String]) -> Self {
-        // Find the "--" separator
-        let separator_pos = args.iter().position(|arg| arg == "--");
-
-        // Check for options before "--"
-        let (preserve_ansi, watch_binary) = separator_pos
-            .map(|pos| {
-                let opts = &args[1..pos];
-                (
-                    opts.contains(&"--ansi".to_string()),
-                    opts.contains(&"-w".to_string()),
-                )
-            })
-            .unwrap_or((false, false));
-
-        // Extract command and arguments after "--"
-        let (command, wrappee_args) = match separator_pos {
-            Some(pos) if pos + 1 < args.len() => {
-                let command = args[pos + 1].clone();
-                let wrappee_args = args
-                    .get(pos + 2..)
-                    .map(|slice| slice.to_vec())
-                    .unwrap_or_default();
-                (command, wrappee_args)
-            }
-            _ => {
-                tracing::warn!(
-                    "No wrappee command specified. Usage: wrap-mcp [options] -- <command> [args...]"
-                );
-                (
-                    "echo".to_string(),
-                    vec!["No wrappee command specified".to_string()],
-                )
+        Self::parse()
+    }
+
+    /// Override the settings from the environment and config file with the command line
+    pub fn apply(&self, config: &mut Config) {
+        fn set<T: Clone>(target: &mut T, value: &Option<T>) {
+            if let Some(value) = value {
+                *target = value.clone();
             }
-        };
+        }
+
+        let launch = &mut config.launch;
+        if let Some((command, args)) = self.wrappee.split_first() {
+            launch.command = Some(command.clone());
+            launch.args = args.to_vec();
+        }
+        set(&mut launch.preserve_ansi, &self.preserve_ansi);
+        set(&mut launch.watch_binary, &self.watch_binary);
+        set(&mut launch.legacy_sse, &self.legacy_sse);
+        if self.replay.is_some() {
+            launch.replay.clone_from(&self.replay);
+        }
+        if let Some(unmatched) = self.replay_unmatched {
+            launch.replay_unmatched = unmatched;
+        }
+
+        let transport = &mut config.transport;
+        set(&mut transport.transport, &self.transport);
+        set(&mut transport.host, &self.host);
+        set(&mut transport.port, &self.port);
+        set(&mut transport.path, &self.path);
+        if self.unix_socket.is_some() {
+            transport.unix_socket.clone_from(&self.unix_socket);
+        }
 
-        tracing::info!(
-            "Parsed CLI options: command={command}, args={wrappee_args:?}, preserve_ansi={preserve_ansi}, watch={watch_binary}"
+        let log = &mut config.log;
+        set(&mut log.log_size, &self.log_size);
+        set(&mut log.log_colors, &self.log_colors);
+        set(&mut log.log_progress, &self.log_progress);
+        set(&mut log.log_raw, &self.log_raw);
+        set(&mut log.rust_log, &self.log_level);
+        if self.log_dir.is_some() {
+            log.persist.dir.clone_from(&self.log_dir);
+        }
+        set(&mut log.persist.rotate_bytes, &self.log_rotate_bytes);
+        set(&mut log.persist.rotate_age_secs, &self.log_rotate_age);
+        set(&mut log.persist.retain, &self.log_retain);
+        set(&mut log.persist.reload, &self.log_reload);
+        if self.log_db.is_some() {
+            log.db.clone_from(&self.log_db);
+        }
+        if !self.log_entry_types.is_empty() {
+            log.entry_types = self.log_entry_types.clone();
+        }
+        if !self.log_sinks.is_empty() {
+            log.sinks = self.log_sinks.clone();
+        }
+
+        let wrappee = &mut config.wrappee;
+        set(&mut wrappee.tool_timeout_secs, &self.tool_timeout);
+        if !self.tool_timeouts.is_empty() {
+            wrappee.tool_timeouts = self.tool_timeouts.clone();
+        }
+        set(&mut wrappee.startup_timeout_secs, &self.startup_timeout);
+        set(&mut wrappee.discovery_timeout_secs, &self.discovery_timeout);
+        set(&mut wrappee.protocol_version, &self.protocol_version);
+        set(&mut wrappee.max_concurrency, &self.max_concurrency);
+        set(&mut wrappee.instructions_mode, &self.instructions);
+        set(
+            &mut wrappee.passthrough_server_info,
+            &self.passthrough_server_info,
+        );
+        set(&mut wrappee.shutdown_grace_ms, &self.shutdown_grace_ms);
+        set(&mut wrappee.ready_timeout_secs, &self.ready_timeout);
+        set(&mut wrappee.restart.policy, &self.restart);
+        set(
+            &mut wrappee.restart.max_attempts,
+            &self.restart_max_attempts,
         );
+        set(&mut wrappee.restart.backoff_ms, &self.restart_backoff_ms);
+        set(
+            &mut wrappee.restart.backoff_max_ms,
+            &self.restart_backoff_max_ms,
+        );
+        set(&mut wrappee.health.interval_secs, &self.ping_interval);
+        set(&mut wrappee.health.failure_threshold, &self.ping_failures);
+        set(&mut wrappee.health.restart, &self.ping_restart);
+
+        // Checked settings given here are named by their flag if they are invalid
+        config.set_command_line_sources(|flag| self.gives(flag));
+    }
 
-        Self {
-            preserve_ansi,
-            watch_binary,
-            command,
-            args: wrappee_args,
+    /// Whether a flag that `Config::validate` checks was given
+    fn gives(&self, flag: &str) -> bool {
+        match flag {
+            "COMMAND" => !self.wrappee.is_empty(),
+            "--path" => self.path.is_some(),
+            "--tool-timeout" => self.tool_timeout.is_some(),
+            "--startup-timeout" => self.startup_timeout.is_some(),
+            "--discovery-timeout" => self.discovery_timeout.is_some(),
+            "--protocol-version" => self.protocol_version.is_some(),
+            "--max-concurrency" => self.max_concurrency.is_some(),
+            "--restart-max-attempts" => self.restart_max_attempts.is_some(),
+            "--ping-failures" => self.ping_failures.is_some(),
+            "--log-size" => self.log_size.is_some(),
+            "--log-rotate-bytes" => self.log_rotate_bytes.is_some(),
+            "--log-retain" => self.log_retain.is_some(),
+            other => {
+                unreachable!("checked setting flag {other} is not handled by the command line")
+            }
         }
     }
 
-    /// Check if colors should be disabled
-    pub fn disable_colors(&self) -> bool {
-        !self.preserve_ansi
+    /// Usage error for when neither the command line nor the config file names a wrappee
+    pub fn missing_wrappee_error() -> clap::Error {
+        Self::command().error(
+            ErrorKind::MissingRequiredArgument,
+            "no wrappee given; pass a command or URL after `--`, a recording with `--replay` \
+             or set `command` in the config file",
+        )
+    }
+}
+
+/// Parse a count that must be greater than 0
+fn parse_positive_usize(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(0) => Err("must be greater than 0".to_string()),
+        Ok(n) => Ok(n),
+        Err(e) => Err(e.to_string()),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::LaunchConfig;
+    use crate::wrappee::WrappeeTarget;
+    use serial_test::serial;
+
+    fn parse(args: &[&str]) -> Result<CliOptions, clap::Error> {
+        CliOptions::try_parse_from(std::iter::once("wrap-mcp").chain(args.iter().copied()))
+    }
+
+    fn launch(opts: &CliOptions) -> LaunchConfig {
+        let mut config = Config::default();
+        opts.apply(&mut config);
+        config.launch
+    }
 
     #[test]
     fn test_parse_with_command() {
-        let args = vec![
-            "wrap-mcp".to_string(),
-            "--".to_string(),
-            "echo".to_string(),
-            "hello".to_string(),
-        ];
-        let opts = CliOptions::parse(&args);
-        assert_eq!(opts.command, "echo");
-        assert_eq!(opts.args, vec!["hello"]);
-        assert!(!opts.preserve_ansi);
-        assert!(!opts.watch_binary);
+        let opts = parse(&["--", "echo", "hello"]).unwrap();
+        let launch = launch(&opts);
+        assert_eq!(launch.command.as_deref(), Some("echo"));
+        assert_eq!(launch.args, vec!["hello"]);
+        assert_eq!(opts.preserve_ansi, None);
+        assert_eq!(opts.watch_binary, None);
+    }
+
+    #[test]
+    fn test_parse_command_without_separator() {
+        let opts = parse(&["my-server", "--port", "8080"]).unwrap();
+        let launch = launch(&opts);
+        assert_eq!(launch.command.as_deref(), Some("my-server"));
+        assert_eq!(launch.args, vec!["--port", "8080"]);
     }
 
     #[test]
     fn test_parse_with_options() {
-        let args = vec![
-            "wrap-mcp".to_string(),
-            "--ansi".to_string(),
-            "-w".to_string(),
-            "--".to_string(),
-            "cat".to_string(),
-        ];
-        let opts = CliOptions::parse(&args);
-        assert_eq!(opts.command, "cat");
-        assert!(opts.preserve_ansi);
-        assert!(opts.watch_binary);
+        let opts = parse(&["--ansi", "-w", "--", "cat"]).unwrap();
+        assert_eq!(launch(&opts).command.as_deref(), Some("cat"));
+        assert_eq!(opts.preserve_ansi, Some(true));
+        assert_eq!(opts.watch_binary, Some(true));
+    }
+
+    #[test]
+    fn test_parse_url_target() {
+        let opts = parse(&["--sse", "--", "http://127.0.0.1:8080/sse"]).unwrap();
+        assert_eq!(opts.legacy_sse, Some(true));
+        assert_eq!(
+            launch(&opts).target(),
+            Some(WrappeeTarget::Sse {
+                url: "http://127.0.0.1:8080/sse".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_replay_target() {
+        let opts = parse(&[
+            "--replay",
+            "session.json",
+            "--replay-unmatched",
+            "same-tool",
+        ])
+        .unwrap();
+        assert_eq!(
+            launch(&opts).target(),
+            Some(WrappeeTarget::Replay {
+                path: PathBuf::from("session.json"),
+                unmatched: UnmatchedCall::SameTool,
+            })
+        );
+        assert!(parse(&["--replay", "session.json", "--replay-unmatched", "closest"]).is_err());
     }
 
     #[test]
     fn test_parse_no_command() {
-        let args = vec!["wrap-mcp".to_string()];
-        let opts = CliOptions::parse(&args);
-        assert_eq!(opts.command, "echo");
-        assert_eq!(opts.args, vec!["No wrappee command specified"]);
+        let opts = parse(&[]).unwrap();
+        assert!(opts.wrappee.is_empty());
+        assert_eq!(launch(&opts).target(), None);
+    }
+
+    #[test]
+    fn test_unknown_option_is_rejected() {
+        let err = parse(&["--verbose", "--", "cat"]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnknownArgument);
+    }
+
+    #[test]
+    fn test_invalid_values_are_rejected() {
+        assert!(parse(&["--tool-timeout", "0", "--", "cat"]).is_err());
+        assert!(parse(&["--log-size", "many", "--", "cat"]).is_err());
+        assert!(parse(&["--protocol-version", "latest", "--", "cat"]).is_err());
+        assert!(parse(&["--transport", "tcp", "--", "cat"]).is_err());
+        assert!(parse(&["--path", "mcp", "--", "cat"]).is_err());
+        assert!(parse(&["--port", "70000", "--", "cat"]).is_err());
+        assert!(parse(&["--restart", "sometimes", "--", "cat"]).is_err());
+        assert!(parse(&["--log-entry-types", "request,reply", "--", "cat"]).is_err());
+        assert!(parse(&["--log-sink", "syslog", "--", "cat"]).is_err());
+    }
+
+    #[test]
+    fn test_help_and_version() {
+        assert_eq!(
+            parse(&["--help"]).unwrap_err().kind(),
+            ErrorKind::DisplayHelp
+        );
+        assert_eq!(
+            parse(&["--version"]).unwrap_err().kind(),
+            ErrorKind::DisplayVersion
+        );
+    }
+
+    #[test]
+    fn test_flags_override_config() {
+        let opts = parse(&[
+            "--transport",
+            "http",
+            "--port",
+            "0",
+            "--path",
+            "/v1/mcp",
+            "--log-size=50",
+            "--log-progress",
+            "--log-raw",
+            "--tool-timeout",
+            "90",
+            "--tool-timeouts",
+            "build_*=600,lint=5",
+            "--protocol-version",
+            "2024-11-05",
+            "--restart",
+            "never",
+            "--ping-restart=false",
+            "--log-entry-types",
+            "request,error",
+            "--log-sink",
+            "stderr@error",
+            "--log-sink",
+            "tcp:127.0.0.1:9000",
+            "--",
+            "cat",
+        ])
+        .unwrap();
+
+        let mut config = Config::default();
+        config.wrappee.health.restart = true;
+        opts.apply(&mut config);
+
+        assert_eq!(config.transport.transport, "http");
+        assert_eq!(config.transport.port, 0);
+        assert_eq!(config.transport.path, "/v1/mcp");
+        assert_eq!(config.transport.host, "127.0.0.1");
+        assert_eq!(config.log.log_size, 50);
+        assert!(config.log.log_progress);
+        assert!(config.log.log_raw);
+        assert_eq!(config.wrappee.tool_timeout_secs, 90);
+        assert_eq!(config.wrappee.tool_timeout("build_all").as_secs(), 600);
+        assert_eq!(config.wrappee.tool_timeout("lint").as_secs(), 5);
+        assert_eq!(config.wrappee.protocol_version, "2024-11-05");
+        assert_eq!(config.wrappee.restart.policy, RestartPolicy::Never);
+        assert!(!config.wrappee.health.restart);
+        assert_eq!(
+            config.log.entry_types,
+            vec![LogEntryType::Request, LogEntryType::Error]
+        );
+        assert_eq!(config.log.sinks.len(), 2);
+        assert_eq!(config.log.sinks[1].target.to_string(), "tcp:127.0.0.1:9000");
+        // Untouched settings keep their values
+        assert_eq!(config.wrappee.max_concurrency, 16);
+    }
+
+    #[test]
+    fn test_command_line_overrides_config_file() {
+        let opts = parse(&["--config", "wrap.toml", "--ansi", "--", "cat"]).unwrap();
+        assert_eq!(opts.config, Some(PathBuf::from("wrap.toml")));
+
+        let mut config = Config {
+            launch: LaunchConfig {
+                command: Some("my-server".to_string()),
+                args: vec!["--verbose".to_string()],
+                watch_binary: true,
+                ..LaunchConfig::default()
+            },
+            ..Config::default()
+        };
+        opts.apply(&mut config);
+        assert_eq!(config.launch.command.as_deref(), Some("cat"));
+        assert!(config.launch.args.is_empty());
+        assert!(config.launch.preserve_ansi);
+        assert!(config.launch.watch_binary);
+
+        let opts = parse(&["--config=wrap.toml"]).unwrap();
+        assert_eq!(opts.config, Some(PathBuf::from("wrap.toml")));
+        opts.apply(&mut config);
+        assert_eq!(config.launch.command.as_deref(), Some("cat"));
+        assert!(config.launch.watch_binary);
+
+        // Switches turned on in the config file can be turned off again
+        let opts = parse(&["--watch=false", "--ansi=false", "--", "cat"]).unwrap();
+        opts.apply(&mut config);
+        assert!(!config.launch.watch_binary);
+        assert!(!config.launch.preserve_ansi);
+    }
+
+    #[test]
+    #[serial]
+    fn test_command_line_overrides_invalid_env_before_validation() {
+        let original = std::env::var("WRAP_MCP_TOOL_TIMEOUT").ok();
+        unsafe {
+            std::env::set_var("WRAP_MCP_TOOL_TIMEOUT", "0");
+        }
+
+        let mut config = Config::read(None).unwrap();
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("WRAP_MCP_TOOL_TIMEOUT"), "{err}");
+
+        parse(&["--tool-timeout", "60", "--", "cat"])
+            .unwrap()
+            .apply(&mut config);
+        assert!(config.validate().is_ok());
+        assert_eq!(config.wrappee.tool_timeout_secs, 60);
+
+        unsafe {
+            std::env::remove_var("WRAP_MCP_TOOL_TIMEOUT");
+            if let Some(v) = original {
+                std::env::set_var("WRAP_MCP_TOOL_TIMEOUT", v);
+            }
+        }
+    }
+
+    #[test]
+    fn test_every_checked_setting_has_a_flag() {
+        // `gives` panics on a checked setting it does not know the flag of
+        let mut config = Config::default();
+        parse(&[]).unwrap().apply(&mut config);
+        assert!(config.sources.is_empty());
     }
 }
@@ -0,0 +1,652 @@
+//! Loading settings from a TOML config file.
+//!
+//! ```toml
+//! [transport]
+//! type = "stdio"
+//! host = "127.0.0.1"
+//! port = 8000
+//! path = "/mcp"
+//! # unix_socket = "/run/wrap-mcp.sock"
+//!
+//! [log]
+//! size = 1000
+//! colors = false
+//! progress = false
+//! raw = false
+//! rust_log = "info"
+//! # db = "/var/log/wrap-mcp/log.db"
+//! # entry_types = ["request", "response", "error"]
+//!
+//! [[log.sinks]]
+//! type = "stderr"
+//! entry_types = ["error", "stderr"]
+//!
+//! [[log.sinks]]
+//! type = "tcp"                  # or "unix" / "file" with `path`
+//! address = "127.0.0.1:9000"
+//!
+//! [log.persist]
+//! dir = "/var/log/wrap-mcp"
+//! rotate_bytes = 10485760
+//! rotate_age = 0
+//! retain = 10
+//! reload = true
+//!
+//! [wrappee]
+//! command = "/path/to/my-mcp-server"
+//! args = ["--port", "8080"]
+//! cwd = "/path/to/workdir"
+//! env = { API_KEY = "secret" }
+//! ansi = false
+//! watch = false
+//! sse = false
+//! # replay = "/path/to/recording.json"  # instead of command
+//! replay_unmatched = "error"
+//! tool_timeout = 30
+//! tool_timeouts = [{ pattern = "build_*", secs = 600 }]
+//! startup_timeout = 30
+//! discovery_timeout = 30
+//! protocol_version = "2025-03-26"
+//! max_concurrency = 16
+//! instructions = "append"
+//! passthrough_server_info = false
+//! shutdown_grace_ms = 2000
+//! ready_timeout = 30
+//!
+//! [wrappee.restart]
+//! policy = "on-failure"
+//! max_attempts = 5
+//! backoff_ms = 500
+//! backoff_max_ms = 30000
+//!
+//! [wrappee.health]
+//! ping_interval = 30
+//! ping_failures = 3
+//! ping_restart = false
+//! ```
+
+use super::{
+    CHECKED_SETTINGS, Config, ConfigError, SinkConfig, Source, ToolTimeout, parse_http_path,
+    parse_protocol_version,
+};
+use crate::logging::LogEntryType;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+impl Config {
+    /// Override settings with the ones present in the config file
+    pub(super) fn apply_file(&mut self, path: &Path) -> Result<(), ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::ReadFile {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let table: Table = text.parse().map_err(|source| ConfigError::FileSyntax {
+            path: path.to_path_buf(),
+            source: Box::new(source),
+        })?;
+
+        for setting in CHECKED_SETTINGS {
+            if has_key(&table, setting.key) {
+                self.set_source(setting.key, Source::File(path.to_path_buf()));
+            }
+        }
+
+        let mut root = Section::new(path, String::new(), table);
+
+        if let Some(mut transport) = root.section("transport")? {
+            if let Some(kind) = transport.string("type")? {
+                self.transport.transport = kind;
+            }
+            if let Some(host) = transport.string("host")? {
+                self.transport.host = host;
+            }
+            if let Some(port) = transport.integer("port")? {
+                self.transport.port = port;
+            }
+            if let Some(path) = transport.string("path")? {
+                self.transport.path =
+                    parse_http_path(&path).map_err(|message| transport.invalid("path", message))?;
+            }
+            if let Some(socket) = transport.string("unix_socket")? {
+                self.transport.unix_socket = Some(PathBuf::from(socket));
+            }
+            transport.finish()?;
+        }
+
+        if let Some(mut log) = root.section("log")? {
+            if let Some(size) = log.positive("size")? {
+                self.log.log_size = size;
+            }
+            if let Some(colors) = log.bool("colors")? {
+                self.log.log_colors = colors;
+            }
+            if let Some(progress) = log.bool("progress")? {
+                self.log.log_progress = progress;
+            }
+            if let Some(raw) = log.bool("raw")? {
+                self.log.log_raw = raw;
+            }
+            if let Some(rust_log) = log.string("rust_log")? {
+                self.log.rust_log = rust_log;
+            }
+            if let Some(db) = log.string("db")? {
+                self.log.db = Some(PathBuf::from(db));
+            }
+            if let Some(entry_types) = log.entry_types("entry_types")? {
+                self.log.entry_types = entry_types;
+            }
+            if let Some(sinks) = log.sinks("sinks")? {
+                self.log.sinks = sinks;
+            }
+            if let Some(mut persist) = log.section("persist")? {
+                let config = &mut self.log.persist;
+                if let Some(dir) = persist.string("dir")? {
+                    config.dir = Some(PathBuf::from(dir));
+                }
+                if let Some(bytes) = persist.positive("rotate_bytes")? {
+                    config.rotate_bytes = bytes;
+                }
+                if let Some(secs) = persist.integer("rotate_age")? {
+                    config.rotate_age_secs = secs;
+                }
+                if let Some(retain) = persist.positive("retain")? {
+                    config.retain = retain;
+                }
+                if let Some(reload) = persist.bool("reload")? {
+                    config.reload = reload;
+                }
+                persist.finish()?;
+            }
+            log.finish()?;
+        }
+
+        if let Some(mut wrappee) = root.section("wrappee")? {
+            self.apply_wrappee_section(&mut wrappee)?;
+            wrappee.finish()?;
+        }
+
+        root.finish()
+    }
+
+    fn apply_wrappee_section(&mut self, wrappee: &mut Section) -> Result<(), ConfigError> {
+        let launch = &mut self.launch;
+        if let Some(command) = wrappee.string("command")? {
+            launch.command = Some(command);
+        }
+        if let Some(args) = wrappee.strings("args")? {
+            launch.args = args;
+        }
+        if let Some(mut env) = wrappee.section("env")? {
+            for name in env.keys() {
+                if let Some(value) = env.string(&name)? {
+                    launch.env.insert(name, value);
+                }
+            }
+        }
+        if let Some(cwd) = wrappee.string("cwd")? {
+            launch.cwd = Some(PathBuf::from(cwd));
+        }
+        if let Some(ansi) = wrappee.bool("ansi")? {
+            launch.preserve_ansi = ansi;
+        }
+        if let Some(watch) = wrappee.bool("watch")? {
+            launch.watch_binary = watch;
+        }
+        if let Some(sse) = wrappee.bool("sse")? {
+            launch.legacy_sse = sse;
+        }
+        if let Some(replay) = wrappee.string("replay")? {
+            launch.replay = Some(PathBuf::from(replay));
+        }
+        if let Some(unmatched) = wrappee.parsed("replay_unmatched")? {
+            launch.replay_unmatched = unmatched;
+        }
+
+        let config = &mut self.wrappee;
+        if let Some(secs) = wrappee.positive("tool_timeout")? {
+            config.tool_timeout_secs = secs;
+        }
+        if let Some(timeouts) = wrappee.tool_timeouts("tool_timeouts")? {
+            config.tool_timeouts = timeouts;
+        }
+        if let Some(secs) = wrappee.positive("startup_timeout")? {
+            config.startup_timeout_secs = secs;
+        }
+        if let Some(secs) = wrappee.positive("discovery_timeout")? {
+            config.discovery_timeout_secs = secs;
+        }
+        if let Some(version) = wrappee.string("protocol_version")? {
+            config.protocol_version = parse_protocol_version(&version)
+                .map_err(|message| wrappee.invalid("protocol_version", message))?;
+        }
+        if let Some(concurrency) = wrappee.positive("max_concurrency")? {
+            config.max_concurrency = concurrency;
+        }
+        if let Some(mode) = wrappee.parsed("instructions")? {
+            config.instructions_mode = mode;
+        }
+        if let Some(passthrough) = wrappee.bool("passthrough_server_info")? {
+            config.passthrough_server_info = passthrough;
+        }
+        if let Some(grace) = wrappee.integer("shutdown_grace_ms")? {
+            config.shutdown_grace_ms = grace;
+        }
+        if let Some(secs) = wrappee.integer("ready_timeout")? {
+            config.ready_timeout_secs = secs;
+        }
+
+        if let Some(mut restart) = wrappee.section("restart")? {
+            if let Some(policy) = restart.parsed("policy")? {
+                config.restart.policy = policy;
+            }
+            if let Some(attempts) = restart.positive("max_attempts")? {
+                config.restart.max_attempts = attempts;
+            }
+            if let Some(backoff) = restart.integer("backoff_ms")? {
+                config.restart.backoff_ms = backoff;
+            }
+            if let Some(backoff) = restart.integer("backoff_max_ms")? {
+                config.restart.backoff_max_ms = backoff;
+            }
+            restart.finish()?;
+        }
+
+        if let Some(mut health) = wrappee.section("health")? {
+            if let Some(secs) = health.integer("ping_interval")? {
+                config.health.interval_secs = secs;
+            }
+            if let Some(failures) = health.positive("ping_failures")? {
+                config.health.failure_threshold = failures;
+            }
+            if let Some(restart) = health.bool("ping_restart")? {
+                config.health.restart = restart;
+            }
+            health.finish()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A table of the config file whose keys are taken as they are applied, so that
+/// the ones left over can be reported as unknown
+struct Section<'a> {
+    path: &'a Path,
+    /// Dotted path of the table, empty for the top level
+    prefix: String,
+    table: Table,
+}
+
+impl<'a> Section<'a> {
+    fn new(path: &'a Path, prefix: String, table: Table) -> Self {
+        Self {
+            path,
+            prefix,
+            table,
+        }
+    }
+
+    /// Full dotted name of a key in this table
+    fn key(&self, key: &str) -> String {
+        if self.prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{key}", self.prefix)
+        }
+    }
+
+    fn invalid(&self, key: &str, message: impl Into<String>) -> ConfigError {
+        ConfigError::FileValue {
+            path: self.path.to_path_buf(),
+            key: self.key(key),
+            message: message.into(),
+        }
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.table.keys().cloned().collect()
+    }
+
+    fn take(&mut self, key: &str, expected: &str) -> Result<Option<Value>, ConfigError> {
+        match self.table.remove(key) {
+            Some(value) if !type_matches(&value, expected) => Err(self.invalid(
+                key,
+                format!("expected {expected}, found {}", value.type_str()),
+            )),
+            value => Ok(value),
+        }
+    }
+
+    fn section(&mut self, key: &str) -> Result<Option<Section<'a>>, ConfigError> {
+        Ok(self.take(key, "table")?.map(|value| {
+            let table = match value {
+                Value::Table(table) => table,
+                _ => unreachable!("type checked by take"),
+            };
+            Section::new(self.path, self.key(key), table)
+        }))
+    }
+
+    fn string(&mut self, key: &str) -> Result<Option<String>, ConfigError> {
+        Ok(self
+            .take(key, "string")?
+            .and_then(|v| v.as_str().map(String::from)))
+    }
+
+    fn bool(&mut self, key: &str) -> Result<Option<bool>, ConfigError> {
+        Ok(self.take(key, "boolean")?.and_then(|v| v.as_bool()))
+    }
+
+    fn strings(&mut self, key: &str) -> Result<Option<Vec<String>>, ConfigError> {
+        let Some(Value::Array(items)) = self.take(key, "array")? else {
+            return Ok(None);
+        };
+        items
+            .into_iter()
+            .enumerate()
+            .map(|(i, item)| match item {
+                Value::String(s) => Ok(s),
+                other => Err(self.invalid(
+                    &format!("{key}[{i}]"),
+                    format!("expected string, found {}", other.type_str()),
+                )),
+            })
+            .collect::<Result<_, _>>()
+            .map(Some)
+    }
+
+    /// A non-negative integer that fits the target type
+    fn integer<T: TryFrom<i64>>(&mut self, key: &str) -> Result<Option<T>, ConfigError> {
+        let Some(value) = self.take(key, "integer")?.and_then(|v| v.as_integer()) else {
+            return Ok(None);
+        };
+        T::try_from(value)
+            .map(Some)
+            .map_err(|_| self.invalid(key, format!("{value} is out of range")))
+    }
+
+    /// An integer that must be greater than 0
+    fn positive<T: TryFrom<i64>>(&mut self, key: &str) -> Result<Option<T>, ConfigError> {
+        if let Some(Value::Integer(0)) = self.table.get(key) {
+            return Err(self.invalid(key, "must be greater than 0"));
+        }
+        self.integer(key)
+    }
+
+    /// A string parsed with `FromStr`, such as a restart policy
+    fn parsed<T: std::str::FromStr<Err = String>>(
+        &mut self,
+        key: &str,
+    ) -> Result<Option<T>, ConfigError> {
+        self.string(key)?
+            .map(|s| {
+                s.parse()
+                    .map_err(|message: String| self.invalid(key, message))
+            })
+            .transpose()
+    }
+
+    /// An array of `{ pattern, secs }` tables, in the order they are tried
+    fn tool_timeouts(&mut self, key: &str) -> Result<Option<Vec<ToolTimeout>>, ConfigError> {
+        let Some(Value::Array(items)) = self.take(key, "array")? else {
+            return Ok(None);
+        };
+        let mut timeouts = Vec::with_capacity(items.len());
+        for (i, item) in items.into_iter().enumerate() {
+            let Value::Table(table) = item else {
+                return Err(self.invalid(
+                    &format!("{key}[{i}]"),
+                    format!("expected table, found {}", item.type_str()),
+                ));
+            };
+            let mut entry = Section::new(self.path, self.key(&format!("{key}[{i}]")), table);
+            let pattern = entry
+                .string("pattern")?
+                .ok_or_else(|| entry.invalid("pattern", "missing"))?;
+            let secs = entry
+                .positive("secs")?
+                .ok_or_else(|| entry.invalid("secs", "missing"))?;
+            entry.finish()?;
+            timeouts.push(ToolTimeout { pattern, secs });
+        }
+        Ok(Some(timeouts))
+    }
+
+    /// An array of entry type names such as `"request"`
+    fn entry_types(&mut self, key: &str) -> Result<Option<Vec<LogEntryType>>, ConfigError> {
+        let Some(names) = self.strings(key)? else {
+            return Ok(None);
+        };
+        names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                name.parse()
+                    .map_err(|message: String| self.invalid(&format!("{key}[{i}]"), message))
+            })
+            .collect::<Result<_, _>>()
+            .map(Some)
+    }
+
+    /// An array of `{ type, path | address, entry_types }` tables
+    fn sinks(&mut self, key: &str) -> Result<Option<Vec<SinkConfig>>, ConfigError> {
+        let Some(Value::Array(items)) = self.take(key, "array")? else {
+            return Ok(None);
+        };
+        let mut sinks = Vec::with_capacity(items.len());
+        for (i, item) in items.into_iter().enumerate() {
+            let Value::Table(table) = item else {
+                return Err(self.invalid(
+                    &format!("{key}[{i}]"),
+                    format!("expected table, found {}", item.type_str()),
+                ));
+            };
+            let mut entry = Section::new(self.path, self.key(&format!("{key}[{i}]")), table);
+            let kind = entry
+                .string("type")?
+                .ok_or_else(|| entry.invalid("type", "missing"))?;
+            let target_key = if kind == "tcp" { "address" } else { "path" };
+            let target = entry.string(target_key)?;
+            let entry_types = entry.entry_types("entry_types")?.unwrap_or_default();
+            // Leftover keys, such as a path given to a TCP sink, are reported first
+            let sink = SinkConfig::new(&kind, target, entry_types)
+                .map_err(|message| entry.invalid("type", message));
+            entry.finish()?;
+            sinks.push(sink?);
+        }
+        Ok(Some(sinks))
+    }
+
+    /// Fail on the first key that has not been taken
+    fn finish(self) -> Result<(), ConfigError> {
+        match self.table.keys().next() {
+            Some(key) => Err(ConfigError::UnknownKey {
+                path: self.path.to_path_buf(),
+                key: self.key(key),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Whether a dotted key such as `wrappee.restart.max_attempts` is present
+fn has_key(table: &Table, key: &str) -> bool {
+    match key.split_once('.') {
+        Some((section, rest)) => table
+            .get(section)
+            .and_then(Value::as_table)
+            .is_some_and(|table| has_key(table, rest)),
+        None => table.contains_key(key),
+    }
+}
+
+fn type_matches(value: &Value, expected: &str) -> bool {
+    matches!(
+        (value, expected),
+        (Value::String(_), "string")
+            | (Value::Integer(_), "integer")
+            | (Value::Boolean(_), "boolean")
+            | (Value::Array(_), "array")
+            | (Value::Table(_), "table")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{InstructionsMode, RestartPolicy};
+
+    fn load(text: &str) -> Result<Config, ConfigError> {
+        let path = std::env::temp_dir().join(format!(
+            "wrap-mcp-config-{}-{:?}.toml",
+            std::process::id(),
+            std::thread::current().id()
+        ));
+        std::fs::write(&path, text).unwrap();
+        let mut config = Config::default();
+        let result = config.apply_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        result.map(|_| config)
+    }
+
+    fn error_key(result: Result<Config, ConfigError>) -> String {
+        match result {
+            Err(ConfigError::FileValue { key, .. } | ConfigError::UnknownKey { key, .. }) => key,
+            other => panic!("expected a key error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_load_full_file() {
+        let config = load(
+            r#"
+            [transport]
+            type = "http"
+            port = 0
+            path = "/"
+
+            [log]
+            size = 50
+            progress = true
+            raw = true
+            db = "/var/log/wrap-mcp/log.db"
+            entry_types = ["request", "response"]
+
+            [[log.sinks]]
+            type = "unix"
+            path = "/run/collector.sock"
+            entry_types = ["error"]
+
+            [log.persist]
+            dir = "/var/log/wrap-mcp"
+            retain = 3
+
+            [wrappee]
+            command = "my-server"
+            args = ["--port", "8080"]
+            cwd = "/tmp"
+            env = { API_KEY = "secret" }
+            watch = true
+            tool_timeout = 60
+            tool_timeouts = [{ pattern = "build_*", secs = 600 }]
+            instructions = "proxy"
+
+            [wrappee.restart]
+            policy = "never"
+
+            [wrappee.health]
+            ping_interval = 0
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.transport.transport, "http");
+        assert_eq!(config.transport.port, 0);
+        assert_eq!(config.transport.path, "/");
+        assert_eq!(config.log.log_size, 50);
+        assert!(config.log.log_progress);
+        assert!(config.log.log_raw);
+        assert_eq!(
+            config.log.persist.dir,
+            Some(PathBuf::from("/var/log/wrap-mcp"))
+        );
+        assert_eq!(config.log.persist.retain, 3);
+        assert_eq!(
+            config.log.db,
+            Some(PathBuf::from("/var/log/wrap-mcp/log.db"))
+        );
+        assert_eq!(
+            config.log.entry_types,
+            vec![LogEntryType::Request, LogEntryType::Response]
+        );
+        assert_eq!(
+            config.log.sinks,
+            vec![SinkConfig {
+                target: crate::config::SinkTarget::Unix(PathBuf::from("/run/collector.sock")),
+                entry_types: vec![LogEntryType::Error],
+            }]
+        );
+        assert_eq!(config.launch.command.as_deref(), Some("my-server"));
+        assert_eq!(config.launch.args, vec!["--port", "8080"]);
+        assert_eq!(config.launch.cwd, Some(PathBuf::from("/tmp")));
+        assert_eq!(config.launch.env["API_KEY"], "secret");
+        assert!(config.launch.watch_binary);
+        assert_eq!(config.wrappee.tool_timeout("build_all").as_secs(), 600);
+        assert_eq!(config.wrappee.tool_timeout("echo").as_secs(), 60);
+        assert_eq!(config.wrappee.instructions_mode, InstructionsMode::Proxy);
+        assert_eq!(config.wrappee.restart.policy, RestartPolicy::Never);
+        assert_eq!(config.wrappee.health.interval_secs, 0);
+    }
+
+    #[test]
+    fn test_errors_name_the_key() {
+        assert_eq!(error_key(load("[log]\nsize = 0")), "log.size");
+        assert_eq!(error_key(load("[log]\nsize = \"big\"")), "log.size");
+        assert_eq!(error_key(load("[log]\nlevel = \"info\"")), "log.level");
+        assert_eq!(error_key(load("verbose = true")), "verbose");
+        assert_eq!(
+            error_key(load("[log.persist]\nretain = 0")),
+            "log.persist.retain"
+        );
+        assert_eq!(
+            error_key(load("[log]\nentry_types = [\"request\", \"reply\"]")),
+            "log.entry_types[1]"
+        );
+        assert_eq!(
+            error_key(load("[[log.sinks]]\ntype = \"tcp\"\npath = \"/tmp/x\"")),
+            "log.sinks[0].path"
+        );
+        assert_eq!(
+            error_key(load("[wrappee]\nreplay_unmatched = \"closest\"")),
+            "wrappee.replay_unmatched"
+        );
+        assert_eq!(
+            error_key(load("[transport]\nport = 70000")),
+            "transport.port"
+        );
+        assert_eq!(
+            error_key(load("[wrappee.restart]\npolicy = \"sometimes\"")),
+            "wrappee.restart.policy"
+        );
+        assert_eq!(
+            error_key(load("[wrappee]\nargs = [\"a\", 1]")),
+            "wrappee.args[1]"
+        );
+        assert_eq!(
+            error_key(load("[wrappee]\ntool_timeouts = [{ pattern = \"x\" }]")),
+            "wrappee.tool_timeouts[0].secs"
+        );
+        assert_eq!(
+            error_key(load("[wrappee]\nprotocol_version = \"latest\"")),
+            "wrappee.protocol_version"
+        );
+    }
+
+    #[test]
+    fn test_syntax_error() {
+        assert!(matches!(
+            load("[log\nsize = 1"),
+            Err(ConfigError::FileSyntax { .. })
+        ));
+    }
+}
@@ -0,0 +1,1504 @@
+mod file;
+
+use crate::logging::LogEntryType;
+use crate::wrappee::WrappeeTarget;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Invalid value for {var}: {message}")]
+    InvalidValue { var: String, message: String },
+
+    #[error("Failed to read config file {}: {source}", path.display())]
+    ReadFile {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Failed to parse config file {}: {source}", path.display())]
+    FileSyntax {
+        path: PathBuf,
+        #[source]
+        source: Box<toml::de::Error>,
+    },
+
+    #[error("Invalid value for `{key}` in {}: {message}", path.display())]
+    FileValue {
+        path: PathBuf,
+        key: String,
+        message: String,
+    },
+
+    #[error("Unknown key `{key}` in {}", path.display())]
+    UnknownKey { path: PathBuf, key: String },
+
+    #[error("Failed to parse {var} as {expected_type}: {source}")]
+    ParseError {
+        var: String,
+        expected_type: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+/// Configuration for logging functionality
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    pub log_size: usize,
+    pub log_colors: bool,
+    pub log_progress: bool,
+    /// Capture every JSON-RPC frame exchanged with the client and the wrappee
+    pub log_raw: bool,
+    pub rust_log: String,
+    pub persist: PersistConfig,
+    /// SQLite database to keep entries in instead of memory, so that large logs
+    /// stay quick to query
+    pub db: Option<PathBuf>,
+    /// Entry types kept in the log (and persisted); all of them when empty
+    pub entry_types: Vec<LogEntryType>,
+    /// Further destinations every entry is sent to
+    pub sinks: Vec<SinkConfig>,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            log_size: 1000,
+            log_colors: false,
+            log_progress: false,
+            log_raw: false,
+            rust_log: "info".to_string(),
+            persist: PersistConfig::default(),
+            db: None,
+            entry_types: Vec::new(),
+            sinks: Vec::new(),
+        }
+    }
+}
+
+/// Configuration for keeping log entries on disk as JSON Lines segments
+#[derive(Debug, Clone)]
+pub struct PersistConfig {
+    /// Directory the segments are written to; nothing is persisted when not set
+    pub dir: Option<PathBuf>,
+    /// Size in bytes after which a new segment is started
+    pub rotate_bytes: u64,
+    /// Age in seconds after which a new segment is started, 0 to rotate by size only
+    pub rotate_age_secs: u64,
+    /// Number of segments kept, including the one being written
+    pub retain: usize,
+    /// Whether the most recent entries are loaded back into memory at startup
+    pub reload: bool,
+}
+
+impl Default for PersistConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            rotate_bytes: 10 * 1024 * 1024,
+            rotate_age_secs: 0,
+            retain: 10,
+            reload: true,
+        }
+    }
+}
+
+/// Where a log sink sends entries, each as one line of JSON
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SinkTarget {
+    /// Appended to a file
+    File(PathBuf),
+    /// Written to Wrap-MCP's own tracing output on stderr
+    Stderr,
+    /// Streamed to a TCP listener at `host:port`
+    Tcp(String),
+    /// Streamed to a listener on a Unix domain socket
+    Unix(PathBuf),
+}
+
+impl std::fmt::Display for SinkTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SinkTarget::File(path) => write!(f, "file:{}", path.display()),
+            SinkTarget::Stderr => write!(f, "stderr"),
+            SinkTarget::Tcp(address) => write!(f, "tcp:{address}"),
+            SinkTarget::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// A destination log entries are fanned out to, optionally limited to some entry types
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SinkConfig {
+    pub target: SinkTarget,
+    /// Entry types sent to the sink; all of them when empty
+    pub entry_types: Vec<LogEntryType>,
+}
+
+impl SinkConfig {
+    /// Build a sink from its kind (`file`, `stderr`, `tcp` or `unix`) and, for all
+    /// but `stderr`, its path or address
+    pub fn new(
+        kind: &str,
+        target: Option<String>,
+        entry_types: Vec<LogEntryType>,
+    ) -> Result<Self, String> {
+        let target = match (kind, target) {
+            ("stderr", None) => SinkTarget::Stderr,
+            ("stderr", Some(_)) => return Err("stderr sink takes no target".to_string()),
+            ("file" | "tcp" | "unix", None) => return Err(format!("{kind} sink needs a target")),
+            ("file", Some(path)) => SinkTarget::File(PathBuf::from(path)),
+            ("tcp", Some(address)) => SinkTarget::Tcp(address),
+            ("unix", Some(path)) => SinkTarget::Unix(PathBuf::from(path)),
+            _ => {
+                return Err(format!(
+                    "unknown sink '{kind}' (expected file, stderr, tcp or unix)"
+                ));
+            }
+        };
+        Ok(Self {
+            target,
+            entry_types,
+        })
+    }
+}
+
+impl std::str::FromStr for SinkConfig {
+    type Err = String;
+
+    /// Parse `kind[:target][@type,type...]`, e.g. `tcp:127.0.0.1:9000@request,response`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (sink, entry_types) = match s.rsplit_once('@') {
+            Some((sink, types)) => (sink, parse_entry_types(types)?),
+            None => (s, Vec::new()),
+        };
+        let (kind, target) = match sink.split_once(':') {
+            Some((kind, target)) => (kind, Some(target.to_string())),
+            None => (sink, None),
+        };
+        Self::new(kind.trim(), target, entry_types).map_err(|e| format!("'{s}': {e}"))
+    }
+}
+
+/// Parse sinks separated by semicolons, e.g. `stderr@error;file:/tmp/wrap-mcp.jsonl`
+pub fn parse_log_sinks(s: &str) -> Result<Vec<SinkConfig>, String> {
+    s.split(';')
+        .map(str::trim)
+        .filter(|sink| !sink.is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// Parse entry types separated by commas, e.g. `request,response,error`
+pub fn parse_entry_types(s: &str) -> Result<Vec<LogEntryType>, String> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|entry_type| !entry_type.is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// MCP protocol versions Wrap-MCP can speak, newest first
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// Check that a protocol version has the `YYYY-MM-DD` form used by MCP
+pub fn is_valid_protocol_version(version: &str) -> bool {
+    version.len() == 10 && chrono::NaiveDate::parse_from_str(version, "%Y-%m-%d").is_ok()
+}
+
+/// Timeout for the tools whose names match a glob pattern (`*` and `?` wildcards)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolTimeout {
+    pub pattern: String,
+    pub secs: u64,
+}
+
+/// Parse `pattern=secs` overrides separated by commas, e.g. `build_*=300,ping=5`
+pub fn parse_tool_timeouts(s: &str) -> Result<Vec<ToolTimeout>, String> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(parse_tool_timeout)
+        .collect()
+}
+
+/// Parse a single `pattern=secs` override
+pub fn parse_tool_timeout(entry: &str) -> Result<ToolTimeout, String> {
+    let (pattern, secs) = entry
+        .split_once('=')
+        .ok_or_else(|| format!("'{entry}' is not of the form pattern=seconds"))?;
+    let secs: u64 = secs
+        .trim()
+        .parse()
+        .map_err(|e| format!("invalid timeout in '{entry}': {e}"))?;
+    if secs == 0 {
+        return Err(format!("timeout in '{entry}' must be greater than 0"));
+    }
+    Ok(ToolTimeout {
+        pattern: pattern.trim().to_string(),
+        secs,
+    })
+}
+
+/// Parse a protocol version of the form `YYYY-MM-DD`
+pub fn parse_protocol_version(version: &str) -> Result<String, String> {
+    if is_valid_protocol_version(version) {
+        Ok(version.to_string())
+    } else {
+        Err(format!(
+            "'{version}' is not a protocol version of the form YYYY-MM-DD"
+        ))
+    }
+}
+
+/// Match a name against a glob pattern where `*` matches any run of characters
+/// and `?` a single character
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` and the name position it currently absorbs up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, absorbed)) => {
+                    p = star + 1;
+                    n = absorbed + 1;
+                    backtrack = Some((star, n));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// How the wrappee's instructions are presented to the client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InstructionsMode {
+    /// Only the wrappee's instructions
+    Passthrough,
+    /// The wrappee's instructions followed by a note about the proxy
+    #[default]
+    Append,
+    /// Only the proxy's own instructions
+    Proxy,
+}
+
+impl std::str::FromStr for InstructionsMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "passthrough" => Ok(InstructionsMode::Passthrough),
+            "append" => Ok(InstructionsMode::Append),
+            "proxy" => Ok(InstructionsMode::Proxy),
+            _ => Err(format!(
+                "unknown mode '{s}' (expected passthrough, append or proxy)"
+            )),
+        }
+    }
+}
+
+/// When a wrappee that went away on its own is restarted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RestartPolicy {
+    /// Never restart automatically
+    Never,
+    /// Restart unless the wrappee exited with code 0
+    #[default]
+    OnFailure,
+    /// Always restart
+    Always,
+}
+
+impl std::str::FromStr for RestartPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "never" => Ok(RestartPolicy::Never),
+            "on-failure" => Ok(RestartPolicy::OnFailure),
+            "always" => Ok(RestartPolicy::Always),
+            _ => Err(format!(
+                "unknown policy '{s}' (expected never, on-failure or always)"
+            )),
+        }
+    }
+}
+
+/// Configuration for restarting a wrappee after it exits
+#[derive(Debug, Clone)]
+pub struct RestartConfig {
+    pub policy: RestartPolicy,
+    pub max_attempts: u32,
+    pub backoff_ms: u64,
+    pub backoff_max_ms: u64,
+}
+
+impl Default for RestartConfig {
+    fn default() -> Self {
+        Self {
+            policy: RestartPolicy::default(),
+            max_attempts: 5,
+            backoff_ms: 500,
+            backoff_max_ms: 30_000,
+        }
+    }
+}
+
+impl RestartConfig {
+    /// Delay before the given restart attempt (1-based), doubling up to the maximum
+    pub fn backoff(&self, attempt: u32) -> std::time::Duration {
+        let factor = 2u64.saturating_pow(attempt.saturating_sub(1));
+        let delay = self
+            .backoff_ms
+            .saturating_mul(factor)
+            .min(self.backoff_max_ms);
+        std::time::Duration::from_millis(delay)
+    }
+}
+
+/// Configuration for pinging the wrappee to detect hangs
+#[derive(Debug, Clone)]
+pub struct HealthConfig {
+    /// Seconds between pings (also the time a ping may take); 0 disables pinging
+    pub interval_secs: u64,
+    /// Consecutive failed pings after which the wrappee is considered unhealthy
+    pub failure_threshold: u32,
+    /// Whether to restart a wrappee once it is unhealthy
+    pub restart: bool,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 30,
+            failure_threshold: 3,
+            restart: false,
+        }
+    }
+}
+
+/// Configuration for wrappee process management
+#[derive(Debug, Clone)]
+pub struct WrappeeConfig {
+    pub tool_timeout_secs: u64,
+    /// Per-tool overrides of `tool_timeout_secs`; the first matching pattern wins
+    pub tool_timeouts: Vec<ToolTimeout>,
+    /// Timeout for connecting to and initializing the wrappee
+    pub startup_timeout_secs: u64,
+    /// Timeout for listing the wrappee's tools
+    pub discovery_timeout_secs: u64,
+    pub protocol_version: String,
+    pub max_concurrency: usize,
+    pub instructions_mode: InstructionsMode,
+    pub passthrough_server_info: bool,
+    pub restart: RestartConfig,
+    pub shutdown_grace_ms: u64,
+    pub ready_timeout_secs: u64,
+    pub health: HealthConfig,
+}
+
+impl Default for WrappeeConfig {
+    fn default() -> Self {
+        Self {
+            tool_timeout_secs: 30,
+            tool_timeouts: Vec::new(),
+            startup_timeout_secs: 30,
+            discovery_timeout_secs: 30,
+            protocol_version: "2025-03-26".to_string(),
+            max_concurrency: 16,
+            instructions_mode: InstructionsMode::default(),
+            passthrough_server_info: false,
+            restart: RestartConfig::default(),
+            shutdown_grace_ms: 2000,
+            ready_timeout_secs: 30,
+            health: HealthConfig::default(),
+        }
+    }
+}
+
+impl WrappeeConfig {
+    /// Timeout for calls of the given tool
+    pub fn tool_timeout(&self, name: &str) -> std::time::Duration {
+        let secs = self
+            .tool_timeouts
+            .iter()
+            .find(|t| glob_match(&t.pattern, name))
+            .map_or(self.tool_timeout_secs, |t| t.secs);
+        std::time::Duration::from_secs(secs)
+    }
+}
+
+/// Configuration for transport layer
+#[derive(Debug, Clone)]
+pub struct TransportConfig {
+    pub transport: String,
+    /// Host the HTTP transport binds to
+    pub host: String,
+    /// Port the HTTP transport binds to, 0 to let the OS pick one
+    pub port: u16,
+    /// Path the MCP endpoint is mounted at
+    pub path: String,
+    /// Unix domain socket the HTTP transport binds to instead of host and port
+    pub unix_socket: Option<PathBuf>,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        Self {
+            transport: "stdio".to_string(),
+            host: "127.0.0.1".to_string(),
+            port: 8000,
+            path: "/mcp".to_string(),
+            unix_socket: None,
+        }
+    }
+}
+
+/// Check that an HTTP mount path is absolute
+pub fn parse_http_path(path: &str) -> Result<String, String> {
+    if path.starts_with('/') {
+        Ok(path.to_string())
+    } else {
+        Err(format!("'{path}' must start with '/'"))
+    }
+}
+
+/// How a replayed wrappee answers a tool call that was not recorded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnmatchedCall {
+    /// Fail the call
+    #[default]
+    Error,
+    /// Answer with a recorded response of the same tool, whatever its arguments
+    SameTool,
+    /// Answer with an empty successful result
+    Empty,
+}
+
+impl std::str::FromStr for UnmatchedCall {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "error" => Ok(UnmatchedCall::Error),
+            "same-tool" => Ok(UnmatchedCall::SameTool),
+            "empty" => Ok(UnmatchedCall::Empty),
+            _ => Err(format!(
+                "unknown mode '{s}' (expected error, same-tool or empty)"
+            )),
+        }
+    }
+}
+
+/// What to run as the wrappee and how to launch it
+#[derive(Debug, Clone, Default)]
+pub struct LaunchConfig {
+    /// Command to spawn, or URL of a wrappee to connect to
+    pub command: Option<String>,
+    pub args: Vec<String>,
+    /// Variables added to the environment of a spawned wrappee
+    pub env: BTreeMap<String, String>,
+    /// Working directory of a spawned wrappee
+    pub cwd: Option<PathBuf>,
+    pub preserve_ansi: bool,
+    pub watch_binary: bool,
+    pub legacy_sse: bool,
+    /// Recorded log to replay instead of running a wrappee
+    pub replay: Option<PathBuf>,
+    pub replay_unmatched: UnmatchedCall,
+}
+
+impl LaunchConfig {
+    /// Where the wrappee lives: a recording is replayed, a URL is connected to, a
+    /// command is spawned. `None` when no wrappee was given.
+    pub fn target(&self) -> Option<WrappeeTarget> {
+        if let Some(path) = &self.replay {
+            if let Some(command) = &self.command {
+                tracing::warn!("Replaying {} instead of running {command}", path.display());
+            }
+            return Some(WrappeeTarget::Replay {
+                path: path.clone(),
+                unmatched: self.replay_unmatched,
+            });
+        }
+
+        let command = self.command.clone()?;
+        let mut target = WrappeeTarget::from_command(
+            command,
+            self.args.clone(),
+            !self.preserve_ansi,
+            self.legacy_sse,
+        );
+        if let WrappeeTarget::Process { env, cwd, .. } = &mut target {
+            env.clone_from(&self.env);
+            cwd.clone_from(&self.cwd);
+        }
+        Some(target)
+    }
+}
+
+/// Where a setting got its value from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Env,
+    File(PathBuf),
+    CommandLine,
+}
+
+/// A setting checked by `Config::validate`, under each of the names it can be given by
+struct CheckedSetting {
+    /// Dotted config file key, which also identifies the setting
+    key: &'static str,
+    env: Option<&'static str>,
+    flag: &'static str,
+}
+
+const CHECKED_SETTINGS: &[CheckedSetting] = &[
+    CheckedSetting {
+        key: "wrappee.command",
+        env: None,
+        flag: "COMMAND",
+    },
+    CheckedSetting {
+        key: "transport.path",
+        env: Some("WRAP_MCP_PATH"),
+        flag: "--path",
+    },
+    CheckedSetting {
+        key: "wrappee.tool_timeout",
+        env: Some("WRAP_MCP_TOOL_TIMEOUT"),
+        flag: "--tool-timeout",
+    },
+    CheckedSetting {
+        key: "wrappee.startup_timeout",
+        env: Some("WRAP_MCP_STARTUP_TIMEOUT"),
+        flag: "--startup-timeout",
+    },
+    CheckedSetting {
+        key: "wrappee.discovery_timeout",
+        env: Some("WRAP_MCP_DISCOVERY_TIMEOUT"),
+        flag: "--discovery-timeout",
+    },
+    CheckedSetting {
+        key: "wrappee.protocol_version",
+        env: Some("WRAP_MCP_PROTOCOL_VERSION"),
+        flag: "--protocol-version",
+    },
+    CheckedSetting {
+        key: "wrappee.max_concurrency",
+        env: Some("WRAP_MCP_MAX_CONCURRENCY"),
+        flag: "--max-concurrency",
+    },
+    CheckedSetting {
+        key: "wrappee.restart.max_attempts",
+        env: Some("WRAP_MCP_RESTART_MAX_ATTEMPTS"),
+        flag: "--restart-max-attempts",
+    },
+    CheckedSetting {
+        key: "wrappee.health.ping_failures",
+        env: Some("WRAP_MCP_PING_FAILURES"),
+        flag: "--ping-failures",
+    },
+    CheckedSetting {
+        key: "log.size",
+        env: Some("WRAP_MCP_LOGSIZE"),
+        flag: "--log-size",
+    },
+    CheckedSetting {
+        key: "log.persist.rotate_bytes",
+        env: Some("WRAP_MCP_LOG_ROTATE_BYTES"),
+        flag: "--log-rotate-bytes",
+    },
+    CheckedSetting {
+        key: "log.persist.retain",
+        env: Some("WRAP_MCP_LOG_RETAIN"),
+        flag: "--log-retain",
+    },
+];
+
+/// Main configuration container
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub log: LogConfig,
+    pub wrappee: WrappeeConfig,
+    pub transport: TransportConfig,
+    pub launch: LaunchConfig,
+    /// Where the settings checked by `validate` got their values, by config file key
+    pub(crate) sources: BTreeMap<&'static str, Source>,
+}
+
+impl Config {
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::load(None)
+    }
+
+    /// Load the configuration, letting environment variables override the
+    /// config file (if any) and the config file override the defaults
+    pub fn load(file: Option<&Path>) -> Result<Self, ConfigError> {
+        let config = Self::read(file)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Like `load`, but leave checking the combined settings to `validate`, so
+    /// that further overrides such as the command line can be applied first
+    pub fn read(file: Option<&Path>) -> Result<Self, ConfigError> {
+        let mut config = Config::default();
+        if let Some(path) = file {
+            config.apply_file(path)?;
+        }
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    /// Record that the settings checked by `validate` whose flags were `given`
+    /// got their values from the command line
+    pub(crate) fn set_command_line_sources(&mut self, given: impl Fn(&str) -> bool) {
+        for setting in CHECKED_SETTINGS {
+            if given(setting.flag) {
+                self.set_source(setting.key, Source::CommandLine);
+            }
+        }
+    }
+
+    /// Record where a setting checked by `validate` got its value, by config file key
+    pub(crate) fn set_source(&mut self, key: &'static str, source: Source) {
+        self.sources.insert(key, source);
+    }
+
+    /// Override settings with the `WRAP_MCP_*` environment variables that are set
+    fn apply_env(&mut self) -> Result<(), ConfigError> {
+        fn set<T>(target: &mut T, value: Option<T>) {
+            if let Some(value) = value {
+                *target = value;
+            }
+        }
+
+        let transport = &mut self.transport;
+        set(&mut transport.transport, env_var("WRAP_MCP_TRANSPORT"));
+        set(&mut transport.host, env_var("WRAP_MCP_HOST"));
+        set(&mut transport.port, env_parse("WRAP_MCP_PORT")?);
+        set(&mut transport.path, env_var("WRAP_MCP_PATH"));
+        if let Some(socket) = env_var("WRAP_MCP_UNIX_SOCKET") {
+            transport.unix_socket = Some(PathBuf::from(socket));
+        }
+
+        let log = &mut self.log;
+        set(&mut log.log_colors, env_bool("WRAP_MCP_LOG_COLORS"));
+        set(&mut log.log_progress, env_bool("WRAP_MCP_LOG_PROGRESS"));
+        set(&mut log.log_raw, env_bool("WRAP_MCP_LOG_RAW"));
+        set(&mut log.log_size, env_parse("WRAP_MCP_LOGSIZE")?);
+        if let Some(dir) = env_var("WRAP_MCP_LOG_DIR") {
+            log.persist.dir = Some(PathBuf::from(dir));
+        }
+        set(
+            &mut log.persist.rotate_bytes,
+            env_parse("WRAP_MCP_LOG_ROTATE_BYTES")?,
+        );
+        set(
+            &mut log.persist.rotate_age_secs,
+            env_parse("WRAP_MCP_LOG_ROTATE_AGE")?,
+        );
+        set(&mut log.persist.retain, env_parse("WRAP_MCP_LOG_RETAIN")?);
+        set(&mut log.persist.reload, env_bool("WRAP_MCP_LOG_RELOAD"));
+        if let Some(db) = env_var("WRAP_MCP_LOG_DB") {
+            log.db = Some(PathBuf::from(db));
+        }
+        set(
+            &mut log.entry_types,
+            env_with("WRAP_MCP_LOG_ENTRY_TYPES", parse_entry_types)?,
+        );
+        set(
+            &mut log.sinks,
+            env_with("WRAP_MCP_LOG_SINKS", parse_log_sinks)?,
+        );
+        set(&mut log.rust_log, env_var("RUST_LOG"));
+
+        let wrappee = &mut self.wrappee;
+        set(
+            &mut wrappee.tool_timeout_secs,
+            env_parse("WRAP_MCP_TOOL_TIMEOUT")?,
+        );
+        set(
+            &mut wrappee.tool_timeouts,
+            env_with("WRAP_MCP_TOOL_TIMEOUTS", parse_tool_timeouts)?,
+        );
+        set(
+            &mut wrappee.startup_timeout_secs,
+            env_parse("WRAP_MCP_STARTUP_TIMEOUT")?,
+        );
+        set(
+            &mut wrappee.discovery_timeout_secs,
+            env_parse("WRAP_MCP_DISCOVERY_TIMEOUT")?,
+        );
+        set(
+            &mut wrappee.protocol_version,
+            env_var("WRAP_MCP_PROTOCOL_VERSION"),
+        );
+        set(
+            &mut wrappee.max_concurrency,
+            env_parse("WRAP_MCP_MAX_CONCURRENCY")?,
+        );
+        set(
+            &mut wrappee.instructions_mode,
+            env_with("WRAP_MCP_INSTRUCTIONS", str::parse)?,
+        );
+        set(
+            &mut wrappee.passthrough_server_info,
+            env_bool("WRAP_MCP_PASSTHROUGH_SERVER_INFO"),
+        );
+        set(
+            &mut wrappee.shutdown_grace_ms,
+            env_parse("WRAP_MCP_SHUTDOWN_GRACE_MS")?,
+        );
+        set(
+            &mut wrappee.ready_timeout_secs,
+            env_parse("WRAP_MCP_READY_TIMEOUT")?,
+        );
+        set(
+            &mut wrappee.restart.policy,
+            env_with("WRAP_MCP_RESTART", str::parse)?,
+        );
+        set(
+            &mut wrappee.restart.max_attempts,
+            env_parse("WRAP_MCP_RESTART_MAX_ATTEMPTS")?,
+        );
+        set(
+            &mut wrappee.restart.backoff_ms,
+            env_parse("WRAP_MCP_RESTART_BACKOFF_MS")?,
+        );
+        set(
+            &mut wrappee.restart.backoff_max_ms,
+            env_parse("WRAP_MCP_RESTART_BACKOFF_MAX_MS")?,
+        );
+        set(
+            &mut wrappee.health.interval_secs,
+            env_parse("WRAP_MCP_PING_INTERVAL")?,
+        );
+        set(
+            &mut wrappee.health.failure_threshold,
+            env_parse("WRAP_MCP_PING_FAILURES")?,
+        );
+        set(
+            &mut wrappee.health.restart,
+            env_bool("WRAP_MCP_PING_RESTART"),
+        );
+
+        for setting in CHECKED_SETTINGS {
+            if setting
+                .env
+                .is_some_and(|var| std::env::var_os(var).is_some())
+            {
+                self.set_source(setting.key, Source::Env);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check the combined settings, naming where a bad value was given
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        // URL wrappees are reached with a plain HTTP client
+        if self.launch.replay.is_none()
+            && let Some(command) = &self.launch.command
+            && command.starts_with("https://")
+        {
+            return Err(self.invalid(
+                "wrappee.command",
+                format!(
+                    "{command}: https:// wrappees are not supported, use an http:// URL \
+                     (for example through a local TLS-terminating proxy)"
+                ),
+            ));
+        }
+
+        if let Err(message) = parse_http_path(&self.transport.path) {
+            return Err(self.invalid("transport.path", message));
+        }
+
+        if self.wrappee.tool_timeout_secs == 0 {
+            return Err(self.invalid("wrappee.tool_timeout", "timeout must be greater than 0"));
+        }
+
+        if self.wrappee.startup_timeout_secs == 0 {
+            return Err(self.invalid("wrappee.startup_timeout", "timeout must be greater than 0"));
+        }
+
+        if self.wrappee.discovery_timeout_secs == 0 {
+            return Err(self.invalid(
+                "wrappee.discovery_timeout",
+                "timeout must be greater than 0",
+            ));
+        }
+
+        if let Err(message) = parse_protocol_version(&self.wrappee.protocol_version) {
+            return Err(self.invalid("wrappee.protocol_version", message));
+        }
+
+        if self.wrappee.max_concurrency == 0 {
+            return Err(self.invalid(
+                "wrappee.max_concurrency",
+                "max concurrency must be greater than 0",
+            ));
+        }
+
+        if self.wrappee.restart.max_attempts == 0 {
+            return Err(self.invalid(
+                "wrappee.restart.max_attempts",
+                "max attempts must be greater than 0",
+            ));
+        }
+
+        if self.wrappee.health.failure_threshold == 0 {
+            return Err(self.invalid(
+                "wrappee.health.ping_failures",
+                "failure threshold must be greater than 0",
+            ));
+        }
+
+        if self.log.log_size == 0 {
+            return Err(self.invalid("log.size", "log size must be greater than 0"));
+        }
+
+        if self.log.persist.rotate_bytes == 0 {
+            return Err(self.invalid(
+                "log.persist.rotate_bytes",
+                "rotation size must be greater than 0",
+            ));
+        }
+
+        if self.log.persist.retain == 0 {
+            return Err(self.invalid(
+                "log.persist.retain",
+                "retained segment count must be greater than 0",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Error for a bad value of a checked setting, naming the flag, environment
+    /// variable or config file key it was given by
+    fn invalid(&self, key: &str, message: impl Into<String>) -> ConfigError {
+        let message = message.into();
+        let setting = CHECKED_SETTINGS
+            .iter()
+            .find(|setting| setting.key == key)
+            .expect("validated settings are listed in CHECKED_SETTINGS");
+        match self.sources.get(key) {
+            Some(Source::File(path)) => ConfigError::FileValue {
+                path: path.clone(),
+                key: key.to_string(),
+                message,
+            },
+            Some(Source::CommandLine) => ConfigError::InvalidValue {
+                var: setting.flag.to_string(),
+                message,
+            },
+            Some(Source::Env) | None => ConfigError::InvalidValue {
+                var: setting.env.unwrap_or(setting.flag).to_string(),
+                message,
+            },
+        }
+    }
+}
+
+/// Value of an environment variable, if it is set
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// Environment variable parsed as `T`, if it is set
+fn env_parse<T>(name: &str) -> Result<Option<T>, ConfigError>
+where
+    T: std::str::FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    env_var(name)
+        .map(|value| {
+            value.parse().map_err(|e| ConfigError::ParseError {
+                var: name.to_string(),
+                expected_type: std::any::type_name::<T>().to_string(),
+                source: Box::new(e),
+            })
+        })
+        .transpose()
+}
+
+/// Environment variable parsed by a function that explains what is wrong, if it is set
+fn env_with<T>(
+    name: &str,
+    parse: impl FnOnce(&str) -> Result<T, String>,
+) -> Result<Option<T>, ConfigError> {
+    env_var(name)
+        .map(|value| {
+            parse(&value).map_err(|message| ConfigError::InvalidValue {
+                var: name.to_string(),
+                message,
+            })
+        })
+        .transpose()
+}
+
+/// Boolean environment variable: `true` (in any case) or `1` enable, anything else disables
+fn env_bool(name: &str) -> Option<bool> {
+    env_var(name).map(|value| value.eq_ignore_ascii_case("true") || value == "1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(test)]
+    use serial_test::serial;
+
+    #[test]
+    fn test_default_config() {
+        let config = Config::default();
+        assert_eq!(config.transport.transport, "stdio");
+        assert_eq!(config.transport.host, "127.0.0.1");
+        assert_eq!(config.transport.port, 8000);
+        assert_eq!(config.transport.path, "/mcp");
+        assert!(config.transport.unix_socket.is_none());
+        assert!(!config.log.log_colors);
+        assert!(!config.log.log_progress);
+        assert!(!config.log.log_raw);
+        assert_eq!(config.wrappee.tool_timeout_secs, 30);
+        assert_eq!(config.wrappee.protocol_version, "2025-03-26");
+        assert_eq!(config.wrappee.max_concurrency, 16);
+        assert_eq!(config.wrappee.instructions_mode, InstructionsMode::Append);
+        assert!(!config.wrappee.passthrough_server_info);
+        assert_eq!(config.wrappee.restart.policy, RestartPolicy::OnFailure);
+        assert_eq!(config.wrappee.restart.max_attempts, 5);
+        assert_eq!(config.wrappee.shutdown_grace_ms, 2000);
+        assert_eq!(config.wrappee.ready_timeout_secs, 30);
+        assert!(config.wrappee.tool_timeouts.is_empty());
+        assert_eq!(config.wrappee.startup_timeout_secs, 30);
+        assert_eq!(config.wrappee.discovery_timeout_secs, 30);
+        assert_eq!(config.wrappee.health.interval_secs, 30);
+        assert_eq!(config.wrappee.health.failure_threshold, 3);
+        assert!(!config.wrappee.health.restart);
+        assert_eq!(config.log.log_size, 1000);
+        assert_eq!(config.log.rust_log, "info");
+        assert!(config.log.persist.dir.is_none());
+        assert_eq!(config.log.persist.retain, 10);
+        assert!(config.log.persist.reload);
+        assert!(config.log.db.is_none());
+        assert!(config.log.entry_types.is_empty());
+        assert!(config.log.sinks.is_empty());
+    }
+
+    #[test]
+    #[serial]
+    fn test_from_env_with_defaults() {
+        // Store original values and clear env vars
+        let env_vars = [
+            "WRAP_MCP_TRANSPORT",
+            "WRAP_MCP_HOST",
+            "WRAP_MCP_PORT",
+            "WRAP_MCP_PATH",
+            "WRAP_MCP_UNIX_SOCKET",
+            "WRAP_MCP_LOG_COLORS",
+            "WRAP_MCP_LOG_PROGRESS",
+            "WRAP_MCP_LOG_RAW",
+            "WRAP_MCP_TOOL_TIMEOUT",
+            "WRAP_MCP_TOOL_TIMEOUTS",
+            "WRAP_MCP_STARTUP_TIMEOUT",
+            "WRAP_MCP_DISCOVERY_TIMEOUT",
+            "WRAP_MCP_PROTOCOL_VERSION",
+            "WRAP_MCP_LOGSIZE",
+            "WRAP_MCP_MAX_CONCURRENCY",
+            "WRAP_MCP_INSTRUCTIONS",
+            "WRAP_MCP_PASSTHROUGH_SERVER_INFO",
+            "WRAP_MCP_RESTART",
+            "WRAP_MCP_RESTART_MAX_ATTEMPTS",
+            "WRAP_MCP_RESTART_BACKOFF_MS",
+            "WRAP_MCP_RESTART_BACKOFF_MAX_MS",
+            "WRAP_MCP_SHUTDOWN_GRACE_MS",
+            "WRAP_MCP_READY_TIMEOUT",
+            "WRAP_MCP_PING_INTERVAL",
+            "WRAP_MCP_PING_FAILURES",
+            "WRAP_MCP_PING_RESTART",
+            "WRAP_MCP_LOG_DIR",
+            "WRAP_MCP_LOG_ROTATE_BYTES",
+            "WRAP_MCP_LOG_ROTATE_AGE",
+            "WRAP_MCP_LOG_RETAIN",
+            "WRAP_MCP_LOG_RELOAD",
+            "WRAP_MCP_LOG_DB",
+            "WRAP_MCP_LOG_ENTRY_TYPES",
+            "WRAP_MCP_LOG_SINKS",
+            "RUST_LOG",
+        ];
+
+        let original_values: Vec<_> = env_vars
+            .iter()
+            .map(|&var| (var, std::env::var(var).ok()))
+            .collect();
+
+        // Clear all relevant env vars
+        unsafe {
+            for var in &env_vars {
+                std::env::remove_var(var);
+            }
+        }
+
+        let config = Config::from_env().unwrap();
+        assert_eq!(config.transport.transport, "stdio");
+        assert!(!config.log.log_colors);
+        assert_eq!(config.wrappee.tool_timeout_secs, 30);
+        assert_eq!(config.wrappee.protocol_version, "2025-03-26");
+        assert_eq!(config.log.log_size, 1000);
+        assert_eq!(config.log.rust_log, "info");
+
+        // Restore original values
+        unsafe {
+            for (var, value) in original_values {
+                if let Some(v) = value {
+                    std::env::set_var(var, v);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_config_with_custom_values() {
+        // Test by directly constructing Config instead of using environment variables
+        let config = Config {
+            transport: TransportConfig {
+                transport: "tcp".to_string(),
+                port: 0,
+                ..TransportConfig::default()
+            },
+            log: LogConfig {
+                log_colors: true,
+                log_progress: true,
+                log_raw: false,
+                log_size: 500,
+                rust_log: "debug".to_string(),
+                persist: PersistConfig {
+                    dir: Some(PathBuf::from("/var/log/wrap-mcp")),
+                    ..PersistConfig::default()
+                },
+                db: None,
+                entry_types: Vec::new(),
+                sinks: Vec::new(),
+            },
+            wrappee: WrappeeConfig {
+                tool_timeout_secs: 60,
+                tool_timeouts: vec![ToolTimeout {
+                    pattern: "build_*".to_string(),
+                    secs: 600,
+                }],
+                startup_timeout_secs: 10,
+                discovery_timeout_secs: 5,
+                protocol_version: "2024-11-05".to_string(),
+                max_concurrency: 4,
+                instructions_mode: InstructionsMode::Passthrough,
+                passthrough_server_info: true,
+                restart: RestartConfig {
+                    policy: RestartPolicy::Never,
+                    ..RestartConfig::default()
+                },
+                shutdown_grace_ms: 500,
+                ready_timeout_secs: 0,
+                health: HealthConfig {
+                    interval_secs: 0,
+                    ..HealthConfig::default()
+                },
+            },
+            launch: LaunchConfig::default(),
+            sources: BTreeMap::new(),
+        };
+
+        assert_eq!(config.transport.transport, "tcp");
+        assert!(config.log.log_colors);
+        assert!(config.log.log_progress);
+        assert_eq!(config.wrappee.tool_timeout_secs, 60);
+        assert_eq!(config.wrappee.tool_timeout("build_all").as_secs(), 600);
+        assert_eq!(config.wrappee.tool_timeout("test").as_secs(), 60);
+        assert_eq!(config.wrappee.protocol_version, "2024-11-05");
+        assert_eq!(config.wrappee.max_concurrency, 4);
+        assert_eq!(
+            config.wrappee.instructions_mode,
+            InstructionsMode::Passthrough
+        );
+        assert!(config.wrappee.passthrough_server_info);
+        assert_eq!(config.wrappee.restart.policy, RestartPolicy::Never);
+        assert_eq!(config.log.log_size, 500);
+        assert_eq!(config.log.rust_log, "debug");
+    }
+
+    #[test]
+    #[serial]
+    fn test_invalid_timeout() {
+        let original = std::env::var("WRAP_MCP_TOOL_TIMEOUT").ok();
+
+        unsafe {
+            std::env::set_var("WRAP_MCP_TOOL_TIMEOUT", "not_a_number");
+        }
+        let result = Config::from_env();
+        assert!(result.is_err());
+
+        unsafe {
+            std::env::remove_var("WRAP_MCP_TOOL_TIMEOUT");
+            if let Some(v) = original {
+                std::env::set_var("WRAP_MCP_TOOL_TIMEOUT", v);
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_zero_timeout_validation() {
+        let original = std::env::var("WRAP_MCP_TOOL_TIMEOUT").ok();
+
+        unsafe {
+            std::env::set_var("WRAP_MCP_TOOL_TIMEOUT", "0");
+        }
+        let result = Config::from_env();
+        assert!(result.is_err());
+
+        unsafe {
+            std::env::remove_var("WRAP_MCP_TOOL_TIMEOUT");
+            if let Some(v) = original {
+                std::env::set_var("WRAP_MCP_TOOL_TIMEOUT", v);
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_invalid_logsize() {
+        let original = std::env::var("WRAP_MCP_LOGSIZE").ok();
+
+        unsafe {
+            std::env::set_var("WRAP_MCP_LOGSIZE", "not_a_number");
+        }
+        let result = Config::from_env();
+        assert!(result.is_err());
+
+        unsafe {
+            std::env::remove_var("WRAP_MCP_LOGSIZE");
+            if let Some(v) = original {
+                std::env::set_var("WRAP_MCP_LOGSIZE", v);
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_zero_logsize_validation() {
+        let original = std::env::var("WRAP_MCP_LOGSIZE").ok();
+
+        unsafe {
+            std::env::set_var("WRAP_MCP_LOGSIZE", "0");
+        }
+        let result = Config::from_env();
+        assert!(result.is_err());
+
+        unsafe {
+            std::env::remove_var("WRAP_MCP_LOGSIZE");
+            if let Some(v) = original {
+                std::env::set_var("WRAP_MCP_LOGSIZE", v);
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_zero_max_concurrency_validation() {
+        let original = std::env::var("WRAP_MCP_MAX_CONCURRENCY").ok();
+
+        unsafe {
+            std::env::set_var("WRAP_MCP_MAX_CONCURRENCY", "0");
+        }
+        let result = Config::from_env();
+        assert!(result.is_err());
+
+        unsafe {
+            std::env::remove_var("WRAP_MCP_MAX_CONCURRENCY");
+            if let Some(v) = original {
+                std::env::set_var("WRAP_MCP_MAX_CONCURRENCY", v);
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_env_overrides_config_file() {
+        let original = std::env::var("WRAP_MCP_TOOL_TIMEOUT").ok();
+        let path = std::env::temp_dir().join(format!("wrap-mcp-{}.toml", std::process::id()));
+        std::fs::write(&path, "[log]\nsize = 50\n\n[wrappee]\ntool_timeout = 60\n").unwrap();
+
+        unsafe {
+            std::env::set_var("WRAP_MCP_TOOL_TIMEOUT", "90");
+        }
+        let result = Config::load(Some(&path));
+        std::fs::remove_file(&path).unwrap();
+        let config = result.unwrap();
+        assert_eq!(config.wrappee.tool_timeout_secs, 90);
+        assert_eq!(config.log.log_size, 50);
+
+        unsafe {
+            std::env::remove_var("WRAP_MCP_TOOL_TIMEOUT");
+            if let Some(v) = original {
+                std::env::set_var("WRAP_MCP_TOOL_TIMEOUT", v);
+            }
+        }
+    }
+
+    #[test]
+    fn test_validation_names_the_source_of_a_bad_value() {
+        let mut config = Config::default();
+        config.wrappee.max_concurrency = 0;
+
+        let err = config.validate().unwrap_err();
+        assert!(
+            matches!(&err, ConfigError::InvalidValue { var, .. } if var == "WRAP_MCP_MAX_CONCURRENCY")
+        );
+
+        config.set_source("wrappee.max_concurrency", Source::CommandLine);
+        let err = config.validate().unwrap_err();
+        assert!(
+            matches!(&err, ConfigError::InvalidValue { var, .. } if var == "--max-concurrency")
+        );
+
+        let path = PathBuf::from("wrap.toml");
+        config.set_source("wrappee.max_concurrency", Source::File(path.clone()));
+        let err = config.validate().unwrap_err();
+        assert!(matches!(
+            &err,
+            ConfigError::FileValue { path: p, key, .. } if *p == path && key == "wrappee.max_concurrency"
+        ));
+    }
+
+    #[test]
+    fn test_https_wrappee_is_rejected() {
+        let mut config = Config::default();
+        config.launch.command = Some("https://example.com/mcp".to_string());
+        config.set_source("wrappee.command", Source::CommandLine);
+        let err = config.validate().unwrap_err();
+        assert!(matches!(&err, ConfigError::InvalidValue { var, .. } if var == "COMMAND"));
+        assert!(
+            err.to_string()
+                .contains("https:// wrappees are not supported")
+        );
+
+        config.launch.command = Some("http://127.0.0.1:3000/mcp".to_string());
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_missing_config_file() {
+        let result = Config::load(Some(Path::new("/nonexistent/wrap-mcp.toml")));
+        assert!(matches!(result, Err(ConfigError::ReadFile { .. })));
+    }
+
+    #[test]
+    #[serial]
+    fn test_invalid_http_path() {
+        let original = std::env::var("WRAP_MCP_PATH").ok();
+
+        unsafe {
+            std::env::set_var("WRAP_MCP_PATH", "mcp");
+        }
+        let result = Config::from_env();
+        assert!(matches!(result, Err(ConfigError::InvalidValue { .. })));
+
+        unsafe {
+            std::env::remove_var("WRAP_MCP_PATH");
+            if let Some(v) = original {
+                std::env::set_var("WRAP_MCP_PATH", v);
+            }
+        }
+    }
+
+    #[test]
+    fn test_protocol_version_format() {
+        assert!(is_valid_protocol_version("2025-03-26"));
+        assert!(!is_valid_protocol_version("2025.03.26"));
+        assert!(!is_valid_protocol_version("2025-3-26"));
+        assert!(!is_valid_protocol_version("2025-13-01"));
+        assert!(!is_valid_protocol_version("latest"));
+    }
+
+    #[test]
+    #[serial]
+    fn test_invalid_protocol_version() {
+        let original = std::env::var("WRAP_MCP_PROTOCOL_VERSION").ok();
+
+        unsafe {
+            std::env::set_var("WRAP_MCP_PROTOCOL_VERSION", "2025.03.26");
+        }
+        let result = Config::from_env();
+        assert!(matches!(result, Err(ConfigError::InvalidValue { .. })));
+
+        unsafe {
+            std::env::remove_var("WRAP_MCP_PROTOCOL_VERSION");
+            if let Some(v) = original {
+                std::env::set_var("WRAP_MCP_PROTOCOL_VERSION", v);
+            }
+        }
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("echo", "echo"));
+        assert!(!glob_match("echo", "echo2"));
+        assert!(glob_match("build_*", "build_"));
+        assert!(glob_match("build_*", "build_release"));
+        assert!(glob_match("*_slow", "query_slow"));
+        assert!(glob_match("a*b*c", "axxbyyc"));
+        assert!(!glob_match("a*b*c", "axxbyy"));
+        assert!(glob_match("tool_?", "tool_1"));
+        assert!(!glob_match("tool_?", "tool_10"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn test_parse_tool_timeouts() {
+        let timeouts = parse_tool_timeouts("build_*=300, ping=5,").unwrap();
+        assert_eq!(
+            timeouts,
+            vec![
+                ToolTimeout {
+                    pattern: "build_*".to_string(),
+                    secs: 300
+                },
+                ToolTimeout {
+                    pattern: "ping".to_string(),
+                    secs: 5
+                },
+            ]
+        );
+
+        assert!(parse_tool_timeouts("build_*").is_err());
+        assert!(parse_tool_timeouts("build_*=soon").is_err());
+        assert!(parse_tool_timeouts("build_*=0").is_err());
+    }
+
+    #[test]
+    fn test_parse_log_sinks() {
+        let sinks =
+            parse_log_sinks("stderr@error,stderr; tcp:127.0.0.1:9000;file:/tmp/log.jsonl").unwrap();
+        assert_eq!(
+            sinks,
+            vec![
+                SinkConfig {
+                    target: SinkTarget::Stderr,
+                    entry_types: vec![LogEntryType::Error, LogEntryType::Stderr],
+                },
+                SinkConfig {
+                    target: SinkTarget::Tcp("127.0.0.1:9000".to_string()),
+                    entry_types: Vec::new(),
+                },
+                SinkConfig {
+                    target: SinkTarget::File(PathBuf::from("/tmp/log.jsonl")),
+                    entry_types: Vec::new(),
+                },
+            ]
+        );
+
+        assert!(parse_log_sinks("tcp").is_err());
+        assert!(parse_log_sinks("stderr:somewhere").is_err());
+        assert!(parse_log_sinks("syslog").is_err());
+        assert!(parse_log_sinks("unix:/tmp/sock@requests").is_err());
+    }
+
+    #[test]
+    fn test_tool_timeout_first_match_wins() {
+        let config = WrappeeConfig {
+            tool_timeouts: parse_tool_timeouts("build_release=900,build_*=300").unwrap(),
+            ..WrappeeConfig::default()
+        };
+        assert_eq!(config.tool_timeout("build_release").as_secs(), 900);
+        assert_eq!(config.tool_timeout("build_debug").as_secs(), 300);
+        assert_eq!(config.tool_timeout("echo").as_secs(), 30);
+    }
+
+    #[test]
+    fn test_restart_backoff() {
+        let restart = RestartConfig {
+            backoff_ms: 500,
+            backoff_max_ms: 3000,
+            ..RestartConfig::default()
+        };
+        assert_eq!(restart.backoff(1).as_millis(), 500);
+        assert_eq!(restart.backoff(2).as_millis(), 1000);
+        assert_eq!(restart.backoff(3).as_millis(), 2000);
+        assert_eq!(restart.backoff(4).as_millis(), 3000);
+        assert_eq!(restart.backoff(100).as_millis(), 3000);
+    }
+
+    #[test]
+    #[serial]
+    fn test_invalid_restart_policy() {
+        let original = std::env::var("WRAP_MCP_RESTART").ok();
+
+        unsafe {
+            std::env::set_var("WRAP_MCP_RESTART", "sometimes");
+        }
+        let result = Config::from_env();
+        assert!(matches!(result, Err(ConfigError::InvalidValue { .. })));
+
+        unsafe {
+            std::env::remove_var("WRAP_MCP_RESTART");
+            if let Some(v) = original {
+                std::env::set_var("WRAP_MCP_RESTART", v);
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_zero_ping_failures_validation() {
+        let original = std::env::var("WRAP_MCP_PING_FAILURES").ok();
+
+        unsafe {
+            std::env::set_var("WRAP_MCP_PING_FAILURES", "0");
+        }
+        let result = Config::from_env();
+        assert!(matches!(result, Err(ConfigError::InvalidValue { .. })));
+
+        unsafe {
+            std::env::remove_var("WRAP_MCP_PING_FAILURES");
+            if let Some(v) = original {
+                std::env::set_var("WRAP_MCP_PING_FAILURES", v);
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_invalid_instructions_mode() {
+        let original = std::env::var("WRAP_MCP_INSTRUCTIONS").ok();
+
+        unsafe {
+            std::env::set_var("WRAP_MCP_INSTRUCTIONS", "verbatim");
+        }
+        let result = Config::from_env();
+        assert!(matches!(result, Err(ConfigError::InvalidValue { .. })));
+
+        unsafe {
+            std::env::remove_var("WRAP_MCP_INSTRUCTIONS");
+            if let Some(v) = original {
+                std::env::set_var("WRAP_MCP_INSTRUCTIONS", v);
+            }
+        }
+    }
+}
@@ -4,7 +4,8 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::logging::LogFilter;
+use crate::logging::filter::Keyword;
+use crate::logging::{Direction, LogFilter};
 use crate::types::{RequestId, ToolName};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,34 +19,107 @@ pub enum LogEntryContent {
         tool_name: ToolName,
         request_id: RequestId,
         response: Value,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        duration_ms: Option<u64>,
+        /// Prompt fetched, for answers to `prompts/get`; `tool_name` then holds the method
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prompt_name: Option<String>,
     },
     Error {
         tool_name: ToolName,
         request_id: RequestId,
         error: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        duration_ms: Option<u64>,
+        /// Prompt fetched, for answers to `prompts/get`; `tool_name` then holds the method
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prompt_name: Option<String>,
     },
     Stderr {
         message: String,
     },
+    Prompt {
+        prompt_name: String,
+        arguments: Value,
+    },
+    Cancelled {
+        tool_name: ToolName,
+        request_id: RequestId,
+        reason: String,
+    },
+    Progress {
+        tool_name: ToolName,
+        request_id: RequestId,
+        progress: f64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        total: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+    },
+    Log {
+        level: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        logger: Option<String>,
+        data: Value,
+    },
+    Handshake {
+        peer: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        implementation: Option<String>,
+        requested: String,
+        negotiated: String,
+    },
+    Exited {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        code: Option<i32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        signal: Option<i32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        shutdown_stage: Option<String>,
+    },
+    Health {
+        healthy: bool,
+        failures: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    Raw {
+        direction: Direction,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        method: Option<String>,
+        message: Value,
+    },
 }
 
 impl LogEntryContent {
+    /// Name of the tool or prompt the entry is about, which the `tool_name` filter matches
     pub fn tool_name(&self) -> Option<&str> {
         match self {
             LogEntryContent::Request { tool_name, .. } => Some(tool_name.as_str()),
-            LogEntryContent::Response { tool_name, .. } => Some(tool_name.as_str()),
-            LogEntryContent::Error { tool_name, .. } => Some(tool_name.as_str()),
+            LogEntryContent::Response {
+                tool_name,
+                prompt_name,
+                ..
+            }
+            | LogEntryContent::Error {
+                tool_name,
+                prompt_name,
+                ..
+            } => Some(prompt_name.as_deref().unwrap_or(tool_name.as_str())),
             LogEntryContent::Stderr { .. } => None,
+            LogEntryContent::Prompt { prompt_name, .. } => Some(prompt_name.as_str()),
+            LogEntryContent::Cancelled { tool_name, .. } => Some(tool_name.as_str()),
+            LogEntryContent::Progress { tool_name, .. } => Some(tool_name.as_str()),
+            LogEntryContent::Log { .. } => None,
+            LogEntryContent::Handshake { .. } => None,
+            LogEntryContent::Exited { .. } => None,
+            LogEntryContent::Health { .. } => None,
+            LogEntryContent::Raw { .. } => None,
         }
     }
 
     pub fn match_tool_name(&self, name: &str) -> bool {
-        match self {
-            LogEntryContent::Request { tool_name, .. } => tool_name.as_str() == name,
-            LogEntryContent::Response { tool_name, .. } => tool_name.as_str() == name,
-            LogEntryContent::Error { tool_name, .. } => tool_name.as_str() == name,
-            LogEntryContent::Stderr { .. } => false,
-        }
+        self.tool_name() == Some(name)
     }
 
     pub fn match_entry_type(&self, entry_type: &str) -> bool {
@@ -54,6 +128,14 @@ impl LogEntryContent {
             LogEntryContent::Response { .. } => entry_type == "response",
             LogEntryContent::Error { .. } => entry_type == "error",
             LogEntryContent::Stderr { .. } => entry_type == "stderr",
+            LogEntryContent::Prompt { .. } => entry_type == "prompt",
+            LogEntryContent::Cancelled { .. } => entry_type == "cancelled",
+            LogEntryContent::Progress { .. } => entry_type == "progress",
+            LogEntryContent::Log { .. } => entry_type == "log",
+            LogEntryContent::Handshake { .. } => entry_type == "handshake",
+            LogEntryContent::Exited { .. } => entry_type == "exited",
+            LogEntryContent::Health { .. } => entry_type == "health",
+            LogEntryContent::Raw { .. } => entry_type == "raw",
         }
     }
 }
@@ -79,6 +161,7 @@ impl LogEntry {
         tool_name: ToolName,
         request_id: RequestId,
         response: Value,
+        duration_ms: Option<u64>,
     ) -> Self {
         Self {
             id,
@@ -87,6 +170,8 @@ impl LogEntry {
                 tool_name,
                 request_id,
                 response,
+                duration_ms,
+                prompt_name: None,
             },
         }
     }
@@ -95,6 +180,7 @@ impl LogEntry {
         tool_name: ToolName,
         request_id: RequestId,
         error: String,
+        duration_ms: Option<u64>,
     ) -> Self {
         Self {
             id,
@@ -103,6 +189,8 @@ impl LogEntry {
                 tool_name,
                 request_id,
                 error,
+                duration_ms,
+                prompt_name: None,
             },
         }
     }
@@ -113,8 +201,156 @@ impl LogEntry {
             content: LogEntryContent::Stderr { message },
         }
     }
+    pub fn new_prompt(id: RequestId, prompt_name: String, arguments: Value) -> Self {
+        Self {
+            id,
+            timestamp: Utc::now(),
+            content: LogEntryContent::Prompt {
+                prompt_name,
+                arguments,
+            },
+        }
+    }
+    pub fn new_cancelled(
+        id: RequestId,
+        tool_name: ToolName,
+        request_id: RequestId,
+        reason: String,
+    ) -> Self {
+        Self {
+            id,
+            timestamp: Utc::now(),
+            content: LogEntryContent::Cancelled {
+                tool_name,
+                request_id,
+                reason,
+            },
+        }
+    }
+    pub fn new_progress(
+        id: RequestId,
+        tool_name: ToolName,
+        request_id: RequestId,
+        progress: f64,
+        total: Option<f64>,
+        message: Option<String>,
+    ) -> Self {
+        Self {
+            id,
+            timestamp: Utc::now(),
+            content: LogEntryContent::Progress {
+                tool_name,
+                request_id,
+                progress,
+                total,
+                message,
+            },
+        }
+    }
+    pub fn new_log(id: RequestId, level: String, logger: Option<String>, data: Value) -> Self {
+        Self {
+            id,
+            timestamp: Utc::now(),
+            content: LogEntryContent::Log {
+                level,
+                logger,
+                data,
+            },
+        }
+    }
+    pub fn new_handshake(
+        id: RequestId,
+        peer: String,
+        implementation: Option<String>,
+        requested: String,
+        negotiated: String,
+    ) -> Self {
+        Self {
+            id,
+            timestamp: Utc::now(),
+            content: LogEntryContent::Handshake {
+                peer,
+                implementation,
+                requested,
+                negotiated,
+            },
+        }
+    }
+    pub fn new_exited(
+        id: RequestId,
+        code: Option<i32>,
+        signal: Option<i32>,
+        shutdown_stage: Option<String>,
+    ) -> Self {
+        Self {
+            id,
+            timestamp: Utc::now(),
+            content: LogEntryContent::Exited {
+                code,
+                signal,
+                shutdown_stage,
+            },
+        }
+    }
+    pub fn new_health(id: RequestId, healthy: bool, failures: u32, error: Option<String>) -> Self {
+        Self {
+            id,
+            timestamp: Utc::now(),
+            content: LogEntryContent::Health {
+                healthy,
+                failures,
+                error,
+            },
+        }
+    }
+    pub fn new_raw(
+        id: RequestId,
+        direction: Direction,
+        method: Option<String>,
+        message: Value,
+    ) -> Self {
+        Self {
+            id,
+            timestamp: Utc::now(),
+            content: LogEntryContent::Raw {
+                direction,
+                method,
+                message,
+            },
+        }
+    }
+
+    /// Mark a response or error as the answer to fetching a prompt
+    pub fn for_prompt(mut self, name: String) -> Self {
+        if let LogEntryContent::Response { prompt_name, .. }
+        | LogEntryContent::Error { prompt_name, .. } = &mut self.content
+        {
+            *prompt_name = Some(name);
+        }
+        self
+    }
+
+    /// The request this entry belongs to: its own id for requests and prompt fetches,
+    /// the referenced request for responses, errors, progress and cancellations
+    pub fn request_id(&self) -> Option<RequestId> {
+        match &self.content {
+            LogEntryContent::Request { .. } | LogEntryContent::Prompt { .. } => Some(self.id),
+            LogEntryContent::Response { request_id, .. }
+            | LogEntryContent::Error { request_id, .. }
+            | LogEntryContent::Cancelled { request_id, .. }
+            | LogEntryContent::Progress { request_id, .. } => Some(*request_id),
+            _ => None,
+        }
+    }
 
     pub fn filter(&self, filter: &LogFilter) -> bool {
+        let keyword = filter.keyword.as_deref().map(Keyword::new);
+        self.filter_with_keyword(filter, keyword.as_ref())
+    }
+
+    /// Like [`LogEntry::filter`], with the filter's keyword already compiled so that
+    /// it is not compiled again for every entry
+    pub fn filter_with_keyword(&self, filter: &LogFilter, keyword: Option<&Keyword>) -> bool {
         if let Some(ref filter_tool_name) = filter.tool_name
             && !self.content.match_tool_name(filter_tool_name)
         {
@@ -136,17 +372,17 @@ impl LogEntry {
         {
             return false;
         }
+        if let Some(request_id) = filter.request_id
+            && self.request_id() != Some(request_id)
+        {
+            return false;
+        }
 
         // Keyword regex filtering
-        if let Some(ref keyword) = filter.keyword {
+        if let Some(keyword) = keyword {
             // Serialize content to string for searching
             let content_str = serde_json::to_string(&self.content).unwrap_or_default();
-            if let Ok(re) = regex::Regex::new(keyword) {
-                if !re.is_match(&content_str) {
-                    return false;
-                }
-            } else if !content_str.contains(keyword) {
-                // If regex is invalid, treat as literal string search
+            if !keyword.is_match(&content_str) {
                 return false;
             }
         }
@@ -156,13 +392,21 @@ impl LogEntry {
 }
 
 // Keep for backwards compatibility in filters
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum LogEntryType {
     Request,
     Response,
     Error,
     Stderr,
+    Prompt,
+    Cancelled,
+    Progress,
+    Log,
+    Handshake,
+    Exited,
+    Health,
+    Raw,
 }
 
 impl fmt::Display for LogEntryType {
@@ -172,6 +416,39 @@ impl fmt::Display for LogEntryType {
             LogEntryType::Response => write!(f, "response"),
             LogEntryType::Error => write!(f, "error"),
             LogEntryType::Stderr => write!(f, "stderr"),
+            LogEntryType::Prompt => write!(f, "prompt"),
+            LogEntryType::Cancelled => write!(f, "cancelled"),
+            LogEntryType::Progress => write!(f, "progress"),
+            LogEntryType::Log => write!(f, "log"),
+            LogEntryType::Handshake => write!(f, "handshake"),
+            LogEntryType::Exited => write!(f, "exited"),
+            LogEntryType::Health => write!(f, "health"),
+            LogEntryType::Raw => write!(f, "raw"),
+        }
+    }
+}
+
+impl std::str::FromStr for LogEntryType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "request" => Ok(LogEntryType::Request),
+            "response" => Ok(LogEntryType::Response),
+            "error" => Ok(LogEntryType::Error),
+            "stderr" => Ok(LogEntryType::Stderr),
+            "prompt" => Ok(LogEntryType::Prompt),
+            "cancelled" => Ok(LogEntryType::Cancelled),
+            "progress" => Ok(LogEntryType::Progress),
+            "log" => Ok(LogEntryType::Log),
+            "handshake" => Ok(LogEntryType::Handshake),
+            "exited" => Ok(LogEntryType::Exited),
+            "health" => Ok(LogEntryType::Health),
+            "raw" => Ok(LogEntryType::Raw),
+            _ => Err(format!(
+                "unknown entry type '{s}' (expected request, response, error, stderr, prompt, \
+                 cancelled, progress, log, handshake, exited, health or raw)"
+            )),
         }
     }
 }
@@ -183,6 +460,14 @@ impl From<&LogEntryContent> for LogEntryType {
             LogEntryContent::Response { .. } => LogEntryType::Response,
             LogEntryContent::Error { .. } => LogEntryType::Error,
             LogEntryContent::Stderr { .. } => LogEntryType::Stderr,
+            LogEntryContent::Prompt { .. } => LogEntryType::Prompt,
+            LogEntryContent::Cancelled { .. } => LogEntryType::Cancelled,
+            LogEntryContent::Progress { .. } => LogEntryType::Progress,
+            LogEntryContent::Log { .. } => LogEntryType::Log,
+            LogEntryContent::Handshake { .. } => LogEntryType::Handshake,
+            LogEntryContent::Exited { .. } => LogEntryType::Exited,
+            LogEntryContent::Health { .. } => LogEntryType::Health,
+            LogEntryContent::Raw { .. } => LogEntryType::Raw,
         }
     }
 }
@@ -222,6 +507,7 @@ mod tests {
             after: None,
             before: None,
             keyword: None,
+            request_id: None,
         };
         assert!(entry.filter(&filter));
 
@@ -232,6 +518,7 @@ mod tests {
             after: None,
             before: None,
             keyword: None,
+            request_id: None,
         };
         assert!(!entry.filter(&filter));
 
@@ -249,6 +536,7 @@ mod tests {
             after: None,
             before: None,
             keyword: None,
+            request_id: None,
         };
         assert!(!stderr_entry.filter(&filter));
     }
@@ -271,6 +559,7 @@ mod tests {
             after: None,
             before: None,
             keyword: None,
+            request_id: None,
         };
         assert!(request_entry.filter(&filter));
 
@@ -281,6 +570,7 @@ mod tests {
             after: None,
             before: None,
             keyword: None,
+            request_id: None,
         };
         assert!(!request_entry.filter(&filter));
     }
@@ -304,6 +594,7 @@ mod tests {
             after: Some(Utc.with_ymd_and_hms(2024, 1, 15, 11, 0, 0).unwrap()),
             before: None,
             keyword: None,
+            request_id: None,
         };
         assert!(entry.filter(&filter));
 
@@ -314,6 +605,7 @@ mod tests {
             after: Some(Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap()),
             before: None,
             keyword: None,
+            request_id: None,
         };
         assert!(!entry.filter(&filter));
 
@@ -324,6 +616,7 @@ mod tests {
             after: None,
             before: Some(Utc.with_ymd_and_hms(2024, 1, 15, 13, 0, 0).unwrap()),
             keyword: None,
+            request_id: None,
         };
         assert!(entry.filter(&filter));
 
@@ -334,6 +627,7 @@ mod tests {
             after: None,
             before: Some(Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap()),
             keyword: None,
+            request_id: None,
         };
         assert!(!entry.filter(&filter));
     }
@@ -347,6 +641,8 @@ mod tests {
                 tool_name: crate::types::ToolName::from("my_tool"),
                 request_id: crate::types::RequestId::new(1),
                 response: serde_json::json!({"result": "ok"}),
+                duration_ms: None,
+                prompt_name: None,
             },
         );
 
@@ -357,6 +653,7 @@ mod tests {
             after: Some(Utc.with_ymd_and_hms(2024, 1, 15, 11, 0, 0).unwrap()),
             before: Some(Utc.with_ymd_and_hms(2024, 1, 15, 13, 0, 0).unwrap()),
             keyword: None,
+            request_id: None,
         };
         assert!(entry.filter(&filter));
 
@@ -367,6 +664,7 @@ mod tests {
             after: Some(Utc.with_ymd_and_hms(2024, 1, 15, 11, 0, 0).unwrap()),
             before: Some(Utc.with_ymd_and_hms(2024, 1, 15, 13, 0, 0).unwrap()),
             keyword: None,
+            request_id: None,
         };
         assert!(!entry.filter(&filter));
     }
@@ -380,6 +678,8 @@ mod tests {
                 tool_name: crate::types::ToolName::from("tool"),
                 request_id: crate::types::RequestId::new(1),
                 error: "test error".to_string(),
+                duration_ms: None,
+                prompt_name: None,
             },
         );
 
@@ -390,6 +690,7 @@ mod tests {
             after: None,
             before: None,
             keyword: None,
+            request_id: None,
         };
         assert!(entry.filter(&filter));
     }
@@ -415,6 +716,7 @@ mod tests {
             after: None,
             before: None,
             keyword: Some(r"important\s+doc".to_string()),
+            request_id: None,
         };
         assert!(entry.filter(&filter));
 
@@ -425,6 +727,7 @@ mod tests {
             after: None,
             before: None,
             keyword: Some(r"missing\s+pattern".to_string()),
+            request_id: None,
         };
         assert!(!entry.filter(&filter));
 
@@ -435,6 +738,7 @@ mod tests {
             after: None,
             before: None,
             keyword: Some(r"(?i)IMPORTANT".to_string()),
+            request_id: None,
         };
         assert!(entry.filter(&filter));
     }
@@ -448,6 +752,8 @@ mod tests {
                 tool_name: crate::types::ToolName::from("database"),
                 request_id: crate::types::RequestId::new(1),
                 error: "Connection timeout after 30 seconds".to_string(),
+                duration_ms: Some(30_000),
+                prompt_name: None,
             },
         );
 
@@ -458,6 +764,7 @@ mod tests {
             after: None,
             before: None,
             keyword: Some("timeout[".to_string()), // Invalid regex (unclosed bracket)
+            request_id: None,
         };
         // Should not match because "timeout[" is not in the content literally
         assert!(!entry.filter(&filter));
@@ -469,7 +776,109 @@ mod tests {
             after: None,
             before: None,
             keyword: Some("timeout".to_string()),
+            request_id: None,
+        };
+        assert!(entry.filter(&filter));
+    }
+
+    #[test]
+    fn test_filter_prompt_entry() {
+        let entry = create_test_entry(
+            1,
+            Utc::now(),
+            LogEntryContent::Prompt {
+                prompt_name: "greet".to_string(),
+                arguments: serde_json::json!({"who": "world"}),
+            },
+        );
+
+        // Should match the prompt entry type and its name
+        let filter = LogFilter {
+            tool_name: Some("greet".to_string()),
+            entry_type: Some("prompt".to_string()),
+            after: None,
+            before: None,
+            keyword: None,
+            request_id: None,
+        };
+        assert!(entry.filter(&filter));
+
+        // Should not match other entry types
+        let filter = LogFilter {
+            tool_name: None,
+            entry_type: Some("request".to_string()),
+            after: None,
+            before: None,
+            keyword: None,
+            request_id: None,
+        };
+        assert!(!entry.filter(&filter));
+    }
+
+    #[test]
+    fn test_filter_log_entry() {
+        let entry = create_test_entry(
+            1,
+            Utc::now(),
+            LogEntryContent::Log {
+                level: "warning".to_string(),
+                logger: Some("db".to_string()),
+                data: serde_json::json!({"message": "slow query"}),
+            },
+        );
+
+        // Should match the log entry type and a keyword in its data
+        let filter = LogFilter {
+            tool_name: None,
+            entry_type: Some("log".to_string()),
+            after: None,
+            before: None,
+            keyword: Some("slow query".to_string()),
+            request_id: None,
         };
         assert!(entry.filter(&filter));
+
+        // Log messages are not attributed to a tool
+        let filter = LogFilter {
+            tool_name: Some("db".to_string()),
+            entry_type: None,
+            after: None,
+            before: None,
+            keyword: None,
+            request_id: None,
+        };
+        assert!(!entry.filter(&filter));
+    }
+
+    #[test]
+    fn test_prompt_fetch_is_linked_and_filtered_by_prompt_name() {
+        let prompt = LogEntry::new_prompt(
+            crate::types::RequestId::new(1),
+            "greeting".to_string(),
+            serde_json::json!({"name": "Ada"}),
+        );
+        let response = LogEntry::new_response(
+            crate::types::RequestId::new(2),
+            crate::types::ToolName::from("prompts/get"),
+            crate::types::RequestId::new(1),
+            serde_json::json!({"messages": []}),
+            None,
+        )
+        .for_prompt("greeting".to_string());
+
+        assert_eq!(prompt.request_id(), Some(crate::types::RequestId::new(1)));
+        assert_eq!(response.request_id(), Some(crate::types::RequestId::new(1)));
+        assert_eq!(response.content.tool_name(), Some("greeting"));
+
+        let filter = LogFilter {
+            tool_name: Some("greeting".to_string()),
+            entry_type: None,
+            after: None,
+            before: None,
+            keyword: None,
+            request_id: None,
+        };
+        assert!(prompt.filter(&filter));
+        assert!(response.filter(&filter));
     }
 }
@@ -1,6 +1,9 @@
 use chrono::{DateTime, Utc};
+use regex::Regex;
 use serde::Deserialize;
 
+use crate::types::RequestId;
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct LogFilter {
     pub tool_name: Option<String>,
@@ -8,4 +11,31 @@ pub struct LogFilter {
     pub after: Option<DateTime<Utc>>,
     pub before: Option<DateTime<Utc>>,
     pub keyword: Option<String>, // Regular expression pattern for content search
+    /// A request and the entries that refer to it (response, error, progress, cancellation)
+    pub request_id: Option<RequestId>,
+}
+
+/// A keyword compiled once per query, matched against the serialized content of entries
+#[derive(Debug, Clone)]
+pub enum Keyword {
+    Regex(Regex),
+    /// Keywords without regex syntax, and invalid patterns, which are searched for literally
+    Literal(String),
+}
+
+impl Keyword {
+    pub fn new(keyword: &str) -> Self {
+        match Regex::new(keyword) {
+            Ok(_) if regex::escape(keyword) == keyword => Keyword::Literal(keyword.to_string()),
+            Ok(re) => Keyword::Regex(re),
+            Err(_) => Keyword::Literal(keyword.to_string()),
+        }
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        match self {
+            Keyword::Regex(re) => re.is_match(text),
+            Keyword::Literal(literal) => text.contains(literal.as_str()),
+        }
+    }
 }
@@ -1,7 +1,15 @@
 mod entry;
 mod filter;
+mod persist;
+mod sink;
 mod storage;
+mod store;
+mod wire;
 
 pub use entry::{LogEntry, LogEntryContent, LogEntryType};
 pub use filter::LogFilter;
+pub(crate) use persist::load_recent;
+pub use sink::{FilteredSink, LogSink};
 pub use storage::LogStorage;
+pub use store::{LogStore, LogStoreError, MemoryStore, SqliteStore};
+pub use wire::{Direction, WireTap};
@@ -0,0 +1,272 @@
+use chrono::{DateTime, Utc};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::config::PersistConfig;
+use crate::logging::sink::LogSink;
+use crate::logging::{LogEntry, LogStoreError};
+
+const SEGMENT_PREFIX: &str = "wrap-mcp-";
+const SEGMENT_SUFFIX: &str = ".jsonl";
+
+/// Appends log entries as JSON Lines to segment files in a directory, starting a new
+/// segment once the current one is too large or too old and keeping only the newest ones
+#[derive(Debug)]
+pub struct LogFile {
+    dir: PathBuf,
+    rotate_bytes: u64,
+    rotate_age_secs: u64,
+    retain: usize,
+    file: File,
+    /// Bytes written to the current segment
+    size: u64,
+    opened_at: Instant,
+}
+
+impl LogFile {
+    /// Start a new segment in the configured directory, creating it if needed
+    pub fn open(dir: &Path, config: &PersistConfig) -> io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let file = new_segment(dir)?;
+        let log_file = Self {
+            dir: dir.to_path_buf(),
+            rotate_bytes: config.rotate_bytes,
+            rotate_age_secs: config.rotate_age_secs,
+            retain: config.retain,
+            file,
+            size: 0,
+            opened_at: Instant::now(),
+        };
+        log_file.prune()?;
+        Ok(log_file)
+    }
+
+    /// Append an entry, rotating first if the current segment is full or too old
+    pub fn append(&mut self, entry: &LogEntry) -> io::Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+
+        let too_large = self.size > 0 && self.size + line.len() as u64 > self.rotate_bytes;
+        let too_old =
+            self.rotate_age_secs > 0 && self.opened_at.elapsed().as_secs() >= self.rotate_age_secs;
+        if too_large || too_old {
+            self.rotate()?;
+        }
+
+        self.file.write_all(&line)?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Remove every segment and start over with an empty one
+    pub fn clear(&mut self) -> io::Result<()> {
+        for segment in segments(&self.dir)? {
+            std::fs::remove_file(segment)?;
+        }
+        self.rotate()
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file = new_segment(&self.dir)?;
+        self.size = 0;
+        self.opened_at = Instant::now();
+        self.prune()
+    }
+
+    /// Remove the oldest segments beyond the retention count
+    fn prune(&self) -> io::Result<()> {
+        let segments = segments(&self.dir)?;
+        let excess = segments.len().saturating_sub(self.retain);
+        for segment in &segments[..excess] {
+            tracing::debug!("Removing old log segment {}", segment.display());
+            std::fs::remove_file(segment)?;
+        }
+        Ok(())
+    }
+}
+
+/// A [`LogFile`] that entries are fanned out to
+#[derive(Debug)]
+pub struct SegmentSink {
+    file: Mutex<LogFile>,
+}
+
+impl SegmentSink {
+    pub fn new(file: LogFile) -> Self {
+        Self {
+            file: Mutex::new(file),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LogFile> {
+        self.file.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl LogSink for SegmentSink {
+    fn name(&self) -> String {
+        format!("segments in {}", self.lock().dir.display())
+    }
+
+    fn write(&self, entry: &LogEntry) -> Result<(), LogStoreError> {
+        Ok(self.lock().append(entry)?)
+    }
+
+    fn clear(&self) -> Result<(), LogStoreError> {
+        Ok(self.lock().clear()?)
+    }
+}
+
+/// Read up to `limit` of the most recent entries from the segments in a directory,
+/// oldest first. Lines that cannot be parsed, such as one cut short by a crash, are skipped.
+pub fn load_recent(dir: &Path, limit: usize) -> io::Result<Vec<LogEntry>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut recent = Vec::new();
+    for segment in segments(dir)?.iter().rev() {
+        let mut entries = Vec::new();
+        for line in BufReader::new(File::open(segment)?).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<LogEntry>(&line) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => tracing::warn!("Skipping unreadable line in {}: {e}", segment.display()),
+            }
+        }
+        // Segments are visited newest first, so older entries go in front
+        entries.append(&mut recent);
+        recent = entries;
+        if recent.len() >= limit {
+            break;
+        }
+    }
+
+    let skip = recent.len().saturating_sub(limit);
+    Ok(recent.split_off(skip))
+}
+
+/// Segment files in a directory, oldest first
+fn segments(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut segments: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    name.starts_with(SEGMENT_PREFIX) && name.ends_with(SEGMENT_SUFFIX)
+                })
+        })
+        .collect();
+    // Names embed the creation time in a sortable form
+    segments.sort();
+    Ok(segments)
+}
+
+/// Create a segment named after the current time
+fn new_segment(dir: &Path) -> io::Result<File> {
+    let mut time = Utc::now();
+    loop {
+        let path = dir.join(segment_name(time));
+        match OpenOptions::new().append(true).create_new(true).open(&path) {
+            Ok(file) => {
+                tracing::debug!("Writing log entries to {}", path.display());
+                return Ok(file);
+            }
+            // Another segment was started within the same microsecond
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                time += chrono::Duration::microseconds(1);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn segment_name(time: DateTime<Utc>) -> String {
+    format!(
+        "{SEGMENT_PREFIX}{}{SEGMENT_SUFFIX}",
+        time.format("%Y%m%dT%H%M%S%.6fZ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{RequestId, ToolName};
+    use serde_json::json;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("wrap-mcp-{name}-{}", std::process::id()));
+        _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn entry(id: usize) -> LogEntry {
+        LogEntry::new_request(
+            RequestId::new(id),
+            ToolName::new("echo"),
+            json!({"index": id}),
+        )
+    }
+
+    #[test]
+    fn test_append_and_reload() {
+        let dir = temp_dir("reload");
+        let mut file = LogFile::open(&dir, &PersistConfig::default()).unwrap();
+        for id in 1..=5 {
+            file.append(&entry(id)).unwrap();
+        }
+
+        let recent = load_recent(&dir, 3).unwrap();
+        let ids: Vec<usize> = recent.iter().map(|e| e.id.inner()).collect();
+        assert_eq!(ids, vec![3, 4, 5]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rotation_and_retention() {
+        let dir = temp_dir("rotate");
+        let config = PersistConfig {
+            rotate_bytes: 1,
+            retain: 3,
+            ..PersistConfig::default()
+        };
+        let mut file = LogFile::open(&dir, &config).unwrap();
+        for id in 1..=10 {
+            file.append(&entry(id)).unwrap();
+        }
+
+        // One entry per segment, and only the newest segments are kept
+        assert_eq!(segments(&dir).unwrap().len(), 3);
+        let ids: Vec<usize> = load_recent(&dir, 100)
+            .unwrap()
+            .iter()
+            .map(|e| e.id.inner())
+            .collect();
+        assert_eq!(ids, vec![8, 9, 10]);
+
+        file.clear().unwrap();
+        assert!(load_recent(&dir, 100).unwrap().is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reload_skips_truncated_line() {
+        let dir = temp_dir("truncated");
+        let mut file = LogFile::open(&dir, &PersistConfig::default()).unwrap();
+        file.append(&entry(1)).unwrap();
+        file.file.write_all(b"{\"id\":2,\"times").unwrap();
+
+        assert_eq!(load_recent(&dir, 10).unwrap().len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
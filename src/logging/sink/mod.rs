@@ -0,0 +1,84 @@
+mod file;
+mod stderr;
+mod store;
+mod stream;
+mod threaded;
+
+use std::fmt;
+use std::sync::Arc;
+use tokio::sync::oneshot;
+
+use crate::config::{SinkConfig, SinkTarget};
+use crate::logging::{LogEntry, LogEntryType, LogStoreError};
+
+pub use file::FileSink;
+pub use stderr::StderrSink;
+pub use store::StoreSink;
+pub use stream::StreamSink;
+pub use threaded::ThreadedSink;
+
+/// A destination `LogStorage` fans every log entry out to
+pub trait LogSink: fmt::Debug + Send + Sync {
+    /// Short description used in warnings, e.g. `tcp:127.0.0.1:9000`
+    fn name(&self) -> String;
+
+    /// Hand over an entry. Called on the logging path, so it must not block for long.
+    fn write(&self, entry: &LogEntry) -> Result<(), LogStoreError>;
+
+    /// Forget what was written so far when the log is cleared; sinks that pass
+    /// entries on to something else have nothing to forget
+    fn clear(&self) -> Result<(), LogStoreError> {
+        Ok(())
+    }
+
+    /// For sinks that write in the background: resolves once everything handed
+    /// over so far has been written
+    fn flushed(&self) -> Option<oneshot::Receiver<()>> {
+        None
+    }
+}
+
+/// A sink together with the entry types it receives
+#[derive(Debug, Clone)]
+pub struct FilteredSink {
+    sink: Arc<dyn LogSink>,
+    /// All entry types when empty
+    entry_types: Vec<LogEntryType>,
+}
+
+impl FilteredSink {
+    pub fn new(sink: Arc<dyn LogSink>, entry_types: Vec<LogEntryType>) -> Self {
+        Self { sink, entry_types }
+    }
+
+    pub fn accepts(&self, entry: &LogEntry) -> bool {
+        self.entry_types.is_empty()
+            || self
+                .entry_types
+                .contains(&LogEntryType::from(&entry.content))
+    }
+
+    pub fn sink(&self) -> &dyn LogSink {
+        self.sink.as_ref()
+    }
+}
+
+/// Create the sink a config entry describes
+pub fn build(config: &SinkConfig) -> Result<FilteredSink, LogStoreError> {
+    let sink: Arc<dyn LogSink> = match &config.target {
+        SinkTarget::File(path) => Arc::new(FileSink::open(path)?),
+        SinkTarget::Stderr => Arc::new(StderrSink),
+        SinkTarget::Tcp(address) => Arc::new(StreamSink::tcp(address.clone())),
+        SinkTarget::Unix(path) => Arc::new(StreamSink::unix(path.clone())),
+    };
+    // Writes and serialization happen off the logging path, which runs on the async runtime
+    let sink = Arc::new(ThreadedSink::spawn(sink)?);
+    Ok(FilteredSink::new(sink, config.entry_types.clone()))
+}
+
+/// Serialize an entry as one line of JSON, newline included
+fn json_line(entry: &LogEntry) -> Result<String, LogStoreError> {
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    Ok(line)
+}
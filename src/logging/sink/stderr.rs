@@ -0,0 +1,18 @@
+use super::LogSink;
+use crate::logging::{LogEntry, LogStoreError};
+
+/// Writes entries to Wrap-MCP's own tracing output, which goes to stderr
+#[derive(Debug)]
+pub struct StderrSink;
+
+impl LogSink for StderrSink {
+    fn name(&self) -> String {
+        "stderr".to_string()
+    }
+
+    fn write(&self, entry: &LogEntry) -> Result<(), LogStoreError> {
+        let line = serde_json::to_string(entry)?;
+        tracing::info!(target: "wrap_mcp::sink", "{line}");
+        Ok(())
+    }
+}
@@ -0,0 +1,31 @@
+use std::sync::Arc;
+
+use super::LogSink;
+use crate::logging::{LogEntry, LogStore, LogStoreError};
+
+/// Keeps entries in the store `show_log` queries: the in-memory ring buffer or
+/// the SQLite database
+#[derive(Debug)]
+pub struct StoreSink {
+    store: Arc<dyn LogStore>,
+}
+
+impl StoreSink {
+    pub fn new(store: Arc<dyn LogStore>) -> Self {
+        Self { store }
+    }
+}
+
+impl LogSink for StoreSink {
+    fn name(&self) -> String {
+        "log store".to_string()
+    }
+
+    fn write(&self, entry: &LogEntry) -> Result<(), LogStoreError> {
+        self.store.insert(entry)
+    }
+
+    fn clear(&self) -> Result<(), LogStoreError> {
+        self.store.clear()
+    }
+}
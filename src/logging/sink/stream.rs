@@ -0,0 +1,183 @@
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+use super::{LogSink, json_line};
+use crate::logging::{LogEntry, LogStoreError};
+
+/// Entries waiting to be sent before new ones are dropped
+const QUEUE_CAPACITY: usize = 1024;
+const RECONNECT_MIN: Duration = Duration::from_millis(500);
+const RECONNECT_MAX: Duration = Duration::from_secs(30);
+
+type Connection = Pin<Box<dyn AsyncWrite + Send>>;
+
+#[derive(Debug, Clone)]
+enum Endpoint {
+    Tcp(String),
+    Unix(PathBuf),
+}
+
+impl Endpoint {
+    fn name(&self) -> String {
+        match self {
+            Endpoint::Tcp(address) => format!("tcp:{address}"),
+            Endpoint::Unix(path) => format!("unix:{}", path.display()),
+        }
+    }
+
+    async fn connect(&self) -> std::io::Result<Connection> {
+        match self {
+            Endpoint::Tcp(address) => {
+                let stream = tokio::net::TcpStream::connect(address.as_str()).await?;
+                Ok(Box::pin(stream))
+            }
+            #[cfg(unix)]
+            Endpoint::Unix(path) => Ok(Box::pin(tokio::net::UnixStream::connect(path).await?)),
+            #[cfg(not(unix))]
+            Endpoint::Unix(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "Unix domain sockets are not supported on this platform",
+            )),
+        }
+    }
+}
+
+/// Streams entries as JSON Lines to a listener on a TCP address or Unix socket.
+///
+/// Entries are queued and sent by a background task that reconnects whenever the
+/// listener goes away; while the queue is full, new entries are dropped rather
+/// than holding up the proxy.
+#[derive(Debug)]
+pub struct StreamSink {
+    name: String,
+    queue: mpsc::Sender<String>,
+    /// Entries dropped because the queue was full
+    dropped: AtomicU64,
+    /// Whether the last entry was dropped, to warn once per run of drops
+    dropping: AtomicBool,
+}
+
+impl StreamSink {
+    /// Stream to `host:port`. Must be called within a Tokio runtime.
+    pub fn tcp(address: String) -> Self {
+        Self::spawn(Endpoint::Tcp(address))
+    }
+
+    /// Stream to a Unix domain socket. Must be called within a Tokio runtime.
+    pub fn unix(path: PathBuf) -> Self {
+        Self::spawn(Endpoint::Unix(path))
+    }
+
+    fn spawn(endpoint: Endpoint) -> Self {
+        let (queue, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        let name = endpoint.name();
+        tokio::spawn(send_entries(endpoint, receiver));
+        Self {
+            name,
+            queue,
+            dropped: AtomicU64::new(0),
+            dropping: AtomicBool::new(false),
+        }
+    }
+}
+
+impl LogSink for StreamSink {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn write(&self, entry: &LogEntry) -> Result<(), LogStoreError> {
+        match self.queue.try_send(json_line(entry)?) {
+            Ok(()) => {
+                self.dropping.store(false, Ordering::Relaxed);
+            }
+            Err(_) => {
+                let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                if !self.dropping.swap(true, Ordering::Relaxed) {
+                    tracing::warn!(
+                        "Log sink {} is not keeping up, dropping entries ({dropped} so far)",
+                        self.name
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Send queued lines until the sink is dropped, reconnecting with backoff
+async fn send_entries(endpoint: Endpoint, mut receiver: mpsc::Receiver<String>) {
+    let name = endpoint.name();
+    let mut backoff = RECONNECT_MIN;
+    // A line that could not be sent because the connection broke
+    let mut unsent: Option<String> = None;
+
+    loop {
+        let mut connection = match endpoint.connect().await {
+            Ok(connection) => {
+                tracing::info!("Log sink {name} connected");
+                backoff = RECONNECT_MIN;
+                connection
+            }
+            Err(e) => {
+                tracing::warn!("Log sink {name} failed to connect: {e}, retrying in {backoff:?}");
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(RECONNECT_MAX);
+                if receiver.is_closed() && receiver.is_empty() {
+                    return;
+                }
+                continue;
+            }
+        };
+
+        loop {
+            let line = match unsent.take() {
+                Some(line) => line,
+                None => match receiver.recv().await {
+                    Some(line) => line,
+                    None => return,
+                },
+            };
+            if let Err(e) = connection.write_all(line.as_bytes()).await {
+                tracing::warn!("Log sink {name} lost its connection: {e}");
+                unsent = Some(line);
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{RequestId, ToolName};
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    #[tokio::test]
+    async fn test_streams_json_lines_over_tcp() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let sink = StreamSink::tcp(address);
+        for id in 1..=2 {
+            let entry = LogEntry::new_request(
+                RequestId::new(id),
+                ToolName::new("echo"),
+                serde_json::json!({"n": id}),
+            );
+            sink.write(&entry).unwrap();
+        }
+
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut lines = BufReader::new(socket).lines();
+        for id in 1..=2 {
+            let line = lines.next_line().await.unwrap().unwrap();
+            let entry: LogEntry = serde_json::from_str(&line).unwrap();
+            assert_eq!(entry.id, RequestId::new(id));
+        }
+    }
+}
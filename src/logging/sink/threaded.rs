@@ -0,0 +1,134 @@
+use std::sync::{Arc, mpsc};
+use tokio::sync::oneshot;
+
+use super::LogSink;
+use crate::logging::{LogEntry, LogStoreError};
+
+/// Work queued for the writer thread, done in the order it was handed over
+enum Write {
+    Insert(LogEntry),
+    Clear,
+    Flush(oneshot::Sender<()>),
+}
+
+/// Hands entries to another sink on a dedicated thread, for sinks whose writes
+/// block, such as files and databases, so that they do not hold up the async runtime
+#[derive(Debug)]
+pub struct ThreadedSink {
+    name: String,
+    writer: Option<mpsc::Sender<Write>>,
+    /// Joined on drop, so that queued entries are written before the process moves on
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl ThreadedSink {
+    pub fn spawn(sink: Arc<dyn LogSink>) -> Result<Self, LogStoreError> {
+        let name = sink.name();
+        let (writer, queue) = mpsc::channel();
+        let thread = std::thread::Builder::new()
+            .name("log-sink-writer".to_string())
+            .spawn(move || write_entries(sink.as_ref(), queue))?;
+        Ok(Self {
+            name,
+            writer: Some(writer),
+            thread: Some(thread),
+        })
+    }
+
+    fn queue(&self, write: Write) -> Result<(), LogStoreError> {
+        if let Some(writer) = &self.writer
+            && writer.send(write).is_ok()
+        {
+            return Ok(());
+        }
+        Err(std::io::Error::new(
+            std::io::ErrorKind::BrokenPipe,
+            format!("writer thread of {} has stopped", self.name),
+        )
+        .into())
+    }
+}
+
+impl Drop for ThreadedSink {
+    fn drop(&mut self) {
+        // Closing the queue lets the thread finish what is left and exit
+        drop(self.writer.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl LogSink for ThreadedSink {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn write(&self, entry: &LogEntry) -> Result<(), LogStoreError> {
+        self.queue(Write::Insert(entry.clone()))
+    }
+
+    fn clear(&self) -> Result<(), LogStoreError> {
+        self.queue(Write::Clear)
+    }
+
+    fn flushed(&self) -> Option<oneshot::Receiver<()>> {
+        let (done, flushed) = oneshot::channel();
+        self.queue(Write::Flush(done)).ok()?;
+        Some(flushed)
+    }
+}
+
+/// Apply queued writes until the sink is dropped
+fn write_entries(sink: &dyn LogSink, queue: mpsc::Receiver<Write>) {
+    for write in queue {
+        match write {
+            Write::Insert(entry) => {
+                if let Err(e) = sink.write(&entry) {
+                    tracing::warn!(
+                        "Failed to write log entry #{} to {}: {e}",
+                        entry.id,
+                        sink.name()
+                    );
+                }
+            }
+            Write::Clear => {
+                if let Err(e) = sink.clear() {
+                    tracing::warn!("Failed to clear {}: {e}", sink.name());
+                }
+            }
+            Write::Flush(done) => {
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::sink::StoreSink;
+    use crate::logging::{LogStore, SqliteStore};
+    use crate::types::{RequestId, ToolName};
+
+    #[tokio::test]
+    async fn test_writes_are_visible_after_flush() {
+        let store: Arc<dyn LogStore> = Arc::new(SqliteStore::open_in_memory(100).unwrap());
+        let sink = ThreadedSink::spawn(Arc::new(StoreSink::new(store.clone()))).unwrap();
+
+        for id in 1..=3 {
+            let entry = LogEntry::new_request(
+                RequestId::new(id),
+                ToolName::new("echo"),
+                serde_json::json!({"n": id}),
+            );
+            sink.write(&entry).unwrap();
+        }
+        sink.flushed().unwrap().await.unwrap();
+        assert_eq!(store.count().unwrap(), 3);
+
+        sink.clear().unwrap();
+        sink.flushed().unwrap().await.unwrap();
+        assert_eq!(store.count().unwrap(), 0);
+    }
+}
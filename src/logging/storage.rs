@@ -1,36 +1,140 @@
 use regex::Regex;
 use serde_json::Value;
-use std::collections::VecDeque;
 use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 use tokio::sync::RwLock;
 
 use crate::config::LogConfig;
-use crate::logging::{LogEntry, LogFilter};
+use crate::logging::persist::{self, LogFile, SegmentSink};
+use crate::logging::sink::{self, FilteredSink, StoreSink, ThreadedSink};
+use crate::logging::{
+    Direction, LogEntry, LogFilter, LogSink, LogStore, LogStoreError, MemoryStore, SqliteStore,
+    WireTap,
+};
 use crate::types::{RequestId, ToolName};
 
+/// Method prompt responses and errors are logged under in place of a tool name
+const PROMPTS_GET: &str = "prompts/get";
+
 // Compile the ANSI regex once at startup
 static ANSI_REGEX: OnceLock<Regex> = OnceLock::new();
 
 #[derive(Debug, Clone)]
 pub struct LogStorage {
-    entries: Arc<RwLock<VecDeque<LogEntry>>>,
+    /// Where `show_log` reads entries from
+    store: Arc<dyn LogStore>,
+    /// Every entry is handed to each of these that accepts its type, the store included.
+    /// Sinks writing in the background are flushed before the store is read.
+    sinks: Vec<FilteredSink>,
     next_id: Arc<RwLock<usize>>,
     max_entries: usize,
     ansi_removal_enabled: Arc<RwLock<bool>>,
+    progress_enabled: bool,
+    persist_dir: Option<std::path::PathBuf>,
+    /// SQLite database entries are kept in instead of memory, if any
+    db_path: Option<std::path::PathBuf>,
+    /// Captures JSON-RPC frames into this storage, if raw logging is enabled
+    wire_tap: Option<WireTap>,
 }
 
 impl LogStorage {
     pub fn new(config: &LogConfig) -> Self {
-        Self::new_with_max_entries(config.log_size)
+        Self {
+            progress_enabled: config.log_progress,
+            ..Self::new_with_max_entries(config.log_size)
+        }
     }
 
     pub fn new_with_max_entries(max_entries: usize) -> Self {
+        let store: Arc<dyn LogStore> = Arc::new(MemoryStore::new(max_entries));
         Self {
-            entries: Arc::new(RwLock::new(VecDeque::new())),
+            sinks: vec![FilteredSink::new(
+                Arc::new(StoreSink::new(store.clone())),
+                Vec::new(),
+            )],
+            store,
             next_id: Arc::new(RwLock::new(1)),
             max_entries,
             ansi_removal_enabled: Arc::new(RwLock::new(true)),
+            progress_enabled: false,
+            persist_dir: None,
+            db_path: None,
+            wire_tap: None,
+        }
+    }
+
+    /// Create the storage with the store the config asks for: an SQLite database, or
+    /// memory optionally backed by JSON Lines segments the most recent entries are
+    /// loaded back from. Entries are also fanned out to the configured sinks.
+    ///
+    /// With raw logging enabled this must be called within a Tokio runtime.
+    pub fn open(config: &LogConfig) -> Result<Self, LogStoreError> {
+        let (store, store_sink, db_path): (Arc<dyn LogStore>, Arc<dyn LogSink>, _) =
+            match &config.db {
+                Some(path) => {
+                    let store = SqliteStore::open(path, config.log_size)?;
+                    tracing::info!(
+                        "Storing log entries in {} ({} kept)",
+                        path.display(),
+                        store.count()?
+                    );
+                    // Inserts are disk writes, so they are kept off the async runtime
+                    let store: Arc<dyn LogStore> = Arc::new(store);
+                    let store_sink = ThreadedSink::spawn(Arc::new(StoreSink::new(store.clone())))?;
+                    (store, Arc::new(store_sink), Some(path.clone()))
+                }
+                None => {
+                    let recent = match &config.persist.dir {
+                        Some(dir) if config.persist.reload => {
+                            persist::load_recent(dir, config.log_size)?
+                        }
+                        _ => Vec::new(),
+                    };
+                    let store: Arc<dyn LogStore> =
+                        Arc::new(MemoryStore::with_entries(config.log_size, recent));
+                    let store_sink = StoreSink::new(store.clone());
+                    (store, Arc::new(store_sink), None)
+                }
+            };
+        let next_id = store.max_id()? + 1;
+
+        let mut sinks = vec![FilteredSink::new(store_sink, config.entry_types.clone())];
+        if let Some(dir) = &config.persist.dir {
+            tracing::info!(
+                "Persisting log entries to {} (next id #{next_id})",
+                dir.display()
+            );
+            // Appends and rotations are disk writes, so they are kept off the async runtime
+            let file = LogFile::open(dir, &config.persist)?;
+            sinks.push(FilteredSink::new(
+                Arc::new(ThreadedSink::spawn(Arc::new(SegmentSink::new(file)))?),
+                config.entry_types.clone(),
+            ));
+        }
+        for sink_config in &config.sinks {
+            tracing::info!("Sending log entries to {}", sink_config.target);
+            sinks.push(sink::build(sink_config)?);
+        }
+
+        let mut storage = Self {
+            store,
+            sinks,
+            next_id: Arc::new(RwLock::new(next_id)),
+            persist_dir: config.persist.dir.clone(),
+            db_path,
+            ..Self::new(config)
+        };
+        if config.log_raw {
+            tracing::info!("Capturing raw JSON-RPC frames");
+            storage.wire_tap = Some(WireTap::spawn(storage.clone()));
         }
+        Ok(storage)
+    }
+
+    /// Also send entries to a sink of your own
+    pub fn with_sink(mut self, sink: FilteredSink) -> Self {
+        self.sinks.push(sink);
+        self
     }
 
     async fn get_next_id(&self) -> RequestId {
@@ -41,16 +145,18 @@ impl LogStorage {
     }
 
     async fn add_entry(&self, entry: LogEntry) {
-        let mut entries = self.entries.write().await;
-        entries.push_back(entry);
-        self.trim_entries(&mut entries).await;
-    }
-
-    async fn trim_entries(&self, entries: &mut VecDeque<LogEntry>) {
-        if entries.len() > self.max_entries {
-            let remove_count = entries.len() - self.max_entries;
-            entries.drain(..remove_count);
-            tracing::debug!("Trimmed {remove_count} old log entries");
+        for filtered in &self.sinks {
+            if !filtered.accepts(&entry) {
+                continue;
+            }
+            let sink = filtered.sink();
+            if let Err(e) = sink.write(&entry) {
+                tracing::warn!(
+                    "Failed to write log entry #{} to {}: {e}",
+                    entry.id,
+                    sink.name()
+                );
+            }
         }
     }
 
@@ -63,13 +169,43 @@ impl LogStorage {
     }
 
     pub async fn add_response(&self, request_id: RequestId, tool_name: String, response: Value) {
+        self.add_response_with_latency(request_id, tool_name, response, None)
+            .await;
+    }
+
+    /// Log a response together with the time it took the wrappee to produce it
+    pub async fn add_response_with_latency(
+        &self,
+        request_id: RequestId,
+        tool_name: String,
+        response: Value,
+        latency: Option<Duration>,
+    ) {
         let id = self.get_next_id().await;
         tracing::info!("Logged response #{} for request #{}", id, request_id);
-        let entry = LogEntry::new_response(id, ToolName::from(tool_name), request_id, response);
+        let entry = LogEntry::new_response(
+            id,
+            ToolName::from(tool_name),
+            request_id,
+            response,
+            latency.map(|d| d.as_millis() as u64),
+        );
         self.add_entry(entry).await;
     }
 
     pub async fn add_error(&self, request_id: RequestId, tool_name: String, error_message: String) {
+        self.add_error_with_latency(request_id, tool_name, error_message, None)
+            .await;
+    }
+
+    /// Log an error together with the time that passed before it occurred
+    pub async fn add_error_with_latency(
+        &self,
+        request_id: RequestId,
+        tool_name: String,
+        error_message: String,
+        latency: Option<Duration>,
+    ) {
         let id = self.get_next_id().await;
         tracing::error!(
             "Logged error #{} for request #{}: {}",
@@ -77,7 +213,105 @@ impl LogStorage {
             request_id,
             error_message
         );
-        let entry = LogEntry::new_error(id, ToolName::from(tool_name), request_id, error_message);
+        let entry = LogEntry::new_error(
+            id,
+            ToolName::from(tool_name),
+            request_id,
+            error_message,
+            latency.map(|d| d.as_millis() as u64),
+        );
+        self.add_entry(entry).await;
+    }
+
+    /// Log that the client cancelled a request before the wrappee answered it
+    pub async fn add_cancelled(&self, request_id: RequestId, tool_name: String, reason: String) {
+        let id = self.get_next_id().await;
+        tracing::info!("Logged cancellation #{} for request #{}", id, request_id);
+        let entry = LogEntry::new_cancelled(id, ToolName::from(tool_name), request_id, reason);
+        self.add_entry(entry).await;
+    }
+
+    /// Log a progress notification for an in-flight request (only if progress logging is enabled)
+    pub async fn add_progress(
+        &self,
+        request_id: RequestId,
+        tool_name: String,
+        progress: f64,
+        total: Option<f64>,
+        message: Option<String>,
+    ) {
+        if !self.progress_enabled {
+            return;
+        }
+
+        let id = self.get_next_id().await;
+        tracing::debug!("Logged progress #{} for request #{}", id, request_id);
+        let entry = LogEntry::new_progress(
+            id,
+            ToolName::from(tool_name),
+            request_id,
+            progress,
+            total,
+            message,
+        );
+        self.add_entry(entry).await;
+    }
+
+    /// Log a structured message the wrappee sent via `notifications/message`
+    pub async fn add_log_message(&self, level: String, logger: Option<String>, data: Value) {
+        let id = self.get_next_id().await;
+        tracing::debug!("Logged wrappee {} message #{}", level, id);
+        let entry = LogEntry::new_log(id, level, logger, data);
+        self.add_entry(entry).await;
+    }
+
+    /// Log the protocol version agreed with the client or the wrappee
+    pub async fn add_handshake(
+        &self,
+        peer: &str,
+        implementation: Option<String>,
+        requested: String,
+        negotiated: String,
+    ) {
+        let id = self.get_next_id().await;
+        tracing::info!("Negotiated protocol {negotiated} with {peer} (requested {requested})");
+        let entry =
+            LogEntry::new_handshake(id, peer.to_string(), implementation, requested, negotiated);
+        self.add_entry(entry).await;
+    }
+
+    /// Log that the wrappee went away, with its exit code or terminating signal if known
+    /// and the shutdown stage that ended it if it was stopped on purpose
+    pub async fn add_exited(
+        &self,
+        code: Option<i32>,
+        signal: Option<i32>,
+        shutdown_stage: Option<String>,
+    ) {
+        let id = self.get_next_id().await;
+        tracing::info!(
+            "Logged wrappee exit #{} (code {:?}, signal {:?})",
+            id,
+            code,
+            signal
+        );
+        let entry = LogEntry::new_exited(id, code, signal, shutdown_stage);
+        self.add_entry(entry).await;
+    }
+
+    /// Log that the wrappee became unhealthy after failed pings, or recovered
+    pub async fn add_health(&self, healthy: bool, failures: u32, error: Option<String>) {
+        let id = self.get_next_id().await;
+        tracing::info!("Logged wrappee health #{id} (healthy: {healthy}, failures: {failures})");
+        let entry = LogEntry::new_health(id, healthy, failures, error);
+        self.add_entry(entry).await;
+    }
+
+    /// Log a JSON-RPC frame exactly as it crossed the wire
+    pub async fn add_raw(&self, direction: Direction, method: Option<String>, message: Value) {
+        let id = self.get_next_id().await;
+        tracing::trace!("Logged {direction} frame #{id}");
+        let entry = LogEntry::new_raw(id, direction, method, message);
         self.add_entry(entry).await;
     }
 
@@ -96,37 +330,128 @@ impl LogStorage {
         self.add_entry(entry).await;
     }
 
-    pub async fn get_logs(&self, limit: Option<usize>, filter: Option<LogFilter>) -> Vec<LogEntry> {
-        let entries = self.entries.read().await;
-        let mut result: Vec<LogEntry> = entries.iter().cloned().collect();
+    pub async fn add_prompt(&self, prompt_name: String, arguments: Value) -> RequestId {
+        let id = self.get_next_id().await;
+        let entry = LogEntry::new_prompt(id, prompt_name, arguments);
+        self.add_entry(entry).await;
+        tracing::info!("Logged prompt #{}", id);
+        id
+    }
 
-        if let Some(filter) = filter {
-            result.retain(|entry| entry.filter(&filter));
-        }
+    /// Log the prompt the wrappee returned for a fetch logged with `add_prompt`
+    pub async fn add_prompt_response(
+        &self,
+        request_id: RequestId,
+        prompt_name: String,
+        response: Value,
+        latency: Option<Duration>,
+    ) {
+        let id = self.get_next_id().await;
+        tracing::info!("Logged prompt response #{} for prompt #{}", id, request_id);
+        let entry = LogEntry::new_response(
+            id,
+            ToolName::from(PROMPTS_GET),
+            request_id,
+            response,
+            latency.map(|d| d.as_millis() as u64),
+        )
+        .for_prompt(prompt_name);
+        self.add_entry(entry).await;
+    }
 
-        // Sort by timestamp descending (newest first)
-        result.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    /// Log why fetching a prompt logged with `add_prompt` failed
+    pub async fn add_prompt_error(
+        &self,
+        request_id: RequestId,
+        prompt_name: String,
+        error_message: String,
+        latency: Option<Duration>,
+    ) {
+        let id = self.get_next_id().await;
+        tracing::error!(
+            "Logged prompt error #{} for prompt #{}: {}",
+            id,
+            request_id,
+            error_message
+        );
+        let entry = LogEntry::new_error(
+            id,
+            ToolName::from(PROMPTS_GET),
+            request_id,
+            error_message,
+            latency.map(|d| d.as_millis() as u64),
+        )
+        .for_prompt(prompt_name);
+        self.add_entry(entry).await;
+    }
 
-        if let Some(limit) = limit {
-            result.truncate(limit);
+    /// Wait until every entry logged so far has reached the store and the other sinks
+    pub async fn flush(&self) {
+        for filtered in &self.sinks {
+            if let Some(flushed) = filtered.sink().flushed() {
+                let _ = flushed.await;
+            }
         }
+    }
 
-        result
+    pub async fn get_logs(&self, limit: Option<usize>, filter: Option<LogFilter>) -> Vec<LogEntry> {
+        // Queries against a large database can take a while
+        self.flush().await;
+        let store = self.store.clone();
+        let result = tokio::task::spawn_blocking(move || store.query(limit, filter.as_ref())).await;
+        match result {
+            Ok(Ok(entries)) => entries,
+            Ok(Err(e)) => {
+                tracing::warn!("Failed to query log entries: {e}");
+                Vec::new()
+            }
+            Err(e) => {
+                tracing::warn!("Log query panicked: {e}");
+                Vec::new()
+            }
+        }
     }
 
+    /// Remove every entry from the store and the sinks. Ids keep counting up,
+    /// so that sinks never see the same id twice.
     pub async fn clear_logs(&self) {
-        let mut entries = self.entries.write().await;
-        entries.clear();
-
-        let mut next_id = self.next_id.write().await;
-        *next_id = 1;
+        for filtered in &self.sinks {
+            let sink = filtered.sink();
+            if let Err(e) = sink.clear() {
+                tracing::warn!("Failed to clear {}: {e}", sink.name());
+            }
+        }
+        self.flush().await;
 
         tracing::info!("Cleared all logs");
     }
 
     pub async fn get_log_count(&self) -> usize {
-        let entries = self.entries.read().await;
-        entries.len()
+        self.flush().await;
+        self.store.count().unwrap_or_else(|e| {
+            tracing::warn!("Failed to count log entries: {e}");
+            0
+        })
+    }
+
+    /// Number of entries kept before the oldest ones are dropped
+    pub fn max_entries(&self) -> usize {
+        self.max_entries
+    }
+
+    /// Directory entries are persisted to, if any
+    pub fn persist_dir(&self) -> Option<&std::path::Path> {
+        self.persist_dir.as_deref()
+    }
+
+    /// SQLite database entries are kept in, if any
+    pub fn db_path(&self) -> Option<&std::path::Path> {
+        self.db_path.as_deref()
+    }
+
+    /// Where transports should record the frames they carry, if raw logging is enabled
+    pub fn wire_tap(&self) -> Option<WireTap> {
+        self.wire_tap.clone()
     }
 
     pub async fn set_ansi_removal(&self, enabled: bool) {
@@ -0,0 +1,87 @@
+use std::collections::VecDeque;
+use std::sync::RwLock;
+
+use super::{LogStore, LogStoreError};
+use crate::logging::filter::Keyword;
+use crate::logging::{LogEntry, LogFilter};
+
+/// Keeps entries in an in-memory ring buffer
+#[derive(Debug)]
+pub struct MemoryStore {
+    entries: RwLock<VecDeque<LogEntry>>,
+    max_entries: usize,
+}
+
+impl MemoryStore {
+    pub fn new(max_entries: usize) -> Self {
+        Self::with_entries(max_entries, Vec::new())
+    }
+
+    /// Start out with entries loaded from elsewhere, oldest first
+    pub fn with_entries(max_entries: usize, entries: Vec<LogEntry>) -> Self {
+        let mut entries = VecDeque::from(entries);
+        let excess = entries.len().saturating_sub(max_entries);
+        entries.drain(..excess);
+        Self {
+            entries: RwLock::new(entries),
+            max_entries,
+        }
+    }
+}
+
+impl LogStore for MemoryStore {
+    fn insert(&self, entry: &LogEntry) -> Result<(), LogStoreError> {
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        entries.push_back(entry.clone());
+        if entries.len() > self.max_entries {
+            let remove_count = entries.len() - self.max_entries;
+            entries.drain(..remove_count);
+            tracing::debug!("Trimmed {remove_count} old log entries");
+        }
+        Ok(())
+    }
+
+    fn query(
+        &self,
+        limit: Option<usize>,
+        filter: Option<&LogFilter>,
+    ) -> Result<Vec<LogEntry>, LogStoreError> {
+        let keyword = filter
+            .and_then(|filter| filter.keyword.as_deref())
+            .map(Keyword::new);
+        let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+
+        // Entries are kept in the order they were logged, so walk them backwards
+        // and stop as soon as there are enough
+        Ok(entries
+            .iter()
+            .rev()
+            .filter(|entry| {
+                filter.is_none_or(|filter| entry.filter_with_keyword(filter, keyword.as_ref()))
+            })
+            .take(limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect())
+    }
+
+    fn count(&self) -> Result<usize, LogStoreError> {
+        Ok(self.entries.read().unwrap_or_else(|e| e.into_inner()).len())
+    }
+
+    fn clear(&self) -> Result<(), LogStoreError> {
+        self.entries
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+        Ok(())
+    }
+
+    fn max_id(&self) -> Result<usize, LogStoreError> {
+        let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+        Ok(entries
+            .iter()
+            .map(|entry| entry.id.inner())
+            .max()
+            .unwrap_or(0))
+    }
+}
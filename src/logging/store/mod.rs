@@ -0,0 +1,43 @@
+mod memory;
+mod sqlite;
+
+use std::fmt;
+
+use crate::logging::{LogEntry, LogFilter};
+
+pub use memory::MemoryStore;
+pub use sqlite::SqliteStore;
+
+#[derive(Debug, thiserror::Error)]
+pub enum LogStoreError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error("Failed to encode or decode log entry: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Where log entries are kept. Each store holds at most a fixed number of entries
+/// and drops the oldest ones beyond that.
+///
+/// Calls may block, so async code runs queries on the blocking thread pool.
+pub trait LogStore: fmt::Debug + Send + Sync {
+    fn insert(&self, entry: &LogEntry) -> Result<(), LogStoreError>;
+
+    /// Entries matching the filter, newest first
+    fn query(
+        &self,
+        limit: Option<usize>,
+        filter: Option<&LogFilter>,
+    ) -> Result<Vec<LogEntry>, LogStoreError>;
+
+    fn count(&self) -> Result<usize, LogStoreError>;
+
+    fn clear(&self) -> Result<(), LogStoreError>;
+
+    /// Highest entry id held, 0 when empty
+    fn max_id(&self) -> Result<usize, LogStoreError>;
+}
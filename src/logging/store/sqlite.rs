@@ -0,0 +1,408 @@
+use chrono::DateTime;
+use regex::Regex;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{Connection, OptionalExtension, params, params_from_iter};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use super::{LogStore, LogStoreError};
+use crate::logging::filter::Keyword;
+use crate::logging::{LogEntry, LogEntryContent, LogEntryType, LogFilter};
+use crate::types::RequestId;
+
+/// Shortest literal the trigram index can look up; shorter ones are scanned for
+const TRIGRAM_MIN_CHARS: usize = 3;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS log_entries (
+        seq        INTEGER PRIMARY KEY,
+        id         INTEGER NOT NULL,
+        timestamp  INTEGER NOT NULL,
+        entry_type TEXT NOT NULL,
+        tool_name  TEXT,
+        request_id INTEGER,
+        content    TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS log_entries_tool_name ON log_entries (tool_name);
+    CREATE INDEX IF NOT EXISTS log_entries_entry_type ON log_entries (entry_type);
+    CREATE INDEX IF NOT EXISTS log_entries_timestamp ON log_entries (timestamp);
+    CREATE INDEX IF NOT EXISTS log_entries_request_id ON log_entries (request_id);
+    CREATE VIRTUAL TABLE IF NOT EXISTS log_entries_fts USING fts5 (
+        content,
+        content = '',
+        contentless_delete = 1,
+        tokenize = 'trigram case_sensitive 1'
+    );
+";
+
+/// Keeps entries in an SQLite database, indexed by tool name, entry type, timestamp
+/// and request id, with a trigram full-text index over the serialized content
+#[derive(Debug)]
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+    max_entries: usize,
+}
+
+impl SqliteStore {
+    /// Open the database at `path`, creating it and its tables if needed
+    pub fn open(path: &Path, max_entries: usize) -> Result<Self, LogStoreError> {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        Self::init(Connection::open(path)?, max_entries)
+    }
+
+    /// A database that lives only as long as the store, for tests
+    #[cfg(test)]
+    pub fn open_in_memory(max_entries: usize) -> Result<Self, LogStoreError> {
+        Self::init(Connection::open_in_memory()?, max_entries)
+    }
+
+    fn init(conn: Connection, max_entries: usize) -> Result<Self, LogStoreError> {
+        // Every insert is its own transaction; WAL keeps them cheap
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        conn.execute_batch(SCHEMA)?;
+        register_regexp(&conn)?;
+
+        let store = Self {
+            conn: Mutex::new(conn),
+            max_entries,
+        };
+        {
+            // The limit may have been lowered since the database was written
+            let conn = store.lock();
+            let newest: Option<i64> =
+                conn.query_row("SELECT MAX(seq) FROM log_entries", [], |row| row.get(0))?;
+            store.trim(&conn, newest.unwrap_or(0))?;
+        }
+        Ok(store)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Delete the oldest entries beyond the limit, given the newest one's sequence number
+    fn trim(&self, conn: &Connection, newest: i64) -> Result<(), LogStoreError> {
+        let cutoff = newest - self.max_entries as i64;
+        if cutoff <= 0 {
+            return Ok(());
+        }
+
+        let removed = conn.execute("DELETE FROM log_entries WHERE seq <= ?1", [cutoff])?;
+        if removed > 0 {
+            conn.execute("DELETE FROM log_entries_fts WHERE rowid <= ?1", [cutoff])?;
+            tracing::debug!("Trimmed {removed} old log entries");
+        }
+        Ok(())
+    }
+}
+
+impl LogStore for SqliteStore {
+    fn insert(&self, entry: &LogEntry) -> Result<(), LogStoreError> {
+        let content = serde_json::to_string(&entry.content)?;
+        let timestamp = entry.timestamp.timestamp_nanos_opt().unwrap_or(i64::MAX);
+        let entry_type = LogEntryType::from(&entry.content).to_string();
+
+        let mut conn = self.lock();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO log_entries (id, timestamp, entry_type, tool_name, request_id, content)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                entry.id.inner() as i64,
+                timestamp,
+                entry_type,
+                entry.content.tool_name(),
+                entry.request_id().map(|id| id.inner() as i64),
+                content,
+            ],
+        )?;
+        let seq = tx.last_insert_rowid();
+        tx.execute(
+            "INSERT INTO log_entries_fts (rowid, content) VALUES (?1, ?2)",
+            params![seq, content],
+        )?;
+        self.trim(&tx, seq)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn query(
+        &self,
+        limit: Option<usize>,
+        filter: Option<&LogFilter>,
+    ) -> Result<Vec<LogEntry>, LogStoreError> {
+        let mut conditions = Vec::new();
+        let mut values: Vec<SqlValue> = Vec::new();
+
+        if let Some(filter) = filter {
+            if let Some(tool_name) = &filter.tool_name {
+                conditions.push("tool_name = ?");
+                values.push(tool_name.clone().into());
+            }
+            if let Some(entry_type) = &filter.entry_type {
+                conditions.push("entry_type = ?");
+                values.push(entry_type.clone().into());
+            }
+            if let Some(after) = filter.after {
+                conditions.push("timestamp > ?");
+                values.push(after.timestamp_nanos_opt().unwrap_or(i64::MIN).into());
+            }
+            if let Some(before) = filter.before {
+                conditions.push("timestamp < ?");
+                values.push(before.timestamp_nanos_opt().unwrap_or(i64::MAX).into());
+            }
+            if let Some(request_id) = filter.request_id {
+                conditions.push("request_id = ?");
+                values.push((request_id.inner() as i64).into());
+            }
+            if let Some(keyword) = &filter.keyword {
+                match Keyword::new(keyword) {
+                    Keyword::Literal(literal) if literal.chars().count() >= TRIGRAM_MIN_CHARS => {
+                        conditions.push(
+                            "seq IN (SELECT rowid FROM log_entries_fts WHERE log_entries_fts MATCH ?)",
+                        );
+                        // A quoted phrase matches the literal as a substring
+                        values.push(format!("\"{}\"", literal.replace('"', "\"\"")).into());
+                    }
+                    Keyword::Literal(literal) => {
+                        conditions.push("instr(content, ?) > 0");
+                        values.push(literal.into());
+                    }
+                    Keyword::Regex(re) => {
+                        conditions.push("content REGEXP ?");
+                        values.push(re.as_str().to_string().into());
+                    }
+                }
+            }
+        }
+
+        let mut sql = String::from("SELECT id, timestamp, content FROM log_entries");
+        if !conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&conditions.join(" AND "));
+        }
+        sql.push_str(" ORDER BY seq DESC");
+        if let Some(limit) = limit {
+            sql.push_str(" LIMIT ?");
+            values.push((limit.min(i64::MAX as usize) as i64).into());
+        }
+
+        let conn = self.lock();
+        let mut stmt = conn.prepare_cached(&sql)?;
+        let rows = stmt.query_map(params_from_iter(values), |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+
+        let mut entries = Vec::new();
+        for row in rows {
+            let (id, timestamp, content) = row?;
+            entries.push(LogEntry {
+                id: RequestId::new(id as usize),
+                timestamp: DateTime::from_timestamp_nanos(timestamp),
+                content: serde_json::from_str::<LogEntryContent>(&content)?,
+            });
+        }
+        Ok(entries)
+    }
+
+    fn count(&self) -> Result<usize, LogStoreError> {
+        let count: i64 = self
+            .lock()
+            .query_row("SELECT COUNT(*) FROM log_entries", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    fn clear(&self) -> Result<(), LogStoreError> {
+        let mut conn = self.lock();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM log_entries", [])?;
+        tx.execute(
+            "INSERT INTO log_entries_fts (log_entries_fts) VALUES ('delete-all')",
+            [],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn max_id(&self) -> Result<usize, LogStoreError> {
+        let id = self
+            .lock()
+            .query_row(
+                "SELECT id FROM log_entries ORDER BY seq DESC LIMIT 1",
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()?;
+        Ok(id.unwrap_or(0) as usize)
+    }
+}
+
+/// Provide the `REGEXP` operator, compiling each pattern once per statement
+fn register_regexp(conn: &Connection) -> rusqlite::Result<()> {
+    conn.create_scalar_function(
+        "regexp",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let re: Arc<Regex> = ctx.get_or_create_aux(0, |pattern| {
+                Regex::new(pattern.as_str()?)
+                    .map_err(|e| rusqlite::Error::UserFunctionError(Box::new(e)))
+            })?;
+            let text = ctx.get_raw(1).as_str().unwrap_or_default();
+            Ok(re.is_match(text))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ToolName;
+    use serde_json::json;
+
+    fn request(id: usize, tool: &str, arguments: serde_json::Value) -> LogEntry {
+        LogEntry::new_request(RequestId::new(id), ToolName::new(tool), arguments)
+    }
+
+    fn filter() -> LogFilter {
+        LogFilter {
+            tool_name: None,
+            entry_type: None,
+            after: None,
+            before: None,
+            keyword: None,
+            request_id: None,
+        }
+    }
+
+    fn ids(entries: &[LogEntry]) -> Vec<usize> {
+        entries.iter().map(|entry| entry.id.inner()).collect()
+    }
+
+    #[test]
+    fn test_insert_query_and_trim() {
+        let store = SqliteStore::open_in_memory(3).unwrap();
+        for id in 1..=5 {
+            store
+                .insert(&request(id, "echo", json!({"n": id})))
+                .unwrap();
+        }
+
+        assert_eq!(store.count().unwrap(), 3);
+        assert_eq!(store.max_id().unwrap(), 5);
+        let entries = store.query(None, None).unwrap();
+        assert_eq!(ids(&entries), vec![5, 4, 3]);
+        assert_eq!(ids(&store.query(Some(2), None).unwrap()), vec![5, 4]);
+
+        store.clear().unwrap();
+        assert_eq!(store.count().unwrap(), 0);
+        assert_eq!(store.max_id().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_filters_match_memory_semantics() {
+        let store = SqliteStore::open_in_memory(100).unwrap();
+        store
+            .insert(&request(1, "search", json!({"query": "Important doc"})))
+            .unwrap();
+        store
+            .insert(&LogEntry::new_response(
+                RequestId::new(2),
+                ToolName::new("search"),
+                RequestId::new(1),
+                json!({"result": "found"}),
+                Some(12),
+            ))
+            .unwrap();
+        store
+            .insert(&LogEntry::new_stderr(
+                RequestId::new(3),
+                "x: timeout[5]".into(),
+            ))
+            .unwrap();
+
+        let by = |f: LogFilter| ids(&store.query(None, Some(&f)).unwrap());
+        assert_eq!(
+            by(LogFilter {
+                tool_name: Some("search".into()),
+                ..filter()
+            }),
+            vec![2, 1]
+        );
+        assert_eq!(
+            by(LogFilter {
+                entry_type: Some("stderr".into()),
+                ..filter()
+            }),
+            vec![3]
+        );
+        assert_eq!(
+            by(LogFilter {
+                request_id: Some(RequestId::new(1)),
+                ..filter()
+            }),
+            vec![2, 1]
+        );
+        // Literal through the trigram index, case-sensitive like the in-memory search
+        assert_eq!(
+            by(LogFilter {
+                keyword: Some("Important".into()),
+                ..filter()
+            }),
+            vec![1]
+        );
+        assert!(
+            by(LogFilter {
+                keyword: Some("important".into()),
+                ..filter()
+            })
+            .is_empty()
+        );
+        // Regex, and an invalid regex searched for literally
+        assert_eq!(
+            by(LogFilter {
+                keyword: Some(r"(?i)important\s+DOC".into()),
+                ..filter()
+            }),
+            vec![1]
+        );
+        assert_eq!(
+            by(LogFilter {
+                keyword: Some("timeout[".into()),
+                ..filter()
+            }),
+            vec![3]
+        );
+    }
+
+    #[test]
+    fn test_reopen_keeps_entries() {
+        let dir = std::env::temp_dir().join(format!("wrap-mcp-sqlite-{}", std::process::id()));
+        _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("log.db");
+
+        let store = SqliteStore::open(&path, 10).unwrap();
+        for id in 1..=4 {
+            store.insert(&request(id, "echo", json!({}))).unwrap();
+        }
+        drop(store);
+
+        // Reopening with a smaller limit drops the oldest entries
+        let store = SqliteStore::open(&path, 2).unwrap();
+        assert_eq!(ids(&store.query(None, None).unwrap()), vec![4, 3]);
+        assert_eq!(store.max_id().unwrap(), 4);
+
+        drop(store);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
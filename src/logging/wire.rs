@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+use crate::logging::LogStorage;
+use crate::wrappee::MessageKind;
+
+/// Requests remembered while waiting for their answer; an unanswered request is
+/// only forgotten when this many are outstanding
+const MAX_PENDING: usize = 4096;
+
+/// Which side of the proxy a captured frame crossed, and which way it went
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    ClientToProxy,
+    ProxyToClient,
+    ProxyToWrappee,
+    WrappeeToProxy,
+}
+
+impl Direction {
+    /// The way an answer to a frame sent in this direction travels
+    fn reverse(self) -> Self {
+        match self {
+            Direction::ClientToProxy => Direction::ProxyToClient,
+            Direction::ProxyToClient => Direction::ClientToProxy,
+            Direction::ProxyToWrappee => Direction::WrappeeToProxy,
+            Direction::WrappeeToProxy => Direction::ProxyToWrappee,
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Direction::ClientToProxy => write!(f, "client -> proxy"),
+            Direction::ProxyToClient => write!(f, "proxy -> client"),
+            Direction::ProxyToWrappee => write!(f, "proxy -> wrappee"),
+            Direction::WrappeeToProxy => write!(f, "wrappee -> proxy"),
+        }
+    }
+}
+
+/// Hands JSON-RPC frames seen on the wire to the log.
+///
+/// Recording never blocks, so frames can be captured from the wrappee's reader
+/// threads as well as from async transports; a background task logs them in the
+/// order they were recorded.
+#[derive(Debug, Clone)]
+pub struct WireTap {
+    frames: mpsc::UnboundedSender<(Direction, Value)>,
+}
+
+impl WireTap {
+    /// Log captured frames to the storage. Must be called within a Tokio runtime.
+    pub(crate) fn spawn(storage: LogStorage) -> Self {
+        let (frames, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(log_frames(storage, receiver));
+        Self { frames }
+    }
+
+    /// Capture a frame that crossed the wire in the given direction
+    pub fn record(&self, direction: Direction, message: Value) {
+        if self.frames.send((direction, message)).is_err() {
+            tracing::debug!("Wire capture stopped, dropping {direction} frame");
+        }
+    }
+
+    /// Capture a typed message as the JSON it is sent as
+    pub fn record_serialized<T: Serialize>(&self, direction: Direction, message: &T) {
+        match serde_json::to_value(message) {
+            Ok(message) => self.record(direction, message),
+            Err(e) => tracing::warn!("Failed to capture {direction} frame: {e}"),
+        }
+    }
+}
+
+async fn log_frames(
+    storage: LogStorage,
+    mut receiver: mpsc::UnboundedReceiver<(Direction, Value)>,
+) {
+    let mut methods = Methods::default();
+    while let Some((direction, message)) = receiver.recv().await {
+        let method = methods.resolve(direction, &message);
+        storage.add_raw(direction, method, message).await;
+    }
+}
+
+/// Names responses after the request they answer, which they do not carry themselves
+#[derive(Debug, Default)]
+struct Methods {
+    /// Method of each unanswered request, by the direction it was sent in and its id
+    pending: HashMap<(Direction, String), String>,
+}
+
+impl Methods {
+    fn resolve(&mut self, direction: Direction, message: &Value) -> Option<String> {
+        let id = message.get("id").map(Value::to_string);
+        match (MessageKind::of(message), id) {
+            (MessageKind::Request, Some(id)) => {
+                let method = message.get("method")?.as_str()?.to_string();
+                if self.pending.len() >= MAX_PENDING {
+                    tracing::debug!("Too many unanswered requests, forgetting their methods");
+                    self.pending.clear();
+                }
+                self.pending.insert((direction, id), method.clone());
+                Some(method)
+            }
+            (MessageKind::Notification, _) => Some(message.get("method")?.as_str()?.to_string()),
+            (MessageKind::Response, Some(id)) => self.pending.remove(&(direction.reverse(), id)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_responses_are_named_after_their_request() {
+        let mut methods = Methods::default();
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": "tools/call", "params": {}});
+        let response = json!({"jsonrpc": "2.0", "id": 1, "result": {}});
+
+        assert_eq!(
+            methods
+                .resolve(Direction::ClientToProxy, &request)
+                .as_deref(),
+            Some("tools/call")
+        );
+        // The same id on the other side of the proxy is a different request
+        assert_eq!(methods.resolve(Direction::WrappeeToProxy, &response), None);
+        assert_eq!(
+            methods
+                .resolve(Direction::ProxyToClient, &response)
+                .as_deref(),
+            Some("tools/call")
+        );
+        // Answered requests are forgotten
+        assert_eq!(methods.resolve(Direction::ProxyToClient, &response), None);
+
+        let notification = json!({"jsonrpc": "2.0", "method": "notifications/initialized"});
+        assert_eq!(
+            methods
+                .resolve(Direction::ProxyToWrappee, &notification)
+                .as_deref(),
+            Some("notifications/initialized")
+        );
+    }
+}
@@ -1,28 +1,38 @@
 use anyhow::Result;
 use tracing_subscriber::{EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
 use wrap_mcp::{
-    WrapServer,
+    CliOptions, WrapServer,
     config::{Config, LogConfig},
     server::transport,
 };
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Load configuration from environment
-    let config = Config::from_env()?;
+    // Command line > environment > config file > defaults
+    let opts = CliOptions::from_args();
+    let mut config = Config::read(opts.config.as_deref())?;
+    opts.apply(&mut config);
+    config.validate()?;
+    if config.launch.target().is_none() {
+        CliOptions::missing_wrappee_error().exit();
+    }
 
     init_tracing(&config.log);
 
     tracing::info!("Starting Wrap MCP Server");
+    tracing::debug!("Parsed CLI options: {opts:?}");
 
     let transport = &config.transport.transport;
 
     // Create a shared server instance for signal handling
-    let server = WrapServer::new(&config.log, &config.wrappee);
+    let server = WrapServer::new(&config.log, &config.wrappee, &config.launch)?;
 
     // Setup signal handlers with a delay to avoid premature shutdown during initialization
     server.setup_signal_handlers_delayed();
 
+    // Frames exchanged with the client are captured by the transport, if asked to
+    let tap = server.wire_tap();
+
     let service_factory = move || {
         tracing::info!("Creating service instance");
 
@@ -36,8 +46,10 @@ async fn main() -> Result<()> {
     };
 
     match transport.as_str() {
-        "stdio" => transport::run_stdio_server(service_factory).await,
-        "streamable-http" | "http" => transport::run_http_server(service_factory).await,
+        "stdio" => transport::run_stdio_server(service_factory, tap).await,
+        "streamable-http" | "http" => {
+            transport::run_http_server(&config.transport, service_factory, tap).await
+        }
         _ => {
             tracing::error!("Unknown transport: {transport}");
             anyhow::bail!("Unknown transport: {transport}. Use 'stdio' or 'streamable-http'",)
@@ -46,8 +58,9 @@ async fn main() -> Result<()> {
 }
 
 fn init_tracing(log_config: &LogConfig) {
+    // RUST_LOG has already been folded into the config, below --log-level
     let env_filter =
-        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&log_config.rust_log));
+        EnvFilter::try_new(&log_config.rust_log).unwrap_or_else(|_| EnvFilter::new("info"));
 
     // Use ANSI colors from config
     let enable_ansi = log_config.log_colors;
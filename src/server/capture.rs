@@ -0,0 +1,134 @@
+use futures::Stream;
+use rmcp::{
+    RoleServer,
+    model::{ClientJsonRpcMessage, ServerJsonRpcMessage},
+    transport::{
+        Transport,
+        common::server_side_http::ServerSseMessage,
+        streamable_http_server::{SessionId, SessionManager},
+    },
+};
+
+use crate::logging::{Direction, WireTap};
+
+/// Records every frame exchanged with the client on the tap, if there is one
+pub struct CaptureTransport<T> {
+    inner: T,
+    tap: Option<WireTap>,
+}
+
+impl<T> CaptureTransport<T> {
+    pub fn new(inner: T, tap: Option<WireTap>) -> Self {
+        Self { inner, tap }
+    }
+}
+
+impl<T: Transport<RoleServer>> Transport<RoleServer> for CaptureTransport<T> {
+    type Error = T::Error;
+
+    fn send(
+        &mut self,
+        item: ServerJsonRpcMessage,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send + 'static {
+        if let Some(tap) = &self.tap {
+            tap.record_serialized(Direction::ProxyToClient, &item);
+        }
+        self.inner.send(item)
+    }
+
+    async fn receive(&mut self) -> Option<ClientJsonRpcMessage> {
+        let message = self.inner.receive().await?;
+        if let Some(tap) = &self.tap {
+            tap.record_serialized(Direction::ClientToProxy, &message);
+        }
+        Some(message)
+    }
+
+    fn close(&mut self) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        self.inner.close()
+    }
+}
+
+/// Hands out session transports that record the frames of every HTTP session.
+///
+/// Everything an HTTP client sends and receives passes through its session's
+/// transport, the initialize exchange included, so the rest is delegated as is.
+pub struct CaptureSessionManager<M> {
+    inner: M,
+    tap: Option<WireTap>,
+}
+
+impl<M> CaptureSessionManager<M> {
+    pub fn new(inner: M, tap: Option<WireTap>) -> Self {
+        Self { inner, tap }
+    }
+}
+
+impl<M: SessionManager> SessionManager for CaptureSessionManager<M> {
+    type Error = M::Error;
+    type Transport = CaptureTransport<M::Transport>;
+
+    async fn create_session(&self) -> Result<(SessionId, Self::Transport), Self::Error> {
+        let (id, transport) = self.inner.create_session().await?;
+        Ok((id, CaptureTransport::new(transport, self.tap.clone())))
+    }
+
+    fn initialize_session(
+        &self,
+        id: &SessionId,
+        message: ClientJsonRpcMessage,
+    ) -> impl Future<Output = Result<ServerJsonRpcMessage, Self::Error>> + Send {
+        self.inner.initialize_session(id, message)
+    }
+
+    fn has_session(
+        &self,
+        id: &SessionId,
+    ) -> impl Future<Output = Result<bool, Self::Error>> + Send {
+        self.inner.has_session(id)
+    }
+
+    fn close_session(
+        &self,
+        id: &SessionId,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        self.inner.close_session(id)
+    }
+
+    fn create_stream(
+        &self,
+        id: &SessionId,
+        message: ClientJsonRpcMessage,
+    ) -> impl Future<
+        Output = Result<impl Stream<Item = ServerSseMessage> + Send + Sync + 'static, Self::Error>,
+    > + Send {
+        self.inner.create_stream(id, message)
+    }
+
+    fn accept_message(
+        &self,
+        id: &SessionId,
+        message: ClientJsonRpcMessage,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        self.inner.accept_message(id, message)
+    }
+
+    fn create_standalone_stream(
+        &self,
+        id: &SessionId,
+    ) -> impl Future<
+        Output = Result<impl Stream<Item = ServerSseMessage> + Send + Sync + 'static, Self::Error>,
+    > + Send {
+        self.inner.create_standalone_stream(id)
+    }
+
+    fn resume(
+        &self,
+        id: &SessionId,
+        last_event_id: String,
+    ) -> impl Future<
+        Output = Result<impl Stream<Item = ServerSseMessage> + Send + Sync + 'static, Self::Error>,
+    > + Send {
+        self.inner.resume(id, last_event_id)
+    }
+}
@@ -2,16 +2,18 @@ use super::wrap_server::WrapServer;
 use anyhow::Result;
 use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use std::path::Path;
+use std::sync::atomic::Ordering;
 use tokio::sync::mpsc;
 use tokio::time::{Duration, Instant};
 
 impl WrapServer {
     pub(crate) async fn start_file_watching(&self) -> Result<()> {
-        // Only clone if we actually have a command to watch
-        let binary_path = {
-            let command_guard = self.wrappee_controller.command.read().await;
-            command_guard.as_ref().cloned()
-        };
+        // Only local wrappee processes have a binary to watch
+        let binary_path = self
+            .wrappee_controller
+            .get_target()
+            .await
+            .and_then(|target| target.binary_path().map(str::to_string));
 
         if let Some(binary_path) = binary_path {
             tracing::info!("Starting file watch for: {binary_path}");
@@ -76,6 +78,7 @@ impl WrapServer {
 
             // Keep watcher alive by storing it
             std::mem::forget(watcher);
+            self.file_watching.store(true, Ordering::SeqCst);
 
             // Spawn debounced restart handler
             let server = self.clone();
@@ -95,19 +98,20 @@ impl WrapServer {
                                     file_deleted = true;
                                     pending_restart = false;  // Cancel any pending restart
                                 }
-                                EventKind::Create(_) if file_deleted || initial_start_needed => {
-                                    // Check if the created file is our binary
-                                    if std::path::Path::new(&binary_path_clone).exists() {
-                                        if initial_start_needed {
-                                            tracing::info!("Binary file created for the first time, scheduling initial start");
-                                            initial_start_needed = false;
-                                        } else {
-                                            tracing::info!("Binary file recreated, scheduling restart");
-                                        }
-                                        file_deleted = false;
-                                        last_event = Instant::now();
-                                        pending_restart = true;
+                                // Only react if the created file is our binary
+                                EventKind::Create(_)
+                                    if (file_deleted || initial_start_needed)
+                                        && std::path::Path::new(&binary_path_clone).exists() =>
+                                {
+                                    if initial_start_needed {
+                                        tracing::info!("Binary file created for the first time, scheduling initial start");
+                                        initial_start_needed = false;
+                                    } else {
+                                        tracing::info!("Binary file recreated, scheduling restart");
                                     }
+                                    file_deleted = false;
+                                    last_event = Instant::now();
+                                    pending_restart = true;
                                 }
                                 EventKind::Modify(_) if !file_deleted && !initial_start_needed => {
                                     tracing::debug!("Binary file modified, scheduling restart");
@@ -132,7 +136,7 @@ impl WrapServer {
                                         let old_pid = server.get_wrappee_pid().await;
 
                                         // Perform restart
-                                        if let Err(e) = server.restart_wrapped_server().await {
+                                        if let Err(e) = server.restart_wrapped_server("binary changed").await {
                                             tracing::error!("Failed to restart wrapped server: {e:?}");
                                         } else {
                                             // Get new PID after restart
@@ -143,12 +147,14 @@ impl WrapServer {
                                         // Initial start - no existing wrappee to shut down
                                         tracing::info!("Binary file now exists, performing initial start");
 
-                                        // Get stored command and args without cloning
-                                        if let Some((cmd, args, disable_colors)) = server.wrappee_controller.get_command().await {
+                                        // Nothing else may start a wrappee meanwhile, such as the supervisor
+                                        let _lifecycle = server.wrappee_controller.lock_lifecycle().await;
+                                        if server.wrappee_controller.is_active().await {
+                                            tracing::info!("Wrappee was started in the meantime, skipping initial start");
+                                        } else if let Some(target) = server.wrappee_controller.get_target().await {
                                             // Start the wrappee
-                                            match server.start_wrappee_internal(&cmd, &args, disable_colors).await {
-                                                Ok(wrappee_client) => {
-                                                    server.wrappee_controller.set_client(Some(wrappee_client)).await;
+                                            match server.wrappee_controller.start_current(&target, &server.tool_manager).await {
+                                                Ok(()) => {
                                                     server.start_stderr_monitoring();
 
                                                     // Get PID of newly started process
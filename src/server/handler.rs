@@ -1,22 +1,195 @@
 use super::wrap_server::WrapServer;
-use rmcp::{ErrorData as McpError, RoleServer, ServerHandler, model::*, service::RequestContext};
+use super::wrappee::MissedClientInfo;
+use crate::config::{InstructionsMode, SUPPORTED_PROTOCOL_VERSIONS, WrappeeConfig};
+use crate::types::Negotiation;
+use rmcp::{
+    ErrorData as McpError, RoleServer, ServerHandler,
+    model::*,
+    service::{NotificationContext, RequestContext},
+};
+use serde_json::Value;
+use tokio::time::Duration;
 
-impl ServerHandler for WrapServer {
-    fn get_info(&self) -> ServerInfo {
-        ServerInfo {
+const PROXY_INSTRUCTIONS: &str = "This is a transparent MCP wrapper that logs all requests/responses while proxying to a wrapped MCP server.";
+
+/// Convert optional pagination parameters into a JSON-RPC params object
+fn paginated_params(request: Option<PaginatedRequestParam>) -> Value {
+    request
+        .and_then(|r| serde_json::to_value(r).ok())
+        .unwrap_or_else(|| Value::Object(serde_json::Map::new()))
+}
+
+/// Protocol version to answer a client with when no wrappee version is known:
+/// the requested one if we speak it, our newest one otherwise
+fn fallback_protocol_version(requested: &ProtocolVersion) -> ProtocolVersion {
+    let requested = requested.to_string();
+    let version = SUPPORTED_PROTOCOL_VERSIONS
+        .iter()
+        .find(|v| **v == requested)
+        .unwrap_or(&SUPPORTED_PROTOCOL_VERSIONS[0]);
+    serde_json::from_value(Value::from(*version)).unwrap_or_default()
+}
+
+/// Present the wrappee's initialize result as our own, adjusted for what the proxy adds
+fn server_info(wrappee: Option<InitializeResult>, config: &WrappeeConfig) -> ServerInfo {
+    let proxy_info = Implementation {
+        name: "Wrap-MCP".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+    };
+
+    let Some(wrappee) = wrappee else {
+        return ServerInfo {
             protocol_version: ProtocolVersion::V_2025_03_26,
             capabilities: ServerCapabilities::builder()
                 .enable_tools()
                 .enable_tool_list_changed()
                 .build(),
-            server_info: Implementation {
-                name: "Wrap-MCP".to_string(),
-                version: env!("CARGO_PKG_VERSION").to_string(),
-            },
-            instructions: Some(
-                "This is a transparent MCP wrapper that logs all requests/responses while proxying to a wrapped MCP server."
-                    .to_string(),
-            ),
+            server_info: proxy_info,
+            instructions: Some(PROXY_INSTRUCTIONS.to_string()),
+        };
+    };
+
+    // Our own tools are always there, and the list changes whenever the wrappee restarts
+    let capabilities = ServerCapabilities {
+        tools: Some(ToolsCapability {
+            list_changed: Some(true),
+        }),
+        // Experimental requests are not proxied
+        experimental: None,
+        ..wrappee.capabilities
+    };
+
+    let instructions = match (config.instructions_mode, wrappee.instructions) {
+        (InstructionsMode::Proxy, _) | (InstructionsMode::Append, None) => {
+            Some(PROXY_INSTRUCTIONS.to_string())
+        }
+        (InstructionsMode::Passthrough, instructions) => instructions,
+        (InstructionsMode::Append, Some(instructions)) => {
+            Some(format!("{instructions}\n\n{PROXY_INSTRUCTIONS}"))
+        }
+    };
+
+    ServerInfo {
+        protocol_version: wrappee.protocol_version,
+        capabilities,
+        server_info: if config.passthrough_server_info {
+            wrappee.server_info
+        } else {
+            proxy_info
+        },
+        instructions,
+    }
+}
+
+impl ServerHandler for WrapServer {
+    fn get_info(&self) -> ServerInfo {
+        server_info(
+            self.wrappee_controller.wrappee_info(),
+            &self.wrappee_controller.config,
+        )
+    }
+
+    async fn initialize(
+        &self,
+        request: InitializeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<InitializeResult, McpError> {
+        // Record these for the wrappee handshake, which may still be about to start
+        self.wrappee_controller.set_client_info(request.clone());
+
+        let requested = request.protocol_version.clone();
+        let implementation = format!(
+            "{} {}",
+            request.client_info.name, request.client_info.version
+        );
+        if context.peer.peer_info().is_none() {
+            context.peer.set_peer_info(request);
+        }
+
+        // Store peer for future notifications
+        *self.peer.write().await = Some(context.peer.clone());
+
+        // Give the wrappee a chance to start so that its capabilities can be mirrored
+        let timeout = Duration::from_secs(self.wrappee_controller.config.startup_timeout_secs);
+        if !self
+            .wrappee_controller
+            .wait_for_initialization(timeout)
+            .await
+        {
+            tracing::warn!("Wrappee not initialized yet, advertising tools capability only");
+        }
+
+        // A wrappee that started before any client was offered the configured protocol
+        // version and nothing about sampling or roots, so bring it up again with what
+        // this client asked for
+        match self.wrappee_controller.take_missed_client_info() {
+            Some(MissedClientInfo::ProtocolVersion(version)) => {
+                // Waited for, so that the answer below carries the renegotiated version;
+                // the client lists tools once initialized, so it is not notified
+                let reason = format!("client requested protocol {version}");
+                match self
+                    .wrappee_controller
+                    .restart(&self.tool_manager, &reason)
+                    .await
+                {
+                    Ok(_) => self.start_stderr_monitoring(),
+                    Err(e) => {
+                        tracing::warn!("Failed to restart wrappee for protocol {version}: {e}")
+                    }
+                }
+            }
+            Some(MissedClientInfo::Capabilities) => {
+                let server = self.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = server
+                        .restart_wrapped_server("client capabilities arrived after start")
+                        .await
+                    {
+                        tracing::warn!("Failed to restart wrappee for client capabilities: {e:?}");
+                    }
+                });
+            }
+            None => {}
+        }
+
+        // Answer with whatever the wrappee agreed to, so both legs speak the same version
+        let mut info = self.get_info();
+        if self.wrappee_controller.wrappee_info().is_none() {
+            info.protocol_version = fallback_protocol_version(&requested);
+        }
+        if info.protocol_version != requested {
+            tracing::warn!(
+                "Client requested protocol {requested}, answering with {}",
+                info.protocol_version
+            );
+        }
+
+        self.tool_manager
+            .log_storage
+            .add_handshake(
+                "client",
+                Some(implementation),
+                requested.to_string(),
+                info.protocol_version.to_string(),
+            )
+            .await;
+        self.wrappee_controller
+            .set_client_negotiation(Negotiation {
+                requested: requested.to_string(),
+                negotiated: info.protocol_version.to_string(),
+            })
+            .await;
+
+        Ok(info)
+    }
+
+    async fn on_roots_list_changed(&self, _context: NotificationContext<RoleServer>) {
+        if let Err(e) = self
+            .wrappee_controller
+            .notify("notifications/roots/list_changed")
+            .await
+        {
+            tracing::warn!("Failed to forward roots/list_changed to wrappee: {e}");
         }
     }
 
@@ -49,6 +222,204 @@ impl ServerHandler for WrapServer {
             .unwrap_or(serde_json::Value::Object(serde_json::Map::new()));
 
         // Delegate all tool calls to the unified handler
-        self.handle_tool_call(&request.name, arguments).await
+        self.handle_tool_call(&request.name, arguments, &context)
+            .await
+    }
+
+    async fn list_resources(
+        &self,
+        request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, McpError> {
+        self.proxy_request("resources/list", paginated_params(request))
+            .await
+    }
+
+    async fn list_resource_templates(
+        &self,
+        request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, McpError> {
+        self.proxy_request("resources/templates/list", paginated_params(request))
+            .await
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        self.proxy_resource_read(request).await
+    }
+
+    async fn subscribe(
+        &self,
+        request: SubscribeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        // Store peer so that resource update notifications can be relayed
+        *self.peer.write().await = Some(context.peer.clone());
+
+        let _: Value = self
+            .proxy_request(
+                "resources/subscribe",
+                serde_json::to_value(&request).unwrap_or_default(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn unsubscribe(
+        &self,
+        request: UnsubscribeRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        let _: Value = self
+            .proxy_request(
+                "resources/unsubscribe",
+                serde_json::to_value(&request).unwrap_or_default(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn set_level(
+        &self,
+        request: SetLevelRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        // Store peer so that log messages can be relayed
+        *self.peer.write().await = Some(context.peer.clone());
+
+        let _: Value = self
+            .proxy_request(
+                "logging/setLevel",
+                serde_json::to_value(&request).unwrap_or_default(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn list_prompts(
+        &self,
+        request: Option<PaginatedRequestParam>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ListPromptsResult, McpError> {
+        // Store peer so that prompt list changes can be relayed
+        *self.peer.write().await = Some(context.peer.clone());
+
+        self.proxy_request("prompts/list", paginated_params(request))
+            .await
+    }
+
+    async fn get_prompt(
+        &self,
+        request: GetPromptRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<GetPromptResult, McpError> {
+        self.proxy_prompt_get(request).await
+    }
+
+    async fn complete(
+        &self,
+        request: CompleteRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<CompleteResult, McpError> {
+        self.proxy_request(
+            "completion/complete",
+            serde_json::to_value(&request).unwrap_or_default(),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wrappee_info(instructions: Option<&str>) -> InitializeResult {
+        InitializeResult {
+            protocol_version: ProtocolVersion::V_2024_11_05,
+            capabilities: ServerCapabilities::builder()
+                .enable_tools()
+                .enable_prompts()
+                .enable_resources()
+                .enable_resources_subscribe()
+                .enable_completions()
+                .build(),
+            server_info: Implementation {
+                name: "inner".to_string(),
+                version: "1.2.3".to_string(),
+            },
+            instructions: instructions.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_fallback_protocol_version() {
+        assert_eq!(
+            fallback_protocol_version(&ProtocolVersion::V_2024_11_05),
+            ProtocolVersion::V_2024_11_05
+        );
+        let unknown: ProtocolVersion = serde_json::from_value(Value::from("2099-01-01")).unwrap();
+        assert_eq!(
+            fallback_protocol_version(&unknown).to_string(),
+            SUPPORTED_PROTOCOL_VERSIONS[0]
+        );
+    }
+
+    #[test]
+    fn test_server_info_without_wrappee() {
+        let info = server_info(None, &WrappeeConfig::default());
+        assert_eq!(info.server_info.name, "Wrap-MCP");
+        assert_eq!(info.instructions.as_deref(), Some(PROXY_INSTRUCTIONS));
+        assert!(info.capabilities.prompts.is_none());
+    }
+
+    #[test]
+    fn test_server_info_mirrors_wrappee() {
+        let info = server_info(
+            Some(wrappee_info(Some("Use inner"))),
+            &WrappeeConfig::default(),
+        );
+        assert_eq!(info.protocol_version, ProtocolVersion::V_2024_11_05);
+        assert_eq!(info.server_info.name, "Wrap-MCP");
+        assert!(info.capabilities.prompts.is_some());
+        assert!(info.capabilities.completions.is_some());
+        assert_eq!(
+            info.capabilities.resources.and_then(|r| r.subscribe),
+            Some(true)
+        );
+        assert_eq!(
+            info.capabilities.tools.and_then(|t| t.list_changed),
+            Some(true)
+        );
+        assert_eq!(
+            info.instructions,
+            Some(format!("Use inner\n\n{PROXY_INSTRUCTIONS}"))
+        );
+    }
+
+    #[test]
+    fn test_server_info_modes() {
+        let config = WrappeeConfig {
+            instructions_mode: InstructionsMode::Passthrough,
+            passthrough_server_info: true,
+            ..WrappeeConfig::default()
+        };
+        let info = server_info(Some(wrappee_info(Some("Use inner"))), &config);
+        assert_eq!(info.instructions.as_deref(), Some("Use inner"));
+        assert_eq!(info.server_info.name, "inner");
+        assert_eq!(info.server_info.version, "1.2.3");
+
+        let info = server_info(Some(wrappee_info(None)), &config);
+        assert!(info.instructions.is_none());
+
+        let config = WrappeeConfig {
+            instructions_mode: InstructionsMode::Proxy,
+            ..WrappeeConfig::default()
+        };
+        let info = server_info(Some(wrappee_info(Some("Use inner"))), &config);
+        assert_eq!(info.instructions.as_deref(), Some(PROXY_INSTRUCTIONS));
     }
 }
@@ -0,0 +1,83 @@
+use super::wrap_server::WrapServer;
+use std::sync::atomic::Ordering;
+use tokio::time::{Duration, MissedTickBehavior};
+
+impl WrapServer {
+    /// Ping whichever wrappee is current at a fixed interval to notice hangs
+    pub(crate) fn start_health_monitoring(&self) {
+        let interval_secs = self.wrappee_controller.config.health.interval_secs;
+        if interval_secs == 0 {
+            tracing::info!("Wrappee health checks disabled");
+            return;
+        }
+
+        let server = self.clone();
+        let interval = Duration::from_secs(interval_secs);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            // The first tick completes immediately, right after the wrappee started
+            ticker.tick().await;
+
+            loop {
+                ticker.tick().await;
+                if server.shutting_down.load(Ordering::SeqCst) {
+                    break;
+                }
+                // A ping that is not answered before the next one is due has failed
+                server.check_health(interval).await;
+            }
+            tracing::debug!("Health monitoring task ended");
+        });
+    }
+
+    /// Ping the current wrappee once, logging and acting on changes in its health
+    async fn check_health(&self, timeout: Duration) {
+        let controller = &self.wrappee_controller;
+        let Some(wrappee) = controller.get_client().await else {
+            return;
+        };
+
+        let outcome = wrappee.ping(timeout).await.map_err(|e| e.to_string());
+        if let Err(e) = &outcome {
+            tracing::warn!("Wrappee did not answer ping: {e}");
+        }
+
+        // The wrappee may have been replaced while the ping was outstanding
+        if !controller.is_current(&wrappee).await {
+            return;
+        }
+        let Some(health) = controller.record_ping(outcome).await else {
+            return;
+        };
+
+        let log_storage = &self.tool_manager.log_storage;
+        if health.healthy {
+            tracing::info!("Wrappee is answering pings again");
+            log_storage.add_health(true, 0, None).await;
+            return;
+        }
+
+        tracing::error!(
+            "Wrappee unhealthy after {} failed pings",
+            health.consecutive_failures
+        );
+        log_storage
+            .add_health(false, health.consecutive_failures, health.last_error)
+            .await;
+
+        if controller.config.health.restart {
+            let reason = format!("unhealthy ({} failed pings)", health.consecutive_failures);
+            let Some(outcome) = controller
+                .restart_unless_busy(&self.tool_manager, &reason)
+                .await
+            else {
+                tracing::info!("Not restarting unhealthy wrappee, it is already being restarted");
+                return;
+            };
+            if let Err(e) = self.finish_restart(outcome).await {
+                tracing::error!("Failed to restart unhealthy wrappee: {}", e.message);
+            }
+        }
+    }
+}
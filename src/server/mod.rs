@@ -1,5 +1,8 @@
+mod capture;
 mod file_watcher;
 mod handler;
+mod health;
+mod supervisor;
 pub mod transport;
 mod wrap_server;
 mod wrappee;
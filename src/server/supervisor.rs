@@ -0,0 +1,107 @@
+use super::wrap_server::WrapServer;
+use crate::config::RestartPolicy;
+use crate::wrappee::{WrappeeClient, WrappeeExit};
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use tokio::time::Duration;
+
+impl WrapServer {
+    /// Record that a wrappee went away and bring it back according to the restart policy
+    pub(crate) async fn handle_wrappee_exit(
+        &self,
+        wrappee: &Arc<WrappeeClient>,
+        exit: WrappeeExit,
+    ) {
+        // Only the current wrappee is supervised; replaced or stopped ones went away on
+        // purpose, and their exit is logged by the shutdown
+        if self.shutting_down.load(Ordering::SeqCst)
+            || !self.wrappee_controller.take_client_if(wrappee).await
+        {
+            tracing::debug!("Wrappee went away as requested ({exit})");
+            return;
+        }
+        tracing::warn!("Wrappee exited unexpectedly: {exit}");
+        self.tool_manager
+            .log_storage
+            .add_exited(exit.code, exit.signal, None)
+            .await;
+
+        let restart = &self.wrappee_controller.config.restart;
+        let should_restart = match restart.policy {
+            RestartPolicy::Never => false,
+            RestartPolicy::OnFailure => !exit.success(),
+            RestartPolicy::Always => true,
+        };
+        if !should_restart {
+            tracing::info!("Not restarting wrappee (policy: {:?})", restart.policy);
+            self.wrappee_controller
+                .mark_down(format!("wrappee exited ({exit}) and is not restarted"));
+            self.drop_wrappee_tools().await;
+            return;
+        }
+        self.wrappee_controller.mark_starting();
+
+        // A wrappee that stayed up for a while is not crash looping
+        if wrappee.uptime() >= Duration::from_millis(restart.backoff_max_ms) {
+            self.wrappee_controller.reset_restart_attempts();
+        }
+
+        loop {
+            let attempt = self.wrappee_controller.next_restart_attempt();
+            if attempt > restart.max_attempts {
+                tracing::error!(
+                    "Giving up restarting wrappee after {} attempts",
+                    restart.max_attempts
+                );
+                self.wrappee_controller.mark_down(format!(
+                    "restart failed after {} attempts",
+                    restart.max_attempts
+                ));
+                self.drop_wrappee_tools().await;
+                return;
+            }
+
+            let delay = restart.backoff(attempt);
+            tracing::info!(
+                "Restarting wrappee in {delay:?} (attempt {attempt}/{})",
+                restart.max_attempts
+            );
+            tokio::time::sleep(delay).await;
+
+            // Someone may have restarted or stopped the wrappee in the meantime; the
+            // lifecycle lock keeps them from doing so between the check and the start
+            let _lifecycle = self.wrappee_controller.lock_lifecycle().await;
+            if self.shutting_down.load(Ordering::SeqCst)
+                || self.wrappee_controller.is_active().await
+            {
+                return;
+            }
+            let Some(target) = self.wrappee_controller.get_target().await else {
+                return;
+            };
+
+            match self
+                .wrappee_controller
+                .start_current(&target, &self.tool_manager)
+                .await
+            {
+                Ok(()) => {
+                    self.wrappee_controller
+                        .record_restart(format!("exited ({exit})"))
+                        .await;
+                    self.start_stderr_monitoring();
+                    self.notify_tools_changed().await;
+                    tracing::info!("Wrappee recovered after {attempt} restart attempt(s)");
+                    return;
+                }
+                Err(e) => tracing::warn!("Restart attempt {attempt} failed: {e}"),
+            }
+        }
+    }
+
+    /// Stop offering the tools of a wrappee that is not coming back
+    async fn drop_wrappee_tools(&self) {
+        self.tool_manager.clear_tools().await;
+        self.notify_tools_changed().await;
+    }
+}
@@ -1,21 +1,30 @@
-use anyhow::Result;
+use super::capture::{CaptureSessionManager, CaptureTransport};
+use crate::config::TransportConfig;
+use crate::logging::WireTap;
+use anyhow::{Context, Result};
 use rmcp::{
     RoleServer, Service, ServiceExt,
     transport::{
-        stdio,
+        async_rw::AsyncRwTransport,
         streamable_http_server::{StreamableHttpService, session::local::LocalSessionManager},
     },
 };
+#[cfg(unix)]
+use std::path::Path;
 
+/// Serve one client over stdio, recording its frames on the tap if one is given
 pub async fn run_stdio_server<S>(
     service_factory: impl Fn() -> Result<S, std::io::Error> + Send + Sync + 'static,
+    tap: Option<WireTap>,
 ) -> Result<()>
 where
     S: Service<RoleServer> + Send + 'static,
 {
     tracing::info!("Initializing stdio transport");
 
-    let service = service_factory()?.serve(stdio()).await.inspect_err(|e| {
+    let stdio = AsyncRwTransport::new_server(tokio::io::stdin(), tokio::io::stdout());
+    let transport = CaptureTransport::new(stdio, tap);
+    let service = service_factory()?.serve(transport).await.inspect_err(|e| {
         tracing::error!("Server error: {:?}", e);
     })?;
 
@@ -25,30 +34,66 @@ where
     Ok(())
 }
 
+/// Serve clients over streamable HTTP, recording the frames of every session on
+/// the tap if one is given
 pub async fn run_http_server<S>(
+    config: &TransportConfig,
     service_factory: impl Fn() -> Result<S, std::io::Error> + Send + Sync + 'static,
+    tap: Option<WireTap>,
 ) -> Result<()>
 where
     S: Service<RoleServer> + Send + 'static,
 {
-    const BIND_ADDRESS: &str = "127.0.0.1:8000";
+    tracing::info!("Initializing streamable HTTP transport");
 
-    tracing::info!("Initializing streamable HTTP transport on {BIND_ADDRESS}");
+    let sessions = CaptureSessionManager::new(LocalSessionManager::default(), tap);
+    let service = StreamableHttpService::new(service_factory, sessions.into(), Default::default());
 
-    let service = StreamableHttpService::new(
-        service_factory,
-        LocalSessionManager::default().into(),
-        Default::default(),
-    );
+    // axum does not nest at the root, so a root mount serves every path
+    let path = config.path.trim_end_matches('/');
+    let router = if path.is_empty() {
+        axum::Router::new().fallback_service(service)
+    } else {
+        axum::Router::new().nest_service(path, service)
+    };
 
-    let router = axum::Router::new().nest_service("/mcp", service);
-    let tcp_listener = tokio::net::TcpListener::bind(BIND_ADDRESS).await?;
+    #[cfg(unix)]
+    if let Some(socket) = &config.unix_socket {
+        remove_stale_socket(socket)?;
+        let listener = tokio::net::UnixListener::bind(socket)
+            .with_context(|| format!("Failed to bind {}", socket.display()))?;
+        announce(&format!("unix:{}{}", socket.display(), config.path));
+        let result = serve(listener, router).await;
+        _ = std::fs::remove_file(socket);
+        return result;
+    }
 
-    tracing::info!(
-        "Server started successfully on streamable HTTP transport at http://{BIND_ADDRESS}/mcp",
-    );
+    #[cfg(not(unix))]
+    if config.unix_socket.is_some() {
+        anyhow::bail!("Unix domain sockets are not supported on this platform");
+    }
 
-    axum::serve(tcp_listener, router)
+    let listener = tokio::net::TcpListener::bind((config.host.as_str(), config.port))
+        .await
+        .with_context(|| format!("Failed to bind {}:{}", config.host, config.port))?;
+    // With port 0 the OS picks the port, so report the address actually bound
+    let address = listener.local_addr()?;
+    announce(&format!("http://{address}{}", config.path));
+    serve(listener, router).await
+}
+
+/// Tell whoever started Wrap-MCP where to connect, even with logging turned down
+fn announce(url: &str) {
+    tracing::info!("Server started successfully on streamable HTTP transport at {url}");
+    eprintln!("Wrap-MCP listening on {url}");
+}
+
+async fn serve<L>(listener: L, router: axum::Router) -> Result<()>
+where
+    L: axum::serve::Listener,
+    L::Addr: std::fmt::Debug,
+{
+    axum::serve(listener, router)
         .with_graceful_shutdown(async {
             tokio::signal::ctrl_c().await.unwrap();
             tracing::info!("Received shutdown signal");
@@ -58,3 +103,19 @@ where
     tracing::info!("Server shutting down");
     Ok(())
 }
+
+/// Remove a socket file left behind by an earlier run, leaving anything else alone
+#[cfg(unix)]
+fn remove_stale_socket(socket: &Path) -> Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    match std::fs::symlink_metadata(socket) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            tracing::debug!("Removing stale socket {}", socket.display());
+            std::fs::remove_file(socket)?;
+        }
+        Ok(_) => anyhow::bail!("{} exists and is not a socket", socket.display()),
+        Err(_) => {}
+    }
+    Ok(())
+}
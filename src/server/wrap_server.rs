@@ -1,15 +1,26 @@
 use crate::{
-    config::{LogConfig, WrappeeConfig},
-    logging::LogStorage,
+    config::{LaunchConfig, LogConfig, WrappeeConfig},
+    logging::{LogStorage, WireTap},
     server::wrappee::WrappeeController,
     tools::ToolManager,
-    wrappee::WrappeeClient,
 };
 use anyhow::Result;
-use rmcp::{RoleServer, service::Peer};
+use rmcp::{
+    ErrorData as McpError, RoleServer, ServiceError,
+    model::{
+        CreateMessageRequestParam, ErrorCode, LoggingMessageNotificationParam,
+        ResourceUpdatedNotificationParam,
+    },
+    service::Peer,
+};
+use serde_json::{Value, json};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::sync::{RwLock, mpsc};
+use tokio::time::{Duration, Instant, timeout};
+
+/// How long to keep collecting stderr of a wrappee that has exited
+const STDERR_DRAIN_TIMEOUT: Duration = Duration::from_millis(100);
 
 #[derive(Clone)]
 pub struct WrapServer {
@@ -17,23 +28,38 @@ pub struct WrapServer {
     pub(crate) wrappee_controller: Arc<WrappeeController>,
     pub(crate) peer: Arc<RwLock<Option<Peer<RoleServer>>>>,
     pub(crate) shutting_down: Arc<AtomicBool>,
+    /// Set once the wrappee binary is being watched for changes
+    pub(crate) file_watching: Arc<AtomicBool>,
     pub(crate) shutdown_tx: Arc<RwLock<Option<mpsc::Sender<()>>>>,
+    /// What to run as the wrappee
+    pub(crate) launch: Arc<LaunchConfig>,
 }
 
 impl WrapServer {
-    pub fn new(log_config: &LogConfig, wrappee_config: &WrappeeConfig) -> Self {
-        let log_storage = Arc::new(LogStorage::new(log_config));
+    pub fn new(
+        log_config: &LogConfig,
+        wrappee_config: &WrappeeConfig,
+        launch_config: &LaunchConfig,
+    ) -> Result<Self> {
+        let log_storage = Arc::new(LogStorage::open(log_config)?);
         let tool_manager = Arc::new(ToolManager::new(log_storage));
 
         let wrappee_controller = Arc::new(WrappeeController::new(wrappee_config));
 
-        Self {
+        Ok(Self {
             tool_manager,
             wrappee_controller,
             peer: Arc::new(RwLock::new(None)),
             shutting_down: Arc::new(AtomicBool::new(false)),
+            file_watching: Arc::new(AtomicBool::new(false)),
             shutdown_tx: Arc::new(RwLock::new(None)),
-        }
+            launch: Arc::new(launch_config.clone()),
+        })
+    }
+
+    /// Where the client transport records frames, if raw logging is enabled
+    pub fn wire_tap(&self) -> Option<WireTap> {
+        self.tool_manager.log_storage.wire_tap()
     }
 
     /// Setup signal handlers for graceful shutdown with a delay to avoid premature shutdown
@@ -98,6 +124,7 @@ impl WrapServer {
     /// Handle the shutdown signal
     async fn handle_shutdown_signal(server: WrapServer) {
         server.shutdown().await;
+        server.tool_manager.log_storage.flush().await;
         // Give some time for graceful shutdown
         tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
         std::process::exit(0);
@@ -113,27 +140,17 @@ impl WrapServer {
             let _ = tx.send(()).await;
         }
 
-        // Shutdown wrappee
-        if let Err(e) = self.wrappee_controller.shutdown().await {
+        // Shutdown wrappee, failing requests that would wait for it
+        self.wrappee_controller
+            .mark_down("Wrap-MCP is shutting down");
+        if let Err(e) = self.wrappee_controller.shutdown(&self.tool_manager).await {
             tracing::warn!("Error shutting down wrappee: {}", e);
         }
     }
 
-    /// Internal method to start a wrappee process with common initialization logic
-    pub(crate) async fn start_wrappee_internal(
-        &self,
-        command: &str,
-        args: &[String],
-        disable_colors: bool,
-    ) -> Result<WrappeeClient> {
-        // Delegate to WrappeeController
-        self.wrappee_controller
-            .start_wrappee(command, args, disable_colors, &self.tool_manager)
-            .await
-    }
-
-    /// Start stderr monitoring for the wrappee
+    /// Start stderr monitoring (and notification/request handling) for the wrappee
     pub(crate) fn start_stderr_monitoring(&self) {
+        let server = self.clone();
         let wrappee_controller = self.wrappee_controller.clone();
         let log_storage = self.tool_manager.log_storage.clone();
         let shutdown_tx = self.shutdown_tx.clone();
@@ -145,38 +162,43 @@ impl WrapServer {
             // Store the shutdown sender
             *shutdown_tx.write().await = Some(tx);
 
-            loop {
-                // Check for stderr messages without holding the lock during async operations
-                let stderr_result = {
-                    let mut wrappee_guard = wrappee_controller.get_client_mut().await;
-                    if let Some(wrappee) = wrappee_guard.as_mut() {
-                        // Try non-blocking receive first
-                        wrappee.receive_stderr().await
-                    } else {
-                        Ok(None)
-                    }
-                    // Lock is released here
-                };
+            // Own the event receivers so that no lock is held while waiting
+            let Some(wrappee) = wrappee_controller.get_client().await else {
+                tracing::warn!("No wrappee available for monitoring");
+                return;
+            };
+            let Some(mut events) = wrappee.take_events() else {
+                tracing::warn!("No wrappee events available for monitoring");
+                return;
+            };
 
-                // Process the result without holding the lock
-                match stderr_result {
-                    Ok(Some(stderr_msg)) => {
+            loop {
+                tokio::select! {
+                    Some(stderr_msg) = events.stderr.recv() => {
                         log_storage.add_stderr(stderr_msg).await;
                     }
-                    Ok(None) => {
-                        // No message available, wait a bit or for shutdown
-                        tokio::select! {
-                            _ = tokio::time::sleep(tokio::time::Duration::from_millis(100)) => {
-                                // Continue checking
-                            }
-                            _ = rx.recv() => {
-                                tracing::info!("Stderr monitoring received shutdown signal");
-                                break;
-                            }
+                    Some(notification) = events.notifications.recv() => {
+                        server.forward_notification(notification).await;
+                    }
+                    Some(request) = events.requests.recv() => {
+                        // Answering may involve the client, so don't block the loop
+                        let server = server.clone();
+                        tokio::spawn(async move {
+                            server.handle_wrappee_request(request).await;
+                        });
+                    }
+                    exit = wrappee.closed() => {
+                        // Whatever the wrappee printed on its way out belongs before its exit
+                        while let Ok(Some(stderr_msg)) =
+                            timeout(STDERR_DRAIN_TIMEOUT, events.stderr.recv()).await
+                        {
+                            log_storage.add_stderr(stderr_msg).await;
                         }
+                        server.handle_wrappee_exit(&wrappee, exit).await;
+                        break;
                     }
-                    Err(e) => {
-                        tracing::error!("Error receiving stderr: {}", e);
+                    _ = rx.recv() => {
+                        tracing::info!("Stderr monitoring received shutdown signal");
                         break;
                     }
                 }
@@ -186,6 +208,221 @@ impl WrapServer {
         });
     }
 
+    /// Relay a notification from the wrappee to the connected client
+    pub(crate) async fn forward_notification(&self, notification: Value) {
+        let method = notification
+            .get("method")
+            .and_then(|m| m.as_str())
+            .unwrap_or_default();
+
+        // Log messages are recorded even when no client is listening
+        if method == "notifications/message" {
+            match notification
+                .get("params")
+                .cloned()
+                .map(serde_json::from_value::<LoggingMessageNotificationParam>)
+            {
+                Some(Ok(params)) => self.relay_log_message(params).await,
+                _ => tracing::warn!("Invalid logging notification: {notification}"),
+            }
+            return;
+        }
+
+        let peer_guard = self.peer.read().await;
+        let Some(peer) = peer_guard.as_ref() else {
+            tracing::debug!("No peer available, dropping wrappee notification {method}");
+            return;
+        };
+
+        let result = match method {
+            "notifications/prompts/list_changed" => peer.notify_prompt_list_changed().await,
+            "notifications/resources/list_changed" => peer.notify_resource_list_changed().await,
+            "notifications/resources/updated" => {
+                match notification
+                    .get("params")
+                    .cloned()
+                    .map(serde_json::from_value::<ResourceUpdatedNotificationParam>)
+                {
+                    Some(Ok(params)) => peer.notify_resource_updated(params).await,
+                    _ => {
+                        tracing::warn!("Invalid resources/updated notification: {notification}");
+                        return;
+                    }
+                }
+            }
+            "notifications/tools/list_changed" => {
+                // Discovery waits on a response that reaches it through the loop
+                // calling us, so it must not hold that loop up
+                let server = self.clone();
+                tokio::spawn(async move { server.rediscover_tools().await });
+                return;
+            }
+            _ => {
+                tracing::debug!("Ignoring wrappee notification {method}");
+                return;
+            }
+        };
+
+        if let Err(e) = result {
+            tracing::warn!("Failed to forward {method} notification: {e}");
+        }
+    }
+
+    /// Record a wrappee log message and pass it on to the client
+    async fn relay_log_message(&self, params: LoggingMessageNotificationParam) {
+        let level = serde_json::to_value(params.level)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default();
+        self.tool_manager
+            .log_storage
+            .add_log_message(level, params.logger.clone(), params.data.clone())
+            .await;
+
+        if let Some(peer) = self.peer.read().await.as_ref()
+            && let Err(e) = peer.notify_logging_message(params).await
+        {
+            tracing::warn!("Failed to forward logging message: {e}");
+        }
+    }
+
+    /// Answer a request initiated by the wrappee
+    pub(crate) async fn handle_wrappee_request(&self, request: Value) {
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let method = request
+            .get("method")
+            .and_then(|m| m.as_str())
+            .unwrap_or_default();
+        let params = request.get("params").cloned().unwrap_or(json!({}));
+
+        let outcome = match method {
+            "ping" => Ok(json!({})),
+            "sampling/createMessage" | "roots/list" | "elicitation/create" => {
+                self.bridge_to_client(method, params).await
+            }
+            _ => {
+                tracing::warn!("Unsupported request from wrappee: {method}");
+                Err(McpError {
+                    code: ErrorCode::METHOD_NOT_FOUND,
+                    message: format!("Method not found: {method}").into(),
+                    data: None,
+                })
+            }
+        };
+
+        let outcome = outcome.map_err(|e| serde_json::to_value(e).unwrap_or_default());
+        if let Err(e) = self.wrappee_controller.respond(id, outcome).await {
+            tracing::warn!("Failed to answer wrappee request {method}: {e}");
+        }
+    }
+
+    /// Pass a wrappee request on to the connected client, logging both directions
+    async fn bridge_to_client(&self, method: &str, params: Value) -> Result<Value, McpError> {
+        let log_storage = &self.tool_manager.log_storage;
+        let request_id = log_storage
+            .add_request(method.to_string(), params.clone())
+            .await;
+
+        let started = Instant::now();
+        let outcome = self.send_to_client(method, params).await;
+        let latency = Some(started.elapsed());
+
+        match &outcome {
+            Ok(result) => {
+                log_storage
+                    .add_response_with_latency(
+                        request_id,
+                        method.to_string(),
+                        json!({ "result": result }),
+                        latency,
+                    )
+                    .await;
+            }
+            Err(e) => {
+                log_storage
+                    .add_error_with_latency(
+                        request_id,
+                        method.to_string(),
+                        e.message.to_string(),
+                        latency,
+                    )
+                    .await;
+            }
+        }
+
+        outcome
+    }
+
+    /// Issue a server-to-client request on behalf of the wrappee
+    async fn send_to_client(&self, method: &str, params: Value) -> Result<Value, McpError> {
+        // Not relayed yet (see "Known Limitations" in the README): rmcp 0.5 has no
+        // elicitation, so a server cannot send `elicitation/create`, the client's
+        // answer would not parse, and clients cannot declare support
+        if method == "elicitation/create" {
+            tracing::warn!("Wrappee asked for elicitation, which cannot be relayed yet");
+            return Err(McpError {
+                code: ErrorCode::METHOD_NOT_FOUND,
+                message: "Elicitation cannot be relayed to the client by Wrap-MCP yet".into(),
+                data: None,
+            });
+        }
+
+        let Some(peer) = self.peer.read().await.clone() else {
+            return Err(McpError {
+                code: ErrorCode::INTERNAL_ERROR,
+                message: "No client connected".into(),
+                data: None,
+            });
+        };
+
+        let result = match method {
+            "sampling/createMessage" => {
+                let params: CreateMessageRequestParam =
+                    serde_json::from_value(params).map_err(|e| McpError {
+                        code: ErrorCode::INVALID_PARAMS,
+                        message: format!("Invalid sampling request: {e}").into(),
+                        data: None,
+                    })?;
+                peer.create_message(params)
+                    .await
+                    .map(|result| serde_json::to_value(result).unwrap_or_default())
+            }
+            "roots/list" => peer
+                .list_roots()
+                .await
+                .map(|result| serde_json::to_value(result).unwrap_or_default()),
+            _ => {
+                return Err(McpError {
+                    code: ErrorCode::METHOD_NOT_FOUND,
+                    message: format!("Method not found: {method}").into(),
+                    data: None,
+                });
+            }
+        };
+
+        result.map_err(|e| match e {
+            ServiceError::McpError(error) => error,
+            other => McpError {
+                code: ErrorCode::INTERNAL_ERROR,
+                message: format!("Client request {method} failed: {other}").into(),
+                data: None,
+            },
+        })
+    }
+
+    /// Rediscover tools after the wrappee reported a change and tell the client
+    pub(crate) async fn rediscover_tools(&self) {
+        let Some(wrappee) = self.wrappee_controller.get_client().await else {
+            return;
+        };
+        let result = self.tool_manager.discover_tools(&wrappee).await;
+
+        match result {
+            Ok(()) => self.notify_tools_changed().await,
+            Err(e) => tracing::warn!("Failed to rediscover tools: {e}"),
+        }
+    }
+
     /// Get PID of current wrappee process
     pub(crate) async fn get_wrappee_pid(&self) -> Option<u32> {
         self.wrappee_controller.get_pid().await
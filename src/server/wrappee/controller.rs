@@ -1,72 +1,290 @@
-use crate::{config::WrappeeConfig, tools::ToolManager, wrappee::WrappeeClient};
+use crate::{
+    config::{SUPPORTED_PROTOCOL_VERSIONS, WrappeeConfig},
+    tools::ToolManager,
+    types::{Health, Negotiation, RestartRecord},
+    wrappee::{ShutdownOutcome, WrappeeClient, WrappeeTarget},
+};
 use anyhow::Result;
+use chrono::Utc;
+use rmcp::{
+    ErrorData as McpError, RoleServer,
+    model::{ErrorCode, InitializeRequestParam, InitializeResult, Meta},
+    service::RequestContext,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::VecDeque;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use tokio::sync::{Mutex, MutexGuard, OwnedSemaphorePermit, RwLock, Semaphore, watch};
+use tokio::time::Duration;
+
+/// `_meta` key through which a client can set the timeout of a single tool call, in seconds
+pub const TIMEOUT_META_KEY: &str = "wrap-mcp/timeout";
+
+/// How many past restarts are remembered for `wrap_status`
+const RESTART_HISTORY: usize = 10;
+
+/// Whether requests can expect a wrappee to serve them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Readiness {
+    /// The wrappee is being started or restarted
+    Starting,
+    /// The wrappee is up and accepting requests
+    Ready,
+    /// The wrappee is gone and nothing is bringing it back; carries the reason
+    Down(String),
+}
+
+/// What a wrappee initialized before the client connected did not get from its handshake
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MissedClientInfo {
+    /// A supported protocol version requested by the client, other than the one
+    /// agreed with the wrappee
+    ProtocolVersion(String),
+    /// Capabilities whose requests are bridged to the client (sampling, roots)
+    Capabilities,
+}
 
 /// Controls the lifecycle and state of a wrapped MCP process
 #[derive(Clone)]
 pub struct WrappeeController {
-    /// The active wrappee client connection
-    pub(crate) client: Arc<RwLock<Option<WrappeeClient>>>,
+    /// The active wrappee client connection, shared by concurrent requests
+    pub(crate) client: Arc<RwLock<Option<Arc<WrappeeClient>>>>,
 
-    /// Command used to spawn the wrappee
-    pub(crate) command: Arc<RwLock<Option<String>>>,
+    /// Limits the number of requests in flight to the wrappee
+    pub(crate) request_slots: Arc<Semaphore>,
 
-    /// Arguments passed to the wrappee command
-    pub(crate) args: Arc<RwLock<Option<Vec<String>>>>,
-
-    /// Whether to disable colors in wrappee output
-    pub(crate) disable_colors: Arc<RwLock<bool>>,
+    /// How to spawn or reach the wrappee
+    pub(crate) target: Arc<RwLock<Option<WrappeeTarget>>>,
 
     /// Configuration for the wrappee
     pub(crate) config: Arc<WrappeeConfig>,
+
+    /// Initialize result reported by the current wrappee
+    /// (a watch channel so that `get_info` can read it synchronously)
+    pub(crate) wrappee_info: Arc<watch::Sender<Option<InitializeResult>>>,
+
+    /// Initialize request of the connected client, mirrored into the wrappee handshake
+    pub(crate) client_info: Arc<watch::Sender<Option<InitializeRequestParam>>>,
+
+    /// Whether the current wrappee's handshake carried the client's initialize request
+    pub(crate) client_info_offered: Arc<AtomicBool>,
+
+    /// Protocol versions negotiated with the client and with the wrappee
+    pub(crate) client_negotiation: Arc<RwLock<Option<Negotiation>>>,
+    pub(crate) wrappee_negotiation: Arc<RwLock<Option<Negotiation>>>,
+
+    /// Automatic restarts attempted since the wrappee last ran stably
+    pub(crate) restart_attempts: Arc<AtomicU32>,
+
+    /// Lifecycle of the wrappee, which requests arriving during a restart wait on
+    pub(crate) readiness: Arc<watch::Sender<Readiness>>,
+
+    /// Held while a wrappee is started or restarted, so that only one is brought up at a time
+    pub(crate) lifecycle: Arc<Mutex<()>>,
+
+    /// Ping results for the current wrappee
+    pub(crate) health: Arc<RwLock<Health>>,
+
+    /// Number of restarts so far and the most recent ones with their reasons
+    pub(crate) restart_count: Arc<AtomicU32>,
+    pub(crate) restart_history: Arc<RwLock<VecDeque<RestartRecord>>>,
 }
 
 impl WrappeeController {
     pub fn new(wrappee_config: &WrappeeConfig) -> Self {
         Self {
             client: Arc::new(RwLock::new(None)),
-            command: Arc::new(RwLock::new(None)),
-            args: Arc::new(RwLock::new(None)),
-            disable_colors: Arc::new(RwLock::new(false)),
+            request_slots: Arc::new(Semaphore::new(wrappee_config.max_concurrency)),
+            target: Arc::new(RwLock::new(None)),
             config: Arc::new(wrappee_config.clone()),
+            wrappee_info: Arc::new(watch::Sender::new(None)),
+            client_info: Arc::new(watch::Sender::new(None)),
+            client_info_offered: Arc::new(AtomicBool::new(false)),
+            client_negotiation: Arc::new(RwLock::new(None)),
+            wrappee_negotiation: Arc::new(RwLock::new(None)),
+            restart_attempts: Arc::new(AtomicU32::new(0)),
+            readiness: Arc::new(watch::Sender::new(Readiness::Starting)),
+            lifecycle: Arc::new(Mutex::new(())),
+            health: Arc::new(RwLock::new(Health::default())),
+            restart_count: Arc::new(AtomicU32::new(0)),
+            restart_history: Arc::new(RwLock::new(VecDeque::new())),
         }
     }
 
-    /// Store the command and arguments for the wrappee
-    pub async fn set_command(&self, command: String, args: Vec<String>, disable_colors: bool) {
-        *self.command.write().await = Some(command);
-        *self.args.write().await = Some(args);
-        *self.disable_colors.write().await = disable_colors;
+    /// Store how to reach the wrappee
+    pub async fn set_target(&self, target: WrappeeTarget) {
+        *self.target.write().await = Some(target);
     }
 
-    /// Get the stored command and arguments
-    pub async fn get_command(&self) -> Option<(String, Vec<String>, bool)> {
-        let cmd = self.command.read().await;
-        let args = self.args.read().await;
-        let disable_colors = *self.disable_colors.read().await;
-
-        match (cmd.as_ref(), args.as_ref()) {
-            (Some(c), Some(a)) => Some((c.clone(), a.clone(), disable_colors)),
-            _ => None,
-        }
+    /// Get the stored wrappee target
+    pub async fn get_target(&self) -> Option<WrappeeTarget> {
+        self.target.read().await.clone()
     }
 
-    /// Set the active wrappee client
-    pub async fn set_client(&self, client: Option<WrappeeClient>) {
-        *self.client.write().await = client;
+    /// Set the active wrappee client (a new client makes the wrappee ready), returning
+    /// the one it replaces, which the caller shuts down
+    #[must_use = "a replaced wrappee keeps running until it is shut down"]
+    pub async fn set_client(&self, client: Option<WrappeeClient>) -> Option<Arc<WrappeeClient>> {
+        let ready = client.is_some();
+        let replaced = std::mem::replace(&mut *self.client.write().await, client.map(Arc::new));
+        if ready {
+            *self.health.write().await = Health::default();
+            self.readiness.send_replace(Readiness::Ready);
+        }
+        replaced
     }
 
-    /// Get a mutable reference to the wrappee client
-    pub async fn get_client_mut(&self) -> tokio::sync::RwLockWriteGuard<'_, Option<WrappeeClient>> {
-        self.client.write().await
+    /// Get a shared handle to the wrappee client (the lock is released immediately)
+    pub async fn get_client(&self) -> Option<Arc<WrappeeClient>> {
+        self.client.read().await.clone()
     }
 
     /// Take the wrappee client (removes it from state)
-    pub async fn take_client(&self) -> Option<WrappeeClient> {
+    pub async fn take_client(&self) -> Option<Arc<WrappeeClient>> {
         self.client.write().await.take()
     }
 
+    /// Check whether the given client is still the active one
+    pub async fn is_current(&self, client: &Arc<WrappeeClient>) -> bool {
+        self.client
+            .read()
+            .await
+            .as_ref()
+            .is_some_and(|c| Arc::ptr_eq(c, client))
+    }
+
+    /// Take the wrappee client, but only if it is still the given one
+    pub async fn take_client_if(&self, client: &Arc<WrappeeClient>) -> bool {
+        let mut current = self.client.write().await;
+        if current.as_ref().is_some_and(|c| Arc::ptr_eq(c, client)) {
+            *current = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Wait until no other wrappee is being started or restarted, and keep it that way
+    /// while the guard is held
+    pub async fn lock_lifecycle(&self) -> MutexGuard<'_, ()> {
+        self.lifecycle.lock().await
+    }
+
+    /// Let requests wait for a wrappee that is being (re)started
+    pub fn mark_starting(&self) {
+        self.readiness.send_replace(Readiness::Starting);
+    }
+
+    /// Fail waiting and future requests until a wrappee is started again
+    pub fn mark_down(&self, reason: impl Into<String>) {
+        self.readiness.send_replace(Readiness::Down(reason.into()));
+    }
+
+    /// Current lifecycle state of the wrappee
+    pub fn readiness(&self) -> Readiness {
+        self.readiness.borrow().clone()
+    }
+
+    /// Get the wrappee client, waiting up to the ready timeout while it is (re)started
+    pub async fn wait_for_client(&self) -> Result<Arc<WrappeeClient>, McpError> {
+        let deadline = Duration::from_secs(self.config.ready_timeout_secs);
+        let mut rx = self.readiness.subscribe();
+        let wait = async {
+            loop {
+                // Look at the state before the client, since the client is set first
+                let readiness = rx.borrow_and_update().clone();
+                if let Some(client) = self.get_client().await {
+                    return Ok(client);
+                }
+                if let Readiness::Down(reason) = readiness {
+                    return Err(reason);
+                }
+                if rx.changed().await.is_err() {
+                    return Err("Wrappee controller dropped".to_string());
+                }
+            }
+        };
+
+        match tokio::time::timeout(deadline, wait).await {
+            Ok(Ok(client)) => Ok(client),
+            Ok(Err(reason)) => Err(McpError {
+                code: ErrorCode::INTERNAL_ERROR,
+                message: format!("Wrappee unavailable: {reason}").into(),
+                data: None,
+            }),
+            Err(_) => Err(McpError {
+                code: ErrorCode::INTERNAL_ERROR,
+                message: format!(
+                    "Wrappee did not become ready within {} seconds",
+                    self.config.ready_timeout_secs
+                )
+                .into(),
+                data: None,
+            }),
+        }
+    }
+
+    /// Health of the current wrappee as determined by pings
+    pub async fn health(&self) -> Health {
+        self.health.read().await.clone()
+    }
+
+    /// Record the outcome of a ping, returning the new health if the wrappee
+    /// just became unhealthy or recovered
+    pub async fn record_ping(&self, outcome: Result<Duration, String>) -> Option<Health> {
+        let mut health = self.health.write().await;
+        let was_healthy = health.healthy;
+        match outcome {
+            Ok(latency) => {
+                health.healthy = true;
+                health.consecutive_failures = 0;
+                health.last_latency_ms = Some(latency.as_millis() as u64);
+            }
+            Err(error) => {
+                health.consecutive_failures += 1;
+                health.last_error = Some(error);
+                if health.consecutive_failures >= self.config.health.failure_threshold {
+                    health.healthy = false;
+                }
+            }
+        }
+        (health.healthy != was_healthy).then(|| health.clone())
+    }
+
+    /// Remember that the wrappee was restarted and why
+    pub async fn record_restart(&self, reason: impl Into<String>) {
+        self.restart_count.fetch_add(1, Ordering::SeqCst);
+        let mut history = self.restart_history.write().await;
+        if history.len() == RESTART_HISTORY {
+            history.pop_front();
+        }
+        history.push_back(RestartRecord {
+            at: Utc::now(),
+            reason: reason.into(),
+        });
+    }
+
+    /// Number of restarts so far and the most recent ones, oldest first
+    pub async fn restarts(&self) -> (u32, Vec<RestartRecord>) {
+        let history = self.restart_history.read().await;
+        (
+            self.restart_count.load(Ordering::SeqCst),
+            history.iter().cloned().collect(),
+        )
+    }
+
+    /// Count an automatic restart attempt and return its number (1-based)
+    pub fn next_restart_attempt(&self) -> u32 {
+        self.restart_attempts.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// Forget earlier automatic restart attempts
+    pub fn reset_restart_attempts(&self) {
+        self.restart_attempts.store(0, Ordering::SeqCst);
+    }
+
     /// Check if a wrappee is currently active
     pub async fn is_active(&self) -> bool {
         self.client.read().await.is_some()
@@ -74,100 +292,349 @@ impl WrappeeController {
 
     /// Get PID of current wrappee process
     pub async fn get_pid(&self) -> Option<u32> {
-        let client_guard = self.client.read().await;
-        if let Some(client) = client_guard.as_ref() {
-            client.get_pid().await
-        } else {
-            None
+        self.get_client().await?.get_pid().await
+    }
+
+    /// Get the initialize result reported by the current wrappee
+    pub fn wrappee_info(&self) -> Option<InitializeResult> {
+        self.wrappee_info.borrow().clone()
+    }
+
+    /// Wait until the wrappee has been initialized, up to the given timeout
+    pub async fn wait_for_initialization(&self, timeout: Duration) -> bool {
+        let mut rx = self.wrappee_info.subscribe();
+        tokio::time::timeout(timeout, rx.wait_for(|info| info.is_some()))
+            .await
+            .is_ok_and(|result| result.is_ok())
+    }
+
+    /// Remember the initialize request of the connected client
+    pub fn set_client_info(&self, request: InitializeRequestParam) {
+        self.client_info.send_replace(Some(request));
+    }
+
+    /// What the running wrappee missed by being initialized before the client: the
+    /// protocol version it asked for, or capabilities whose requests could be bridged
+    /// to it. Only the first caller is told, so that a single restart hands them over.
+    pub fn take_missed_client_info(&self) -> Option<MissedClientInfo> {
+        let client_info = self.client_info.borrow().clone()?;
+        let agreed = self
+            .wrappee_info
+            .borrow()
+            .as_ref()?
+            .protocol_version
+            .to_string();
+
+        let requested = client_info.protocol_version.to_string();
+        let capabilities = &client_info.capabilities;
+        let missed =
+            if requested != agreed && SUPPORTED_PROTOCOL_VERSIONS.contains(&requested.as_str()) {
+                MissedClientInfo::ProtocolVersion(requested)
+            } else if capabilities.sampling.is_some() || capabilities.roots.is_some() {
+                MissedClientInfo::Capabilities
+            } else {
+                return None;
+            };
+
+        self.client_info_offered
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+            .then_some(missed)
+    }
+
+    /// Record the protocol version agreed with the client
+    pub async fn set_client_negotiation(&self, negotiation: Negotiation) {
+        *self.client_negotiation.write().await = Some(negotiation);
+    }
+
+    /// Protocol versions negotiated with the client and with the wrappee
+    pub async fn negotiations(&self) -> (Option<Negotiation>, Option<Negotiation>) {
+        (
+            self.client_negotiation.read().await.clone(),
+            self.wrappee_negotiation.read().await.clone(),
+        )
+    }
+
+    /// Start (or connect to) a wrappee and make it the current one. Callers hold
+    /// the lifecycle lock.
+    pub async fn start_current(
+        &self,
+        target: &WrappeeTarget,
+        tool_manager: &ToolManager,
+    ) -> Result<()> {
+        let (client, info) = self.start_wrappee(target, tool_manager).await?;
+        if let Some(replaced) = self.set_client(Some(client)).await {
+            // Normally shut down before the start, but never leave one running unowned
+            tracing::warn!("Stopping wrappee replaced by the new one");
+            if let Err(e) = self.stop(&replaced, tool_manager).await {
+                tracing::warn!("Failed to stop replaced wrappee: {e}");
+            }
         }
+
+        // Published only now, so that whoever waits for initialization finds the
+        // client current and can restart it
+        if let Some(info) = info {
+            self.wrappee_info.send_replace(Some(info));
+        }
+        Ok(())
     }
 
-    /// Start a wrappee process with the stored configuration
-    pub async fn start_wrappee(
+    /// Start (or connect to) a wrappee and perform the MCP handshake, returning the
+    /// client with the initialize result it reported
+    async fn start_wrappee(
         &self,
-        command: &str,
-        args: &[String],
-        disable_colors: bool,
+        target: &WrappeeTarget,
         tool_manager: &ToolManager,
-    ) -> Result<WrappeeClient> {
-        tracing::info!("Starting wrappee process: {command} {args:?}");
+    ) -> Result<(WrappeeClient, Option<InitializeResult>)> {
+        tracing::info!("Starting wrappee: {target}");
+
+        // Spawn the wrappee process or connect to it
+        let wrappee_client = WrappeeClient::connect(
+            target,
+            self.config.as_ref().clone(),
+            tool_manager.log_storage.wire_tap(),
+        )
+        .await?;
+
+        // Don't leave a half-started wrappee behind
+        match self.handshake(&wrappee_client, tool_manager).await {
+            Ok(info) => Ok((wrappee_client, info)),
+            Err(e) => {
+                if let Err(shutdown_error) = wrappee_client.shutdown().await {
+                    tracing::warn!("Failed to stop wrappee after failed start: {shutdown_error}");
+                }
+                Err(e)
+            }
+        }
+    }
 
-        // Spawn the wrappee process
-        let mut wrappee_client =
-            WrappeeClient::spawn(command, args, disable_colors, self.config.as_ref().clone())?;
+    /// Initialize a freshly started wrappee and discover its tools
+    async fn handshake(
+        &self,
+        wrappee_client: &WrappeeClient,
+        tool_manager: &ToolManager,
+    ) -> Result<Option<InitializeResult>> {
+        // Offer the wrappee whatever the client can do (sampling, roots),
+        // since its requests for those are bridged back to the client,
+        // and the protocol version the client asked for. A client that shows up
+        // later has the wrappee restarted once it initializes, if it asks for
+        // another version or brings such capabilities.
+        let client_info = self.client_info.borrow().clone();
+        self.client_info_offered
+            .store(client_info.is_some(), Ordering::SeqCst);
+        let (capabilities, requested) = match client_info {
+            Some(info) => (
+                serde_json::to_value(info.capabilities)?,
+                info.protocol_version.to_string(),
+            ),
+            None => {
+                tracing::info!("No client yet, initializing wrappee without client capabilities");
+                (serde_json::json!({}), self.config.protocol_version.clone())
+            }
+        };
 
         // Initialize the wrappee
-        wrappee_client
-            .initialize(&self.config.protocol_version)
+        let result = self
+            .negotiate(wrappee_client, &requested, capabilities)
             .await?;
+        let negotiated = result
+            .get("protocolVersion")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+        if !SUPPORTED_PROTOCOL_VERSIONS.contains(&negotiated.as_str()) {
+            tracing::warn!("Wrappee chose unsupported protocol version '{negotiated}'");
+        }
+        let implementation = result.get("serverInfo").map(|server| {
+            let field = |key| server.get(key).and_then(|v| v.as_str()).unwrap_or("");
+            format!("{} {}", field("name"), field("version"))
+        });
+        tool_manager
+            .log_storage
+            .add_handshake(
+                "wrappee",
+                implementation,
+                requested.clone(),
+                negotiated.clone(),
+            )
+            .await;
+        *self.wrappee_negotiation.write().await = Some(Negotiation {
+            requested,
+            negotiated,
+        });
 
-        // Discover tools from wrappee
-        tool_manager.discover_tools(&mut wrappee_client).await?;
+        // Keep the initialize result so that it can be presented to the client
+        let info = serde_json::from_value::<InitializeResult>(result)
+            .inspect_err(|e| tracing::warn!("Failed to parse wrappee initialize result: {e}"))
+            .ok();
 
-        Ok(wrappee_client)
+        // Discover tools from wrappee
+        tool_manager.discover_tools(wrappee_client).await?;
+        Ok(info)
     }
 
-    /// Shutdown the current wrappee process
-    pub async fn shutdown(&self) -> Result<()> {
-        if let Some(client) = self.take_client().await {
-            tracing::info!("Shutting down wrappee process");
-            client.shutdown().await?;
+    /// Initialize the wrappee with the requested protocol version, falling back to
+    /// older supported versions while the wrappee rejects the handshake
+    async fn negotiate(
+        &self,
+        wrappee_client: &WrappeeClient,
+        requested: &str,
+        capabilities: Value,
+    ) -> Result<Value> {
+        let mut candidates = protocol_candidates(requested);
+        let mut last_error = Value::Null;
+
+        while !candidates.is_empty() {
+            let version = candidates.remove(0);
+            let response = wrappee_client
+                .initialize(&version, capabilities.clone())
+                .await?;
+            if let Some(result) = response.get("result") {
+                return Ok(result.to_owned());
+            }
+
+            last_error = response.get("error").cloned().unwrap_or(response);
+            tracing::warn!("Wrappee rejected protocol version {version}: {last_error}");
+
+            // Servers may tell which versions they support
+            if let Some(supported) = last_error
+                .pointer("/data/supported")
+                .and_then(|s| s.as_array())
+            {
+                candidates.retain(|c| supported.iter().any(|s| s.as_str() == Some(c)));
+            }
         }
-        Ok(())
+
+        anyhow::bail!("Wrappee rejected every supported protocol version: {last_error}")
+    }
+
+    /// Shutdown the current wrappee process, logging how it went away
+    pub async fn shutdown(&self, tool_manager: &ToolManager) -> Result<Option<ShutdownOutcome>> {
+        let Some(client) = self.take_client().await else {
+            return Ok(None);
+        };
+        self.stop(&client, tool_manager).await.map(Some)
     }
 
-    /// Initialize and start wrappee from command configuration
+    /// Shut down a wrappee that is no longer current, logging how it went away
+    async fn stop(
+        &self,
+        client: &WrappeeClient,
+        tool_manager: &ToolManager,
+    ) -> Result<ShutdownOutcome> {
+        tracing::info!("Shutting down wrappee process");
+        let outcome = client.shutdown().await?;
+        tracing::info!("Wrappee shut down: {outcome}");
+        tool_manager
+            .log_storage
+            .add_exited(
+                outcome.exit.code,
+                outcome.exit.signal,
+                Some(outcome.stage.to_string()),
+            )
+            .await;
+        Ok(outcome)
+    }
+
+    /// Initialize and start wrappee from its target
     pub async fn initialize(
         &self,
-        command: &str,
-        args: &[String],
-        disable_colors: bool,
+        target: WrappeeTarget,
         tool_manager: &ToolManager,
     ) -> Result<()> {
+        let _lifecycle = self.lock_lifecycle().await;
+
         // Store configuration for potential restart
-        self.set_command(command.to_string(), args.to_vec(), disable_colors)
-            .await;
+        self.set_target(target.clone()).await;
 
         // Start the wrappee
-        let client = self
-            .start_wrappee(command, args, disable_colors, tool_manager)
-            .await?;
-
-        // Store the client
-        self.set_client(Some(client)).await;
+        if let Err(e) = self.start_current(&target, tool_manager).await {
+            self.mark_down(format!("failed to start: {e}"));
+            return Err(e);
+        }
+        self.reset_restart_attempts();
 
         Ok(())
     }
 
-    /// Restart the wrappee with stored configuration
-    pub async fn restart(&self, tool_manager: &ToolManager) -> Result<()> {
+    /// Restart the wrappee with stored configuration, returning how the old one went away.
+    /// Waits for a start or restart in progress to finish first.
+    pub async fn restart(
+        &self,
+        tool_manager: &ToolManager,
+        reason: &str,
+    ) -> Result<Option<ShutdownOutcome>> {
+        let _lifecycle = self.lock_lifecycle().await;
+        self.restart_locked(tool_manager, reason).await
+    }
+
+    /// Like `restart`, but return `None` without restarting if a wrappee is being
+    /// started or restarted already
+    pub async fn restart_unless_busy(
+        &self,
+        tool_manager: &ToolManager,
+        reason: &str,
+    ) -> Option<Result<Option<ShutdownOutcome>>> {
+        let _lifecycle = self.lifecycle.try_lock().ok()?;
+        Some(self.restart_locked(tool_manager, reason).await)
+    }
+
+    /// `restart` for a caller holding the lifecycle lock
+    async fn restart_locked(
+        &self,
+        tool_manager: &ToolManager,
+        reason: &str,
+    ) -> Result<Option<ShutdownOutcome>> {
         // Check if configuration exists
-        let (command, args, disable_colors) = self
-            .get_command()
+        let target = self
+            .get_target()
             .await
             .ok_or_else(|| anyhow::anyhow!("No wrappee configuration available for restart"))?;
 
-        // Shutdown existing wrappee
-        if let Err(e) = self.shutdown().await {
-            tracing::warn!("Error during shutdown: {e}");
-        }
+        // Requests arriving from now on wait for the new wrappee
+        self.mark_starting();
 
-        // Wait for clean shutdown
-        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+        // Shutdown existing wrappee (this waits until it is gone)
+        let outcome = match self.shutdown(tool_manager).await {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                tracing::warn!("Error during shutdown: {e}");
+                None
+            }
+        };
 
         // Clear tools before restarting
         tool_manager.clear_tools().await;
 
         // Start new wrappee
-        let client = self
-            .start_wrappee(&command, &args, disable_colors, tool_manager)
-            .await?;
+        if let Err(e) = self.start_current(&target, tool_manager).await {
+            self.mark_down(format!("restart failed: {e}"));
+            return Err(e);
+        }
+        self.reset_restart_attempts();
+        self.record_restart(reason).await;
 
-        // Store the new client
-        self.set_client(Some(client)).await;
+        tracing::info!("Wrappee restarted successfully ({reason})");
+        Ok(outcome)
+    }
 
-        tracing::info!("Wrappee restarted successfully");
-        Ok(())
+    /// Wait for the current wrappee client and a free request slot
+    async fn acquire(&self) -> Result<(OwnedSemaphorePermit, Arc<WrappeeClient>), McpError> {
+        // Don't occupy a slot while waiting out a restart
+        let wrappee = self.wait_for_client().await?;
+
+        let permit = self
+            .request_slots
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| McpError {
+                code: ErrorCode::INTERNAL_ERROR,
+                message: "Wrappee request slots closed".into(),
+                data: None,
+            })?;
+
+        Ok((permit, wrappee))
     }
 
     /// Proxy a tool call to the wrappee
@@ -176,16 +643,261 @@ impl WrappeeController {
         name: &str,
         arguments: serde_json::Value,
         tool_manager: &ToolManager,
+        context: &RequestContext<RoleServer>,
     ) -> Result<rmcp::model::CallToolResult, rmcp::ErrorData> {
-        let mut wrappee_guard = self.get_client_mut().await;
-        if let Some(wrappee) = wrappee_guard.as_mut() {
-            tool_manager.proxy_tool_call(name, arguments, wrappee).await
-        } else {
-            Err(rmcp::ErrorData {
-                code: rmcp::model::ErrorCode::INTERNAL_ERROR,
-                message: "Wrappee not initialized".into(),
-                data: None,
-            })
+        let timeout = timeout_hint(&context.meta).unwrap_or_else(|| self.config.tool_timeout(name));
+        let (_permit, wrappee) = self.acquire().await?;
+        tool_manager
+            .proxy_tool_call(name, arguments, &wrappee, timeout, context)
+            .await
+    }
+
+    /// Answer a request that the wrappee initiated
+    pub async fn respond(&self, id: Value, outcome: Result<Value, Value>) -> Result<()> {
+        match self.get_client().await {
+            Some(wrappee) => wrappee.respond(id, outcome).await,
+            None => anyhow::bail!("Wrappee not initialized"),
         }
     }
+
+    /// Send a notification to the wrappee
+    pub async fn notify(&self, method: &str) -> Result<()> {
+        match self.get_client().await {
+            Some(wrappee) => wrappee.notify(method, None).await,
+            None => anyhow::bail!("Wrappee not initialized"),
+        }
+    }
+
+    /// Forward an arbitrary request to the wrappee and return the raw JSON-RPC response
+    pub async fn proxy_request(&self, method: &str, params: Value) -> Result<Value, McpError> {
+        let (_permit, wrappee) = self.acquire().await?;
+
+        wrappee.request(method, params).await.map_err(|e| McpError {
+            code: ErrorCode::INTERNAL_ERROR,
+            message: format!("Failed to forward {method}: {e}").into(),
+            data: None,
+        })
+    }
+}
+
+/// Timeout requested by the client for a single call through `_meta`
+fn timeout_hint(meta: &Meta) -> Option<Duration> {
+    let value = meta.get(TIMEOUT_META_KEY)?;
+    match value.as_u64() {
+        Some(secs) if secs > 0 => Some(Duration::from_secs(secs)),
+        _ => {
+            tracing::warn!("Ignoring invalid {TIMEOUT_META_KEY} hint: {value}");
+            None
+        }
+    }
+}
+
+/// Protocol versions to offer the wrappee: the requested one, then older supported ones
+fn protocol_candidates(requested: &str) -> Vec<String> {
+    let mut candidates = vec![requested.to_string()];
+    candidates.extend(
+        SUPPORTED_PROTOCOL_VERSIONS
+            .iter()
+            .filter(|v| **v < requested)
+            .map(|v| v.to_string()),
+    );
+    candidates
+}
+
+/// Extract the typed result from a wrappee JSON-RPC response, preserving wrappee errors
+pub(crate) fn parse_response<T: DeserializeOwned>(response: &Value) -> Result<T, McpError> {
+    if let Some(result) = response.get("result") {
+        serde_json::from_value(result.to_owned()).map_err(|e| McpError {
+            code: ErrorCode::INTERNAL_ERROR,
+            message: format!("Invalid result from wrappee: {e}").into(),
+            data: Some(result.to_owned()),
+        })
+    } else if let Some(error) = response.get("error") {
+        let code = error
+            .get("code")
+            .and_then(|c| c.as_i64())
+            .map(|c| ErrorCode(c as i32))
+            .unwrap_or(ErrorCode::INTERNAL_ERROR);
+        let message = error
+            .get("message")
+            .and_then(|m| m.as_str())
+            .unwrap_or("Unknown error")
+            .to_string();
+
+        Err(McpError {
+            code,
+            message: message.into(),
+            data: error.get("data").cloned(),
+        })
+    } else {
+        Err(McpError {
+            code: ErrorCode::INTERNAL_ERROR,
+            message: "Wrappee response has neither result nor error".into(),
+            data: Some(response.to_owned()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::ClientCapabilities;
+    use serde_json::json;
+
+    #[test]
+    fn test_protocol_candidates() {
+        assert_eq!(
+            protocol_candidates("2025-06-18"),
+            vec!["2025-06-18", "2025-03-26", "2024-11-05"]
+        );
+        assert_eq!(
+            protocol_candidates("2025-03-26"),
+            vec!["2025-03-26", "2024-11-05"]
+        );
+        assert_eq!(
+            protocol_candidates("2099-01-01"),
+            vec!["2099-01-01", "2025-06-18", "2025-03-26", "2024-11-05"]
+        );
+    }
+
+    #[test]
+    fn test_timeout_hint() {
+        let mut meta = Meta::new();
+        assert_eq!(timeout_hint(&meta), None);
+
+        meta.insert(TIMEOUT_META_KEY.to_string(), json!(120));
+        assert_eq!(timeout_hint(&meta), Some(Duration::from_secs(120)));
+
+        meta.insert(TIMEOUT_META_KEY.to_string(), json!(0));
+        assert_eq!(timeout_hint(&meta), None);
+        meta.insert(TIMEOUT_META_KEY.to_string(), json!("soon"));
+        assert_eq!(timeout_hint(&meta), None);
+    }
+
+    #[tokio::test]
+    async fn test_record_ping() {
+        let controller = WrappeeController::new(&WrappeeConfig::default());
+        let failure = || Err("timed out".to_string());
+
+        assert_eq!(controller.record_ping(failure()).await, None);
+        assert_eq!(controller.record_ping(failure()).await, None);
+        let health = controller.record_ping(failure()).await.unwrap();
+        assert!(!health.healthy);
+        assert_eq!(health.consecutive_failures, 3);
+        assert_eq!(controller.record_ping(failure()).await, None);
+
+        let health = controller
+            .record_ping(Ok(Duration::from_millis(7)))
+            .await
+            .unwrap();
+        assert!(health.healthy);
+        assert_eq!(health.consecutive_failures, 0);
+        assert_eq!(health.last_latency_ms, Some(7));
+    }
+
+    #[tokio::test]
+    async fn test_restart_history_is_bounded() {
+        let controller = WrappeeController::new(&WrappeeConfig::default());
+        for i in 0..RESTART_HISTORY + 2 {
+            controller.record_restart(format!("restart {i}")).await;
+        }
+
+        let (count, history) = controller.restarts().await;
+        assert_eq!(count as usize, RESTART_HISTORY + 2);
+        assert_eq!(history.len(), RESTART_HISTORY);
+        assert_eq!(history[0].reason, "restart 2");
+        assert_eq!(
+            history.last().unwrap().reason,
+            format!("restart {}", RESTART_HISTORY + 1)
+        );
+    }
+
+    #[test]
+    fn test_missed_client_capabilities_are_reported_once() {
+        let controller = WrappeeController::new(&WrappeeConfig::default());
+        controller.set_client_info(InitializeRequestParam {
+            capabilities: ClientCapabilities::builder().enable_sampling().build(),
+            ..Default::default()
+        });
+        // Nothing is missed while no wrappee has been initialized
+        assert_eq!(controller.take_missed_client_info(), None);
+
+        controller
+            .wrappee_info
+            .send_replace(Some(InitializeResult::default()));
+        assert_eq!(
+            controller.take_missed_client_info(),
+            Some(MissedClientInfo::Capabilities)
+        );
+        assert_eq!(controller.take_missed_client_info(), None);
+    }
+
+    #[test]
+    fn test_missed_protocol_version_is_reported() {
+        let controller = WrappeeController::new(&WrappeeConfig::default());
+        controller.wrappee_info.send_replace(Some(InitializeResult {
+            protocol_version: rmcp::model::ProtocolVersion::V_2025_03_26,
+            ..Default::default()
+        }));
+
+        // Versions Wrap-MCP does not know are not worth a restart
+        controller.set_client_info(InitializeRequestParam {
+            protocol_version: serde_json::from_value(json!("2099-01-01")).unwrap(),
+            ..Default::default()
+        });
+        assert_eq!(controller.take_missed_client_info(), None);
+
+        controller.set_client_info(InitializeRequestParam {
+            protocol_version: rmcp::model::ProtocolVersion::V_2024_11_05,
+            ..Default::default()
+        });
+        assert_eq!(
+            controller.take_missed_client_info(),
+            Some(MissedClientInfo::ProtocolVersion("2024-11-05".to_string()))
+        );
+        assert_eq!(controller.take_missed_client_info(), None);
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_client_fails_when_down() {
+        let controller = WrappeeController::new(&WrappeeConfig::default());
+        let waiting = controller.clone();
+        let wait = tokio::spawn(async move { waiting.wait_for_client().await });
+
+        controller.mark_down("restart failed");
+        let err = wait.await.unwrap().unwrap_err();
+        assert_eq!(err.message, "Wrappee unavailable: restart failed");
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_client_times_out_while_starting() {
+        let controller = WrappeeController::new(&WrappeeConfig {
+            ready_timeout_secs: 0,
+            ..WrappeeConfig::default()
+        });
+        assert_eq!(controller.readiness(), Readiness::Starting);
+
+        let err = controller.wait_for_client().await.unwrap_err();
+        assert_eq!(err.message, "Wrappee did not become ready within 0 seconds");
+    }
+
+    #[test]
+    fn test_parse_response_result() {
+        let response = json!({"jsonrpc": "2.0", "id": 1, "result": {"contents": []}});
+        let result: rmcp::model::ReadResourceResult = parse_response(&response).unwrap();
+        assert!(result.contents.is_empty());
+    }
+
+    #[test]
+    fn test_parse_response_preserves_error_code() {
+        let response = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "error": {"code": -32002, "message": "Resource not found", "data": {"uri": "file:///x"}}
+        });
+        let err = parse_response::<Value>(&response).unwrap_err();
+        assert_eq!(err.code, ErrorCode::RESOURCE_NOT_FOUND);
+        assert_eq!(err.message, "Resource not found");
+        assert_eq!(err.data, Some(json!({"uri": "file:///x"})));
+    }
 }
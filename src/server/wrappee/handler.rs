@@ -1,35 +1,35 @@
+use super::{Readiness, parse_response};
 use crate::server::WrapServer;
-use crate::{
-    cli::CliOptions,
-    tools::{
-        clear_log::{ClearLogRequest, clear_log},
-        show_log::{ShowLogRequest, show_log},
-    },
+use crate::tools::{
+    clear_log::{ClearLogRequest, clear_log},
+    show_log::{ShowLogRequest, show_log},
+    wrap_status::{WrapStatus, WrapStatusRequest, wrap_status},
 };
+use crate::wrappee::ShutdownOutcome;
 use anyhow::Result;
-use rmcp::{ErrorData as McpError, model::*};
+use rmcp::{ErrorData as McpError, RoleServer, model::*, service::RequestContext};
+use serde::de::DeserializeOwned;
 use serde_json::Value;
+use std::sync::atomic::Ordering;
+use tokio::time::Instant;
 
 impl WrapServer {
     pub async fn initialize_wrappee(&self) -> Result<()> {
-        // Parse command line arguments
-        let opts = CliOptions::from_args();
+        let launch = self.launch.clone();
+        let target = launch
+            .target()
+            .ok_or_else(|| anyhow::anyhow!("No wrappee command specified"))?;
 
         // Configure ANSI removal
         self.tool_manager
             .log_storage
-            .set_ansi_removal(!opts.preserve_ansi)
+            .set_ansi_removal(!launch.preserve_ansi)
             .await;
 
         // Initialize the wrappee
         let init_result = self
             .wrappee_controller
-            .initialize(
-                &opts.command,
-                &opts.args,
-                opts.disable_colors(),
-                &self.tool_manager,
-            )
+            .initialize(target.clone(), &self.tool_manager)
             .await;
 
         match init_result {
@@ -39,11 +39,10 @@ impl WrapServer {
             }
             Err(e) => {
                 // If not in watch mode, return error instead of panicking
-                if !opts.watch_binary {
-                    tracing::error!("Failed to spawn wrappee process '{}': {e}", opts.command);
+                if !launch.watch_binary {
+                    tracing::error!("Failed to spawn wrappee process '{target}': {e}");
                     return Err(anyhow::anyhow!(
-                        "Failed to spawn wrappee process '{}': {e}",
-                        opts.command
+                        "Failed to spawn wrappee process '{target}': {e}"
                     ));
                 }
                 // In watch mode, log the error but continue to set up file watching
@@ -51,8 +50,11 @@ impl WrapServer {
             }
         }
 
+        // Ping the wrappee, whichever instance is current
+        self.start_health_monitoring();
+
         // Start file watching if enabled
-        if opts.watch_binary {
+        if launch.watch_binary {
             tracing::info!("Binary file watching enabled (-w option)");
             self.start_file_watching().await?;
         }
@@ -60,18 +62,28 @@ impl WrapServer {
         Ok(())
     }
 
-    pub async fn restart_wrapped_server(&self) -> Result<CallToolResult, McpError> {
-        tracing::info!("Restarting wrapped server");
+    /// Restart the wrappee, recording the given reason in its restart history
+    pub async fn restart_wrapped_server(&self, reason: &str) -> Result<CallToolResult, McpError> {
+        tracing::info!("Restarting wrapped server ({reason})");
 
         // Restart the wrappee
-        self.wrappee_controller
-            .restart(&self.tool_manager)
-            .await
-            .map_err(|e| McpError {
-                code: ErrorCode::INTERNAL_ERROR,
-                message: format!("Failed to restart wrapped server: {e}").into(),
-                data: None,
-            })?;
+        let outcome = self
+            .wrappee_controller
+            .restart(&self.tool_manager, reason)
+            .await;
+        self.finish_restart(outcome).await
+    }
+
+    /// Bring the rest of the server in line with a restarted wrappee
+    pub(crate) async fn finish_restart(
+        &self,
+        outcome: Result<Option<ShutdownOutcome>>,
+    ) -> Result<CallToolResult, McpError> {
+        let outcome = outcome.map_err(|e| McpError {
+            code: ErrorCode::INTERNAL_ERROR,
+            message: format!("Failed to restart wrapped server: {e}").into(),
+            data: None,
+        })?;
 
         // Send tool list changed notification
         self.notify_tools_changed().await;
@@ -79,9 +91,55 @@ impl WrapServer {
         // Restart stderr monitoring
         self.start_stderr_monitoring();
 
-        Ok(CallToolResult::success(vec![Content::text(
-            "✅ Wrapped server restarted successfully",
-        )]))
+        let text = match outcome {
+            Some(outcome) => {
+                format!("✅ Wrapped server restarted successfully (previous instance: {outcome})")
+            }
+            None => "✅ Wrapped server restarted successfully".to_string(),
+        };
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
+    /// Collect the state reported by the `wrap_status` tool
+    pub async fn status(&self) -> WrapStatus {
+        let controller = &self.wrappee_controller;
+        let (client_protocol, wrappee_protocol) = controller.negotiations().await;
+        let (restart_count, restarts) = controller.restarts().await;
+        let log_storage = &self.tool_manager.log_storage;
+
+        WrapStatus {
+            target: controller.get_target().await.map(|t| t.to_string()),
+            running: controller.is_active().await,
+            down_reason: match controller.readiness() {
+                Readiness::Down(reason) => Some(reason),
+                _ => None,
+            },
+            pid: controller.get_pid().await,
+            uptime_secs: controller
+                .get_client()
+                .await
+                .map(|client| client.uptime().as_secs()),
+            restart_count,
+            restarts,
+            server: controller
+                .wrappee_info()
+                .map(|info| format!("{} {}", info.server_info.name, info.server_info.version)),
+            client_protocol,
+            wrappee_protocol,
+            health: if controller.config.health.interval_secs > 0 {
+                Some(controller.health().await)
+            } else {
+                None
+            },
+            tool_count: self.tool_manager.wrappee_tools.read().await.len(),
+            log_entries: log_storage.get_log_count().await,
+            log_capacity: log_storage.max_entries(),
+            log_dir: log_storage
+                .persist_dir()
+                .map(|dir| dir.display().to_string()),
+            log_db: log_storage.db_path().map(|db| db.display().to_string()),
+            file_watching: self.file_watching.load(Ordering::SeqCst),
+        }
     }
 
     /// Handle tool calls - both built-in and proxied tools
@@ -89,10 +147,11 @@ impl WrapServer {
         &self,
         name: &str,
         arguments: Value,
+        context: &RequestContext<RoleServer>,
     ) -> Result<CallToolResult, McpError> {
         // Handle built-in tools
         match name {
-            "restart_wrapped_server" => self.restart_wrapped_server().await,
+            "restart_wrapped_server" => self.restart_wrapped_server("requested by client").await,
             "show_log" => {
                 let req: ShowLogRequest =
                     serde_json::from_value(arguments).map_err(|e| McpError {
@@ -111,12 +170,128 @@ impl WrapServer {
                     })?;
                 clear_log(req, &self.tool_manager.log_storage).await
             }
+            "wrap_status" => {
+                let req: WrapStatusRequest =
+                    serde_json::from_value(arguments).map_err(|e| McpError {
+                        code: ErrorCode::INVALID_PARAMS,
+                        message: format!("Invalid parameters: {e}").into(),
+                        data: None,
+                    })?;
+                wrap_status(req, self.status().await).await
+            }
             _ => {
                 // Proxy to wrappee
                 self.wrappee_controller
-                    .proxy_tool_call(name, arguments, &self.tool_manager)
+                    .proxy_tool_call(name, arguments, &self.tool_manager, context)
                     .await
             }
         }
     }
+
+    /// Forward a non-tool request to the wrappee and parse its result
+    pub(crate) async fn proxy_request<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<T, McpError> {
+        let response = self
+            .wrappee_controller
+            .proxy_request(method, params)
+            .await?;
+        parse_response(&response)
+    }
+
+    /// Forward a resources/read request to the wrappee, logging it like a tool call
+    pub(crate) async fn proxy_resource_read(
+        &self,
+        request: ReadResourceRequestParam,
+    ) -> Result<ReadResourceResult, McpError> {
+        const METHOD: &str = "resources/read";
+        let params = serde_json::to_value(&request).unwrap_or_default();
+        let request_id = self
+            .tool_manager
+            .log_storage
+            .add_request(METHOD.to_string(), params.clone())
+            .await;
+        self.proxy_logged(METHOD, params, request_id, None).await
+    }
+
+    /// Forward a prompts/get request to the wrappee, logging it as a prompt fetch
+    pub(crate) async fn proxy_prompt_get(
+        &self,
+        request: GetPromptRequestParam,
+    ) -> Result<GetPromptResult, McpError> {
+        let arguments = request
+            .arguments
+            .clone()
+            .map(Value::Object)
+            .unwrap_or(Value::Object(serde_json::Map::new()));
+        let request_id = self
+            .tool_manager
+            .log_storage
+            .add_prompt(request.name.clone(), arguments)
+            .await;
+        let params = serde_json::to_value(&request).unwrap_or_default();
+        self.proxy_logged("prompts/get", params, request_id, Some(request.name))
+            .await
+    }
+
+    /// Forward a request already logged as `request_id` to the wrappee, logging its
+    /// response or error with the time it took. Answers to prompt fetches are logged
+    /// under the prompt's name.
+    async fn proxy_logged<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Value,
+        request_id: crate::types::RequestId,
+        prompt_name: Option<String>,
+    ) -> Result<T, McpError> {
+        let log_storage = &self.tool_manager.log_storage;
+
+        let started = Instant::now();
+        let outcome = self.wrappee_controller.proxy_request(method, params).await;
+        let latency = Some(started.elapsed());
+
+        let result = match outcome {
+            Ok(response) => {
+                match prompt_name.clone() {
+                    Some(prompt_name) => {
+                        log_storage
+                            .add_prompt_response(request_id, prompt_name, response.clone(), latency)
+                            .await
+                    }
+                    None => {
+                        log_storage
+                            .add_response_with_latency(
+                                request_id,
+                                method.to_string(),
+                                response.clone(),
+                                latency,
+                            )
+                            .await
+                    }
+                }
+                parse_response(&response)
+            }
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = &result {
+            let message = e.message.to_string();
+            match prompt_name {
+                Some(prompt_name) => {
+                    log_storage
+                        .add_prompt_error(request_id, prompt_name, message, latency)
+                        .await
+                }
+                None => {
+                    log_storage
+                        .add_error_with_latency(request_id, method.to_string(), message, latency)
+                        .await
+                }
+            }
+        }
+
+        result
+    }
 }
@@ -1,4 +1,5 @@
 mod controller;
 pub mod handler; // Make handler public so its impl blocks are accessible
 
-pub use controller::WrappeeController;
+pub(crate) use controller::parse_response;
+pub use controller::{MissedClientInfo, Readiness, WrappeeController};
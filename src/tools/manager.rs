@@ -1,11 +1,27 @@
 use crate::logging::LogStorage;
-use crate::wrappee::WrappeeClient;
+use crate::types::RequestId;
+use crate::wrappee::{RequestCancelled, WrappeeClient};
 use anyhow::Result;
-use rmcp::{ErrorData as McpError, model::*};
+use rmcp::{
+    ErrorData as McpError, RoleServer,
+    model::*,
+    service::{Peer, RequestContext},
+};
 use serde_json::{Map, Value};
 use std::borrow::Cow;
 use std::sync::{Arc, LazyLock};
-use tokio::sync::RwLock;
+use tokio::sync::{RwLock, mpsc};
+use tokio::time::{Duration, Instant};
+
+/// Where progress notifications for an in-flight tool call should go
+#[derive(Debug, Clone)]
+struct ProgressRoute {
+    peer: Peer<RoleServer>,
+    request_id: RequestId,
+    tool_name: String,
+    /// The token the client asked for progress under
+    token: ProgressToken,
+}
 
 #[derive(Debug, Clone)]
 pub struct ToolManager {
@@ -21,7 +37,7 @@ impl ToolManager {
         }
     }
 
-    pub async fn discover_tools(&self, wrappee: &mut WrappeeClient) -> Result<()> {
+    pub async fn discover_tools(&self, wrappee: &WrappeeClient) -> Result<()> {
         tracing::info!("Discovering tools from wrappee");
 
         let response = wrappee.list_tools().await?;
@@ -63,13 +79,14 @@ impl ToolManager {
         let wrappee_tools = self.wrappee_tools.read().await;
 
         // Create a new vector with capacity for all tools
-        let mut all_tools = Vec::with_capacity(wrappee_tools.len() + 3);
+        let mut all_tools = Vec::with_capacity(wrappee_tools.len() + 4);
         all_tools.extend(wrappee_tools.iter().cloned());
 
         // Add wrapper-provided tools
         all_tools.push(create_show_log_tool());
         all_tools.push(create_clear_log_tool());
         all_tools.push(create_restart_wrapped_server_tool());
+        all_tools.push(create_wrap_status_tool());
 
         all_tools
     }
@@ -78,7 +95,9 @@ impl ToolManager {
         &self,
         name: &str,
         arguments: Value,
-        wrappee: &mut WrappeeClient,
+        wrappee: &WrappeeClient,
+        timeout: Duration,
+        context: &RequestContext<RoleServer>,
     ) -> Result<CallToolResult, McpError> {
         tracing::info!("Proxying tool call: {name}");
 
@@ -88,12 +107,43 @@ impl ToolManager {
             .add_request(name.to_string(), arguments.clone())
             .await;
 
-        // Forward to wrappee
-        match wrappee.call_tool(name, arguments).await {
+        // Forward to wrappee, relaying its progress back to the calling peer
+        let started = Instant::now();
+        let outcome = match context.meta.get_progress_token() {
+            Some(token) => {
+                // Clients pick their tokens independently of each other, so the wrappee
+                // is given one of ours and its progress is relayed under the client's
+                let (wrappee_token, mut updates) = wrappee.watch_progress();
+                let call =
+                    wrappee.call_tool(name, arguments, Some(&wrappee_token), timeout, &context.ct);
+                let route = ProgressRoute {
+                    peer: context.peer.clone(),
+                    request_id,
+                    tool_name: name.to_string(),
+                    token,
+                };
+                let outcome = self.relay_progress_during(call, &mut updates, &route).await;
+                wrappee.unwatch_progress(&wrappee_token);
+                outcome
+            }
+            None => {
+                wrappee
+                    .call_tool(name, arguments, None, timeout, &context.ct)
+                    .await
+            }
+        };
+        let latency = Some(started.elapsed());
+
+        match outcome {
             Ok(response) => {
                 // Log the response
                 self.log_storage
-                    .add_response(request_id, name.to_string(), response.clone())
+                    .add_response_with_latency(
+                        request_id,
+                        name.to_string(),
+                        response.clone(),
+                        latency,
+                    )
                     .await;
 
                 // Extract the result from the response
@@ -118,7 +168,12 @@ impl ToolManager {
                     let error_data = error.get("data").cloned();
 
                     self.log_storage
-                        .add_error(request_id, name.to_string(), error_msg.clone())
+                        .add_error_with_latency(
+                            request_id,
+                            name.to_string(),
+                            error_msg.clone(),
+                            latency,
+                        )
                         .await;
 
                     Err(McpError {
@@ -132,11 +187,27 @@ impl ToolManager {
                     )]))
                 }
             }
+            Err(e) if e.downcast_ref::<RequestCancelled>().is_some() => {
+                self.log_storage
+                    .add_cancelled(request_id, name.to_string(), e.root_cause().to_string())
+                    .await;
+
+                Err(McpError {
+                    code: ErrorCode::INTERNAL_ERROR,
+                    message: "Request cancelled".into(),
+                    data: None,
+                })
+            }
             Err(e) => {
                 let error_msg = format!("Failed to call tool: {e}");
 
                 self.log_storage
-                    .add_error(request_id, name.to_string(), error_msg.clone())
+                    .add_error_with_latency(
+                        request_id,
+                        name.to_string(),
+                        error_msg.clone(),
+                        latency,
+                    )
                     .await;
 
                 Err(McpError {
@@ -147,6 +218,58 @@ impl ToolManager {
             }
         }
     }
+
+    /// Wait for a call to finish while relaying its progress notifications
+    async fn relay_progress_during(
+        &self,
+        call: impl Future<Output = Result<Value>>,
+        updates: &mut mpsc::UnboundedReceiver<Value>,
+        route: &ProgressRoute,
+    ) -> Result<Value> {
+        tokio::pin!(call);
+        loop {
+            tokio::select! {
+                biased;
+                Some(update) = updates.recv() => self.relay_progress(update, route).await,
+                outcome = &mut call => {
+                    // Progress sent before the response must still reach the client first
+                    while let Ok(update) = updates.try_recv() {
+                        self.relay_progress(update, route).await;
+                    }
+                    return outcome;
+                }
+            }
+        }
+    }
+
+    /// Relay a wrappee progress notification to the peer that made the call
+    async fn relay_progress(&self, update: Value, route: &ProgressRoute) {
+        let params = match update
+            .get("params")
+            .cloned()
+            .map(serde_json::from_value::<ProgressNotificationParam>)
+        {
+            Some(Ok(params)) => ProgressNotificationParam {
+                progress_token: route.token.clone(),
+                ..params
+            },
+            _ => return tracing::warn!("Invalid progress notification: {update}"),
+        };
+
+        self.log_storage
+            .add_progress(
+                route.request_id,
+                route.tool_name.clone(),
+                params.progress,
+                params.total,
+                params.message.clone(),
+            )
+            .await;
+
+        if let Err(e) = route.peer.notify_progress(params).await {
+            tracing::warn!("Failed to forward progress notification: {e}");
+        }
+    }
 }
 
 // Static JSON schemas for tools
@@ -161,17 +284,21 @@ static SHOW_LOG_SCHEMA: LazyLock<Map<String, Value>> = LazyLock::new(|| {
         },
         "tool_name": {
             "type": "string",
-            "description": "Filter logs by tool name"
+            "description": "Filter logs by tool name (or prompt name)"
         },
         "entry_type": {
             "type": "string",
-            "enum": ["request", "response", "error", "stderr"],
+            "enum": ["request", "response", "error", "stderr", "prompt", "cancelled", "progress", "log", "handshake", "exited", "health", "raw"],
             "description": "Filter logs by entry type"
         },
         "keyword": {
             "type": "string",
             "description": "Regular expression pattern to search in log content (fallback to literal search if invalid regex)"
         },
+        "request_id": {
+            "type": "integer",
+            "description": "Show only this request and the entries that refer to it (response, error, progress, cancellation)"
+        },
         "format": {
             "type": "string",
             "enum": ["ai", "text", "json"],
@@ -224,3 +351,15 @@ fn create_restart_wrapped_server_tool() -> Tool {
         annotations: None,
     }
 }
+
+fn create_wrap_status_tool() -> Tool {
+    Tool {
+        name: Cow::Borrowed("wrap_status"),
+        description: Some(Cow::Borrowed(
+            "Show the state of the wrapped server: PID, uptime, restarts, protocol versions, health, tools, log usage and file watching",
+        )),
+        input_schema: Arc::new(EMPTY_SCHEMA.clone()),
+        output_schema: None,
+        annotations: None,
+    }
+}
@@ -1,5 +1,6 @@
 pub mod clear_log;
 pub mod manager;
 pub mod show_log;
+pub mod wrap_status;
 
 pub use manager::ToolManager;
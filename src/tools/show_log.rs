@@ -1,4 +1,4 @@
-use crate::logging::{LogEntry, LogEntryContent, LogEntryType, LogFilter, LogStorage};
+use crate::logging::{Direction, LogEntry, LogEntryContent, LogEntryType, LogFilter, LogStorage};
 use crate::types::RequestId;
 use anyhow::Result;
 use rmcp::{ErrorData as McpError, model::*};
@@ -20,6 +20,9 @@ pub struct ShowLogRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub keyword: Option<String>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<usize>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub format: Option<String>,
 }
@@ -52,17 +55,41 @@ fn format_request_entry(id: RequestId, tool_name: &str, args: &Value) -> String
     format!("[REQUEST #{id}] {tool_name}({args_str})\n")
 }
 
+// Format the latency suffix of a response/error label
+fn format_latency(duration_ms: Option<u64>) -> String {
+    duration_ms
+        .map(|ms| format!(" ({ms}ms)"))
+        .unwrap_or_default()
+}
+
 // Format a response log entry
-fn format_response_entry(request_id: RequestId, response: &Value) -> String {
+fn format_response_entry(
+    request_id: RequestId,
+    response: &Value,
+    duration_ms: Option<u64>,
+) -> String {
     let mut output = String::new();
+    let latency = format_latency(duration_ms);
 
+    // Tool results carry "content", resource reads carry "contents"
     if let Some(result) = response.get("result")
-        && let Some(content_array) = result.get("content")
+        && let Some(content_array) = result.get("content").or_else(|| result.get("contents"))
         && let Some(arr) = content_array.as_array()
     {
         for item in arr {
             if let Some(text) = item["text"].as_str() {
-                output.push_str(&format!("[RESPONSE #{request_id}] \"{text}\"\n"));
+                output.push_str(&format!("[RESPONSE #{request_id}]{latency} \"{text}\"\n"));
+            }
+        }
+    }
+
+    // Prompt results carry "messages" whose content holds the text
+    if let Some(result) = response.get("result")
+        && let Some(messages) = result.get("messages").and_then(|m| m.as_array())
+    {
+        for message in messages {
+            if let Some(text) = message["content"]["text"].as_str() {
+                output.push_str(&format!("[RESPONSE #{request_id}]{latency} \"{text}\"\n"));
             }
         }
     }
@@ -70,9 +97,100 @@ fn format_response_entry(request_id: RequestId, response: &Value) -> String {
     output
 }
 
+// Format a prompt fetch log entry
+fn format_prompt_entry(id: RequestId, prompt_name: &str, args: &Value) -> String {
+    let args_str = format_request_args(args);
+    format!("[PROMPT #{id}] {prompt_name}({args_str})\n")
+}
+
+// Format a cancellation log entry
+fn format_cancelled_entry(request_id: RequestId, reason: &str) -> String {
+    format!("[CANCELLED #{request_id}] {reason}\n")
+}
+
+// Format a progress log entry
+fn format_progress_entry(
+    request_id: RequestId,
+    progress: f64,
+    total: Option<f64>,
+    message: Option<&str>,
+) -> String {
+    let amount = match total {
+        Some(total) => format!("{progress}/{total}"),
+        None => progress.to_string(),
+    };
+    match message {
+        Some(message) => format!("[PROGRESS #{request_id}] {amount} {message}\n"),
+        None => format!("[PROGRESS #{request_id}] {amount}\n"),
+    }
+}
+
+// Format a structured log message from the wrappee
+fn format_log_entry(level: &str, logger: Option<&str>, data: &Value) -> String {
+    let data_str = match data {
+        Value::String(s) => s.clone(),
+        _ => data.to_string(),
+    };
+    match logger {
+        Some(logger) => format!("[LOG {level}] {logger}: {data_str}\n"),
+        None => format!("[LOG {level}] {data_str}\n"),
+    }
+}
+
+// Format a protocol negotiation with the client or the wrappee
+fn format_handshake_entry(
+    peer: &str,
+    implementation: Option<&str>,
+    requested: &str,
+    negotiated: &str,
+) -> String {
+    let who = match implementation {
+        Some(implementation) => format!("{peer} ({implementation})"),
+        None => peer.to_string(),
+    };
+    format!("[HANDSHAKE] {who}: requested {requested}, negotiated {negotiated}\n")
+}
+
+// Format the exit of the wrappee
+fn format_exited_entry(
+    code: Option<i32>,
+    signal: Option<i32>,
+    shutdown_stage: Option<&str>,
+) -> String {
+    let status = match (code, signal) {
+        (Some(code), _) => format!("exit code {code}"),
+        (None, Some(signal)) => format!("killed by signal {signal}"),
+        (None, None) => "disconnected".to_string(),
+    };
+    match shutdown_stage {
+        Some(stage) => format!("[EXITED] {status} (shutdown: {stage})\n"),
+        None => format!("[EXITED] {status}\n"),
+    }
+}
+
+// Format a change in the health of the wrappee
+fn format_health_entry(healthy: bool, failures: u32, error: Option<&str>) -> String {
+    match (healthy, error) {
+        (true, _) => "[HEALTH] healthy again\n".to_string(),
+        (false, Some(error)) => {
+            format!("[HEALTH] unhealthy after {failures} failed pings: {error}\n")
+        }
+        (false, None) => format!("[HEALTH] unhealthy after {failures} failed pings\n"),
+    }
+}
+
+// Format a JSON-RPC frame captured on the wire
+fn format_raw_entry(direction: Direction, method: Option<&str>, message: &Value) -> String {
+    match method {
+        Some(method) => format!("[RAW {direction}] {method} {message}\n"),
+        None => format!("[RAW {direction}] {message}\n"),
+    }
+}
+
 // Format an error log entry
-fn format_error_entry(request_id: RequestId, error: &str) -> String {
-    format!("[ERROR #{request_id}] {error}\n")
+fn format_error_entry(request_id: RequestId, error: &str, duration_ms: Option<u64>) -> String {
+    let latency = format_latency(duration_ms);
+    format!("[ERROR #{request_id}]{latency} {error}\n")
 }
 
 // Clean and format stderr message
@@ -126,12 +244,56 @@ fn format_ai_output(logs: Vec<LogEntry>) -> Content {
                 LogEntryContent::Response {
                     request_id,
                     response,
+                    duration_ms,
                     ..
-                } => format_response_entry(*request_id, response),
+                } => format_response_entry(*request_id, response, *duration_ms),
                 LogEntryContent::Error {
-                    request_id, error, ..
-                } => format_error_entry(*request_id, error),
+                    request_id,
+                    error,
+                    duration_ms,
+                    ..
+                } => format_error_entry(*request_id, error, *duration_ms),
                 LogEntryContent::Stderr { message } => format_stderr_entry(message),
+                LogEntryContent::Prompt {
+                    prompt_name,
+                    arguments,
+                } => format_prompt_entry(log.id, prompt_name, arguments),
+                LogEntryContent::Cancelled {
+                    request_id, reason, ..
+                } => format_cancelled_entry(*request_id, reason),
+                LogEntryContent::Progress {
+                    request_id,
+                    progress,
+                    total,
+                    message,
+                    ..
+                } => format_progress_entry(*request_id, *progress, *total, message.as_deref()),
+                LogEntryContent::Log {
+                    level,
+                    logger,
+                    data,
+                } => format_log_entry(level, logger.as_deref(), data),
+                LogEntryContent::Handshake {
+                    peer,
+                    implementation,
+                    requested,
+                    negotiated,
+                } => format_handshake_entry(peer, implementation.as_deref(), requested, negotiated),
+                LogEntryContent::Exited {
+                    code,
+                    signal,
+                    shutdown_stage,
+                } => format_exited_entry(*code, *signal, shutdown_stage.as_deref()),
+                LogEntryContent::Health {
+                    healthy,
+                    failures,
+                    error,
+                } => format_health_entry(*healthy, *failures, error.as_deref()),
+                LogEntryContent::Raw {
+                    direction,
+                    method,
+                    message,
+                } => format_raw_entry(*direction, method.as_deref(), message),
             };
 
             output.push_str(&formatted_entry);
@@ -188,6 +350,7 @@ pub async fn show_log(
         after: None,
         before: None,
         keyword: req.keyword,
+        request_id: req.request_id.map(RequestId::new),
     };
 
     let logs = log_storage.get_logs(Some(req.limit), Some(filter)).await;
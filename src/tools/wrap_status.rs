@@ -0,0 +1,213 @@
+use rmcp::{
+    ErrorData as McpError,
+    model::{CallToolResult, Content},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::types::{Health, Negotiation, RestartRecord};
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct WrapStatusRequest {}
+
+/// Snapshot of the proxy state reported by `wrap_status`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WrapStatus {
+    pub target: Option<String>,
+    pub running: bool,
+    /// Why the wrappee is down; `None` while it is running or (re)starting
+    pub down_reason: Option<String>,
+    pub pid: Option<u32>,
+    /// Seconds since the current wrappee was started
+    pub uptime_secs: Option<u64>,
+    /// Restarts so far and the most recent ones, oldest first
+    pub restart_count: u32,
+    pub restarts: Vec<RestartRecord>,
+    pub server: Option<String>,
+    pub client_protocol: Option<Negotiation>,
+    pub wrappee_protocol: Option<Negotiation>,
+    /// Ping results, `None` when health checks are disabled
+    pub health: Option<Health>,
+    /// Tools discovered from the wrappee
+    pub tool_count: usize,
+    pub log_entries: usize,
+    pub log_capacity: usize,
+    /// Directory log entries are persisted to, if any
+    pub log_dir: Option<String>,
+    /// SQLite database log entries are kept in, if any
+    pub log_db: Option<String>,
+    pub file_watching: bool,
+}
+
+fn format_negotiation(negotiation: Option<&Negotiation>) -> String {
+    match negotiation {
+        Some(n) if n.requested == n.negotiated => n.negotiated.clone(),
+        Some(n) => format!("{} (requested {})", n.negotiated, n.requested),
+        None => "not negotiated".to_string(),
+    }
+}
+
+fn format_uptime(secs: u64) -> String {
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    match (hours, minutes) {
+        (0, 0) => format!("{seconds}s"),
+        (0, _) => format!("{minutes}m {seconds}s"),
+        _ => format!("{hours}h {minutes}m {seconds}s"),
+    }
+}
+
+fn format_health(health: Option<&Health>) -> String {
+    let Some(health) = health else {
+        return "not checked".to_string();
+    };
+    if !health.healthy {
+        let error = health.last_error.as_deref().unwrap_or("unknown error");
+        return format!(
+            "unhealthy ({} failed pings, last error: {error})",
+            health.consecutive_failures
+        );
+    }
+    match (health.last_latency_ms, health.consecutive_failures) {
+        (_, failures) if failures > 0 => format!("healthy ({failures} failed pings)"),
+        (Some(latency), _) => format!("healthy (last ping {latency} ms)"),
+        (None, _) => "healthy (not pinged yet)".to_string(),
+    }
+}
+
+fn format_status(status: &WrapStatus) -> String {
+    let mut output = String::new();
+
+    let target = status.target.as_deref().unwrap_or("none");
+    output.push_str(&format!("Wrappee: {target}\n"));
+
+    let state = match (status.running, status.pid) {
+        (true, Some(pid)) => format!("running (pid {pid})"),
+        (true, None) => "connected".to_string(),
+        (false, _) => match &status.down_reason {
+            Some(reason) => format!("down ({reason})"),
+            None => "starting".to_string(),
+        },
+    };
+    output.push_str(&format!("State: {state}\n"));
+
+    if let Some(uptime) = status.uptime_secs {
+        output.push_str(&format!("Uptime: {}\n", format_uptime(uptime)));
+    }
+
+    if let Some(server) = &status.server {
+        output.push_str(&format!("Server: {server}\n"));
+    }
+
+    output.push_str(&format!(
+        "Protocol (client): {}\n",
+        format_negotiation(status.client_protocol.as_ref())
+    ));
+    output.push_str(&format!(
+        "Protocol (wrappee): {}\n",
+        format_negotiation(status.wrappee_protocol.as_ref())
+    ));
+    output.push_str(&format!(
+        "Health: {}\n",
+        format_health(status.health.as_ref())
+    ));
+
+    output.push_str(&format!("Restarts: {}\n", status.restart_count));
+    for restart in &status.restarts {
+        output.push_str(&format!(
+            "  - {}: {}\n",
+            restart.at.format("%Y-%m-%d %H:%M:%S UTC"),
+            restart.reason
+        ));
+    }
+
+    output.push_str(&format!("Tools: {}\n", status.tool_count));
+    output.push_str(&format!(
+        "Log: {}/{} entries",
+        status.log_entries, status.log_capacity
+    ));
+    if let Some(dir) = &status.log_dir {
+        output.push_str(&format!(" (persisted to {dir})"));
+    }
+    if let Some(db) = &status.log_db {
+        output.push_str(&format!(" (stored in {db})"));
+    }
+    output.push('\n');
+    let watching = if status.file_watching {
+        "active"
+    } else {
+        "inactive"
+    };
+    output.push_str(&format!("File watching: {watching}\n"));
+
+    output
+}
+
+pub async fn wrap_status(
+    _req: WrapStatusRequest,
+    status: WrapStatus,
+) -> Result<CallToolResult, McpError> {
+    tracing::debug!("wrap_status called");
+
+    Ok(CallToolResult::success(vec![Content::text(format_status(
+        &status,
+    ))]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_format_status() {
+        let status = WrapStatus {
+            target: Some("my-server --flag".to_string()),
+            running: true,
+            down_reason: None,
+            pid: Some(42),
+            uptime_secs: Some(3725),
+            restart_count: 1,
+            restarts: vec![RestartRecord {
+                at: chrono::Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap(),
+                reason: "binary changed".to_string(),
+            }],
+            server: Some("inner 1.0".to_string()),
+            client_protocol: Some(Negotiation {
+                requested: "2025-06-18".to_string(),
+                negotiated: "2025-03-26".to_string(),
+            }),
+            wrappee_protocol: None,
+            health: Some(Health {
+                last_latency_ms: Some(3),
+                ..Health::default()
+            }),
+            tool_count: 4,
+            log_entries: 12,
+            log_capacity: 1000,
+            log_dir: None,
+            log_db: None,
+            file_watching: true,
+        };
+
+        let output = format_status(&status);
+        assert!(output.contains("Wrappee: my-server --flag"));
+        assert!(output.contains("State: running (pid 42)"));
+        assert!(output.contains("Protocol (client): 2025-03-26 (requested 2025-06-18)"));
+        assert!(output.contains("Protocol (wrappee): not negotiated"));
+        assert!(output.contains("Health: healthy (last ping 3 ms)"));
+        assert!(output.contains("Uptime: 1h 2m 5s"));
+        assert!(output.contains("Restarts: 1\n  - 2025-01-02 03:04:05 UTC: binary changed"));
+        assert!(output.contains("Tools: 4"));
+        assert!(output.contains("Log: 12/1000 entries"));
+        assert!(output.contains("File watching: active"));
+
+        let status = WrapStatus {
+            down_reason: Some("restart failed after 5 attempts".to_string()),
+            ..WrapStatus::default()
+        };
+        let output = format_status(&status);
+        assert!(output.contains("Wrappee: none"));
+        assert!(output.contains("State: down (restart failed after 5 attempts)"));
+        assert!(output.contains("Health: not checked"));
+    }
+}
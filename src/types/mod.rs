@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -81,3 +82,38 @@ impl AsRef<str> for ToolName {
         &self.0
     }
 }
+
+/// Protocol version requested on one leg of the proxy and the one finally agreed on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Negotiation {
+    pub requested: String,
+    pub negotiated: String,
+}
+
+/// Health of the current wrappee as determined by periodic pings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Health {
+    /// Cleared once the failure threshold is reached, set again by the next answered ping
+    pub healthy: bool,
+    pub consecutive_failures: u32,
+    pub last_latency_ms: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self {
+            healthy: true,
+            consecutive_failures: 0,
+            last_latency_ms: None,
+            last_error: None,
+        }
+    }
+}
+
+/// A restart of the wrappee and what caused it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RestartRecord {
+    pub at: DateTime<Utc>,
+    pub reason: String,
+}
@@ -0,0 +1,379 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::logging::{Direction, WireTap};
+
+/// Kind of a JSON-RPC message received from the wrappee
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    /// Response (result or error) to one of our requests
+    Response,
+    /// Request initiated by the wrappee (e.g. ping, sampling)
+    Request,
+    /// Notification from the wrappee
+    Notification,
+    /// Not a valid JSON-RPC message
+    Invalid,
+}
+
+impl MessageKind {
+    pub fn of(message: &Value) -> Self {
+        let has_id = message.get("id").is_some_and(|id| !id.is_null());
+        let has_method = message.get("method").is_some();
+        let has_outcome = message.get("result").is_some() || message.get("error").is_some();
+
+        match (has_method, has_id) {
+            (true, true) => MessageKind::Request,
+            (true, false) => MessageKind::Notification,
+            (false, true) if has_outcome => MessageKind::Response,
+            _ => MessageKind::Invalid,
+        }
+    }
+}
+
+/// Wrappee-initiated message that is not a response
+enum Inbound {
+    Notification(Value),
+    Request(Value),
+}
+
+impl Inbound {
+    fn blocking_send(self, routes: &Routes) {
+        let dropped = match self {
+            Inbound::Notification(message) => routes.notifications.blocking_send(message).is_err(),
+            Inbound::Request(message) => routes.requests.blocking_send(message).is_err(),
+        };
+        if dropped {
+            tracing::debug!("Event channel closed, dropping wrappee message");
+        }
+    }
+
+    async fn send(self, routes: &Routes) {
+        let dropped = match self {
+            Inbound::Notification(message) => routes.notifications.send(message).await.is_err(),
+            Inbound::Request(message) => routes.requests.send(message).await.is_err(),
+        };
+        if dropped {
+            tracing::debug!("Event channel closed, dropping wrappee message");
+        }
+    }
+}
+
+/// Channels that receive wrappee-initiated traffic
+#[derive(Debug, Clone)]
+pub struct Routes {
+    pub notifications: mpsc::Sender<Value>,
+    pub requests: mpsc::Sender<Value>,
+    /// Lines that are not JSON-RPC at all (e.g. stray log output on stdout)
+    pub stray: mpsc::Sender<String>,
+}
+
+/// Correlates outgoing requests with incoming responses by JSON-RPC id
+#[derive(Debug, Clone)]
+pub struct Dispatcher {
+    next_id: Arc<AtomicU64>,
+    pending: Arc<Mutex<HashMap<u64, oneshot::Sender<Value>>>>,
+    /// Progress notifications are routed to the call that owns the token, so that
+    /// they stay ordered ahead of that call's response. Tokens are handed out here
+    /// rather than taken from clients, whose tokens may collide with each other.
+    progress: Arc<Mutex<HashMap<String, mpsc::UnboundedSender<Value>>>>,
+    /// Captures every message received from the wrappee, if raw logging is enabled
+    tap: Option<WireTap>,
+}
+
+impl Default for Dispatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Dispatcher {
+    pub fn new() -> Self {
+        Self::with_tap(None)
+    }
+
+    /// Create a dispatcher that also records each incoming message on the tap
+    pub fn with_tap(tap: Option<WireTap>) -> Self {
+        Self {
+            next_id: Arc::new(AtomicU64::new(1)),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            progress: Arc::new(Mutex::new(HashMap::new())),
+            tap,
+        }
+    }
+
+    /// Allocate a new request id and register a slot for its response
+    pub fn register(&self) -> (u64, oneshot::Receiver<Value>) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.pending_map().insert(id, tx);
+        (id, rx)
+    }
+
+    /// Drop the slot of a request that will no longer be awaited
+    pub fn forget(&self, id: u64) -> bool {
+        self.pending_map().remove(&id).is_some()
+    }
+
+    /// Number of requests still waiting for a response
+    pub fn pending_count(&self) -> usize {
+        self.pending_map().len()
+    }
+
+    /// Allocate a progress token no other call uses, and receive the progress
+    /// notifications carrying it until it is unwatched
+    pub fn watch_progress(&self) -> (String, mpsc::UnboundedReceiver<Value>) {
+        let token = format!(
+            "wrap-mcp-progress-{}",
+            self.next_id.fetch_add(1, Ordering::Relaxed)
+        );
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.progress_map().insert(token.clone(), tx);
+        (token, rx)
+    }
+
+    /// Stop routing progress notifications for this token
+    pub fn unwatch_progress(&self, token: &str) {
+        self.progress_map().remove(token);
+    }
+
+    /// Deliver a response to the request that is waiting for it
+    pub fn complete(&self, response: Value) {
+        let Some(id) = response.get("id").and_then(|id| id.as_u64()) else {
+            tracing::warn!("Discarding wrappee response with unexpected id: {response}");
+            return;
+        };
+
+        match self.pending_map().remove(&id) {
+            Some(tx) => {
+                if tx.send(response).is_err() {
+                    tracing::debug!("Requester for #{id} went away, discarding response");
+                }
+            }
+            None => tracing::warn!("Discarding wrappee response for unknown request #{id}"),
+        }
+    }
+
+    /// Fail every pending request (their receivers observe a closed channel)
+    pub fn close(&self) {
+        let mut pending = self.pending_map();
+        if !pending.is_empty() {
+            tracing::warn!("Dropping {} pending wrappee requests", pending.len());
+        }
+        pending.clear();
+    }
+
+    /// Route one line read from the wrappee to the right destination
+    /// (for blocking reader threads)
+    pub fn route_line(&self, line: String, routes: &Routes) {
+        let message = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => message,
+            Err(_) => {
+                tracing::warn!("Non JSON-RPC output on wrappee stdout: {line}");
+                let _ = routes.stray.blocking_send(line);
+                return;
+            }
+        };
+
+        if let Some(inbound) = self.accept(message) {
+            inbound.blocking_send(routes);
+        }
+    }
+
+    /// Route a message body received over HTTP (a single message or a batch)
+    pub async fn route_data(&self, data: &str, routes: &Routes) {
+        let messages = match serde_json::from_str::<Value>(data) {
+            Ok(Value::Array(batch)) => batch,
+            Ok(message) => vec![message],
+            Err(_) => {
+                tracing::warn!("Non JSON-RPC data from wrappee: {data}");
+                let _ = routes.stray.send(data.to_string()).await;
+                return;
+            }
+        };
+
+        for message in messages {
+            if let Some(inbound) = self.accept(message) {
+                inbound.send(routes).await;
+            }
+        }
+    }
+
+    /// Complete responses in place and hand back what has to be routed elsewhere
+    fn accept(&self, message: Value) -> Option<Inbound> {
+        if let Some(tap) = &self.tap {
+            tap.record(Direction::WrappeeToProxy, message.clone());
+        }
+
+        match MessageKind::of(&message) {
+            MessageKind::Response => {
+                self.complete(message);
+                None
+            }
+            MessageKind::Notification => match self.progress_watcher(&message) {
+                Some(tx) => {
+                    let _ = tx.send(message);
+                    None
+                }
+                None => Some(Inbound::Notification(message)),
+            },
+            MessageKind::Request => Some(Inbound::Request(message)),
+            MessageKind::Invalid => {
+                tracing::warn!("Ignoring invalid JSON-RPC message from wrappee: {message}");
+                None
+            }
+        }
+    }
+
+    /// Find the call waiting for a progress notification, if this is one
+    fn progress_watcher(&self, message: &Value) -> Option<mpsc::UnboundedSender<Value>> {
+        if message.get("method").and_then(|m| m.as_str()) != Some("notifications/progress") {
+            return None;
+        }
+        let token = message.get("params")?.get("progressToken")?.as_str()?;
+        self.progress_map().get(token).cloned()
+    }
+
+    fn progress_map(
+        &self,
+    ) -> std::sync::MutexGuard<'_, HashMap<String, mpsc::UnboundedSender<Value>>> {
+        self.progress.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn pending_map(&self) -> std::sync::MutexGuard<'_, HashMap<u64, oneshot::Sender<Value>>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_message_kind() {
+        assert_eq!(
+            MessageKind::of(&json!({"jsonrpc": "2.0", "id": 1, "result": {}})),
+            MessageKind::Response
+        );
+        assert_eq!(
+            MessageKind::of(&json!({"jsonrpc": "2.0", "id": 1, "error": {"code": -1}})),
+            MessageKind::Response
+        );
+        assert_eq!(
+            MessageKind::of(&json!({"jsonrpc": "2.0", "id": 7, "method": "ping"})),
+            MessageKind::Request
+        );
+        assert_eq!(
+            MessageKind::of(&json!({"jsonrpc": "2.0", "method": "notifications/progress"})),
+            MessageKind::Notification
+        );
+        assert_eq!(MessageKind::of(&json!({"id": 1})), MessageKind::Invalid);
+    }
+
+    #[tokio::test]
+    async fn test_responses_are_correlated_by_id() {
+        let dispatcher = Dispatcher::new();
+        let (first_id, first_rx) = dispatcher.register();
+        let (second_id, second_rx) = dispatcher.register();
+        assert_ne!(first_id, second_id);
+
+        // Responses may arrive out of order
+        dispatcher.complete(json!({"id": second_id, "result": "second"}));
+        dispatcher.complete(json!({"id": first_id, "result": "first"}));
+
+        assert_eq!(first_rx.await.unwrap()["result"], "first");
+        assert_eq!(second_rx.await.unwrap()["result"], "second");
+        assert_eq!(dispatcher.pending_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_forget_and_close() {
+        let dispatcher = Dispatcher::new();
+        let (id, _rx) = dispatcher.register();
+        assert!(dispatcher.forget(id));
+        assert!(!dispatcher.forget(id));
+
+        // A late response for a forgotten request is discarded
+        dispatcher.complete(json!({"id": id, "result": {}}));
+
+        let (_, rx) = dispatcher.register();
+        dispatcher.close();
+        assert!(rx.await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_progress_is_routed_to_its_watcher() {
+        let (notification_tx, mut notification_rx) = mpsc::channel(4);
+        let (request_tx, _request_rx) = mpsc::channel(4);
+        let (stray_tx, _stray_rx) = mpsc::channel(4);
+        let routes = Routes {
+            notifications: notification_tx,
+            requests: request_tx,
+            stray: stray_tx,
+        };
+
+        let dispatcher = Dispatcher::new();
+        let (token, mut progress_rx) = dispatcher.watch_progress();
+        let (other, _other_rx) = dispatcher.watch_progress();
+        assert_ne!(token, other);
+        dispatcher.unwatch_progress(&other);
+        let progress = |token: &str| {
+            json!({"jsonrpc": "2.0", "method": "notifications/progress",
+                   "params": {"progressToken": token, "progress": 1}})
+        };
+
+        dispatcher
+            .route_data(&progress(&token).to_string(), &routes)
+            .await;
+        dispatcher
+            .route_data(&progress(&other).to_string(), &routes)
+            .await;
+
+        assert_eq!(
+            progress_rx.try_recv().unwrap()["params"]["progressToken"],
+            token.as_str()
+        );
+        // Unwatched tokens fall through to the general notification route
+        assert_eq!(
+            notification_rx.recv().await.unwrap()["params"]["progressToken"],
+            other.as_str()
+        );
+
+        dispatcher.unwatch_progress(&token);
+        assert!(progress_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_route_data_handles_batches() {
+        let (notification_tx, mut notification_rx) = mpsc::channel(4);
+        let (request_tx, mut request_rx) = mpsc::channel(4);
+        let (stray_tx, mut stray_rx) = mpsc::channel(4);
+        let routes = Routes {
+            notifications: notification_tx,
+            requests: request_tx,
+            stray: stray_tx,
+        };
+
+        let dispatcher = Dispatcher::new();
+        let (id, rx) = dispatcher.register();
+        let batch = json!([
+            {"jsonrpc": "2.0", "method": "notifications/progress", "params": {}},
+            {"jsonrpc": "2.0", "id": id, "result": {}},
+            {"jsonrpc": "2.0", "id": "s-1", "method": "roots/list"}
+        ]);
+        dispatcher.route_data(&batch.to_string(), &routes).await;
+        dispatcher.route_data("not json", &routes).await;
+
+        assert_eq!(rx.await.unwrap()["id"], id);
+        assert_eq!(
+            notification_rx.recv().await.unwrap()["method"],
+            "notifications/progress"
+        );
+        assert_eq!(request_rx.recv().await.unwrap()["method"], "roots/list");
+        assert_eq!(stray_rx.recv().await.unwrap(), "not json");
+    }
+}
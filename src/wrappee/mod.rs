@@ -1,268 +1,358 @@
+mod dispatcher;
+mod transport;
+
+pub use dispatcher::{Dispatcher, MessageKind, Routes};
+pub use transport::{
+    Recording, ReplayTransport, ShutdownOutcome, ShutdownStage, SseTransport, StdioTransport,
+    StreamableHttpTransport, WrappeeExit, WrappeeTarget, WrappeeTransport,
+};
+
 use crate::config::WrappeeConfig;
+use crate::logging::{Direction, WireTap};
 use anyhow::{Context, Result};
+use rmcp::model::{NumberOrString, ProgressToken};
 use serde_json::{Value, json};
-use std::io::{BufRead, BufReader, Write};
-use std::process::{Child, Command, Stdio};
-use std::sync::Arc;
-use tokio::sync::{Mutex, mpsc};
-use tokio::task;
-use tokio::time::{Duration, timeout};
+use std::sync::Mutex as StdMutex;
+use tokio::sync::mpsc;
+use tokio::time::{Duration, Instant, timeout};
+use tokio_util::sync::CancellationToken;
+
+/// How long to wait for the wrappee to take a `notifications/cancelled` message
+const CANCEL_SEND_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Error returned when the client cancelled a request before the wrappee answered
+#[derive(Debug, thiserror::Error)]
+#[error("Request cancelled by client")]
+pub struct RequestCancelled;
+
+/// Receivers for traffic the wrappee sends on its own initiative
+#[derive(Debug)]
+pub struct WrappeeEvents {
+    pub stderr: mpsc::Receiver<String>,
+    pub notifications: mpsc::Receiver<Value>,
+    pub requests: mpsc::Receiver<Value>,
+}
 
 #[derive(Debug)]
 pub struct WrappeeClient {
-    child: Arc<Mutex<Child>>,
-    stdin: Arc<Mutex<std::process::ChildStdin>>,
-    stdout_rx: mpsc::Receiver<String>,
-    stderr_rx: mpsc::Receiver<String>,
+    transport: Box<dyn WrappeeTransport>,
+    dispatcher: Dispatcher,
+    events: StdMutex<Option<WrappeeEvents>>,
+    /// Timeout for requests other than tool calls, the handshake and tool discovery
     timeout_duration: Duration,
+    startup_timeout: Duration,
+    discovery_timeout: Duration,
+    started_at: Instant,
+    /// Captures every message sent to the wrappee, if raw logging is enabled
+    tap: Option<WireTap>,
 }
 
 impl WrappeeClient {
-    pub fn spawn(
-        command: &str,
-        args: &[String],
-        disable_colors: bool,
+    /// Spawn or connect to the wrappee described by the target, recording the
+    /// messages exchanged with it on the tap if one is given
+    pub async fn connect(
+        target: &WrappeeTarget,
         config: WrappeeConfig,
+        tap: Option<WireTap>,
     ) -> Result<Self> {
-        tracing::info!("Spawning wrappee process: {command} {args:?}");
-
-        let mut cmd = Command::new(command);
-        cmd.args(args)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-
-        // Set environment variables to disable colors if requested
-        if disable_colors {
-            cmd.env("NO_COLOR", "1")
-                .env("CLICOLOR", "0")
-                .env("RUST_LOG_STYLE", "never");
-            tracing::debug!("Setting NO_COLOR=1, CLICOLOR=0, RUST_LOG_STYLE=never for wrappee");
-        }
-
-        let mut child = cmd.spawn().context("Failed to spawn wrappee process")?;
-
-        let stdin = child.stdin.take().context("Failed to get stdin")?;
-        let stdout = child.stdout.take().context("Failed to get stdout")?;
-        let stderr = child.stderr.take().context("Failed to get stderr")?;
-
-        let (stdout_tx, stdout_rx) = mpsc::channel(100);
         let (stderr_tx, stderr_rx) = mpsc::channel(100);
-
-        // Spawn stdout reader
-        task::spawn_blocking(move || {
-            let reader = BufReader::new(stdout);
-            tracing::debug!("Starting stdout reader");
-            for line in reader.lines() {
-                match line {
-                    Ok(line) => {
-                        tracing::debug!("Read line from wrappee stdout: {line}");
-                        if stdout_tx.blocking_send(line).is_err() {
-                            tracing::error!("Failed to send line to channel");
-                            break;
-                        }
-                    }
-                    Err(e) => {
-                        tracing::error!("Error reading stdout: {e}");
-                        break;
-                    }
-                }
-            }
-            tracing::debug!("Stdout reader finished");
-        });
-
-        // Spawn stderr reader
-        task::spawn_blocking(move || {
-            let reader = BufReader::new(stderr);
-            tracing::debug!("Starting stderr reader");
-            for line in reader.lines() {
-                match line {
-                    Ok(line) => {
-                        tracing::info!("Wrappee stderr: {line}");
-                        if stderr_tx.blocking_send(line).is_err() {
-                            tracing::error!("Failed to send stderr line to channel");
-                            break;
-                        }
-                    }
-                    Err(e) => {
-                        tracing::error!("Error reading stderr: {e}");
-                        break;
-                    }
-                }
-            }
-            tracing::debug!("Stderr reader finished");
-        });
-
-        // Get timeout from config
+        let (notification_tx, notification_rx) = mpsc::channel(100);
+        let (request_tx, request_rx) = mpsc::channel(100);
+
+        let dispatcher = Dispatcher::with_tap(tap.clone());
+        let routes = Routes {
+            notifications: notification_tx,
+            requests: request_tx,
+            // Stray output ends up in the log alongside stderr
+            stray: stderr_tx,
+        };
+
+        // Get timeouts from config
         let timeout_secs = config.tool_timeout_secs;
+        let timeout_duration = Duration::from_secs(timeout_secs);
+        let startup_timeout = Duration::from_secs(config.startup_timeout_secs);
+
+        let transport: Box<dyn WrappeeTransport> =
+            match target {
+                WrappeeTarget::Process {
+                    command,
+                    args,
+                    env,
+                    cwd,
+                    disable_colors,
+                } => Box::new(StdioTransport::spawn(
+                    StdioTransport::command(command, args, env, cwd.as_deref(), *disable_colors),
+                    Duration::from_millis(config.shutdown_grace_ms),
+                    dispatcher.clone(),
+                    routes,
+                )?),
+                WrappeeTarget::StreamableHttp { url } => Box::new(
+                    StreamableHttpTransport::connect(url, dispatcher.clone(), routes)?,
+                ),
+                WrappeeTarget::Sse { url } => Box::new(
+                    SseTransport::connect(url, dispatcher.clone(), routes, startup_timeout).await?,
+                ),
+                WrappeeTarget::Replay { path, unmatched } => Box::new(ReplayTransport::open(
+                    path,
+                    *unmatched,
+                    dispatcher.clone(),
+                    routes,
+                )?),
+            };
 
         tracing::info!("Tool timeout set to {timeout_secs} seconds");
 
         Ok(Self {
-            child: Arc::new(Mutex::new(child)),
-            stdin: Arc::new(Mutex::new(stdin)),
-            stdout_rx,
-            stderr_rx,
-            timeout_duration: Duration::from_secs(timeout_secs),
+            transport,
+            dispatcher,
+            events: StdMutex::new(Some(WrappeeEvents {
+                stderr: stderr_rx,
+                notifications: notification_rx,
+                requests: request_rx,
+            })),
+            timeout_duration,
+            startup_timeout,
+            discovery_timeout: Duration::from_secs(config.discovery_timeout_secs),
+            started_at: Instant::now(),
+            tap,
         })
     }
 
-    pub async fn send_request(&mut self, request: Value) -> Result<()> {
-        let request_str = serde_json::to_string(&request)?;
-        tracing::debug!("Sending request to wrappee: {request_str}");
+    /// Send a single JSON-RPC message to the wrappee
+    pub async fn send_message(&self, message: &Value) -> Result<()> {
+        tracing::debug!("Sending message to wrappee: {message}");
+        if let Some(tap) = &self.tap {
+            tap.record(Direction::ProxyToWrappee, message.clone());
+        }
+        self.transport.send(message).await
+    }
 
-        let mut stdin = self.stdin.lock().await;
-        writeln!(stdin, "{request_str}")?;
-        stdin.flush()?;
+    /// Wait until the wrappee has gone away
+    pub async fn closed(&self) -> WrappeeExit {
+        self.transport.closed().await
+    }
 
-        Ok(())
+    /// How long ago the wrappee was started or connected to
+    pub fn uptime(&self) -> Duration {
+        self.started_at.elapsed()
     }
 
-    pub async fn receive_response(&mut self) -> Result<Option<Value>> {
-        if let Ok(line) = self.stdout_rx.try_recv() {
-            tracing::debug!("Received response from wrappee: {line}");
-            let response: Value = serde_json::from_str(&line)?;
-            return Ok(Some(response));
-        }
-        Ok(None)
+    /// Take the event receivers for monitoring (only the first caller gets them)
+    pub fn take_events(&self) -> Option<WrappeeEvents> {
+        self.events.lock().unwrap_or_else(|e| e.into_inner()).take()
     }
 
-    pub async fn receive_stderr(&mut self) -> Result<Option<String>> {
-        // Try to receive stderr without blocking
-        match self.stderr_rx.try_recv() {
-            Ok(line) => {
-                tracing::debug!("Received stderr from wrappee: {line}");
-                Ok(Some(line))
-            }
-            Err(mpsc::error::TryRecvError::Empty) => Ok(None),
-            Err(mpsc::error::TryRecvError::Disconnected) => {
-                tracing::debug!("Stderr channel disconnected");
-                Ok(None)
-            }
-        }
+    /// Allocate a progress token to pass to the wrappee, and receive the wrappee's
+    /// progress notifications for it until it is unwatched
+    pub fn watch_progress(&self) -> (ProgressToken, mpsc::UnboundedReceiver<Value>) {
+        let (token, updates) = self.dispatcher.watch_progress();
+        (ProgressToken(NumberOrString::String(token.into())), updates)
     }
 
-    /// Receive stderr with async wait - used for monitoring
-    pub async fn receive_stderr_async(&mut self) -> Result<Option<String>> {
-        // Use async recv for monitoring where we want to wait
-        match self.stderr_rx.recv().await {
-            Some(line) => {
-                tracing::debug!("Received stderr from wrappee: {line}");
-                Ok(Some(line))
-            }
-            None => {
-                tracing::debug!("Stderr channel closed");
-                Ok(None)
-            }
+    /// Stop receiving progress notifications for a token
+    pub fn unwatch_progress(&self, token: &ProgressToken) {
+        self.dispatcher.unwatch_progress(&token.0.to_string());
+    }
+
+    /// Number of requests currently awaiting a response from the wrappee
+    pub fn in_flight(&self) -> usize {
+        self.dispatcher.pending_count()
+    }
+
+    pub async fn initialize(&self, protocol_version: &str, capabilities: Value) -> Result<Value> {
+        tracing::info!("Initializing wrappee with protocol version: {protocol_version}",);
+        tracing::debug!("Declaring client capabilities to wrappee: {capabilities}");
+
+        let response = self
+            .request_with_timeout(
+                "initialize",
+                json!({
+                    "protocolVersion": protocol_version,
+                    "capabilities": capabilities,
+                    "clientInfo": {
+                        "name": "wrap-mcp",
+                        "version": env!("CARGO_PKG_VERSION")
+                    }
+                }),
+                self.startup_timeout,
+                None,
+            )
+            .await?;
+
+        // Send initialized notification, unless the wrappee refused the handshake
+        if response.get("result").is_some() {
+            self.notify("notifications/initialized", None).await?;
         }
+
+        Ok(response)
     }
 
-    /// Get a clone of the stderr receiver for external monitoring
-    pub fn take_stderr_receiver(&mut self) -> Option<mpsc::Receiver<String>> {
-        // This will only work once - the receiver can't be cloned
-        // So we need to restructure this differently
-        None // Placeholder for now
+    /// Send a request with a fresh id and wait for its response using the default timeout
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        self.request_with_timeout(method, params, self.timeout_duration, None)
+            .await
     }
 
-    pub async fn wait_for_response(&mut self) -> Result<Value> {
-        let timeout_duration = self.timeout_duration.as_secs();
-        tracing::debug!(
-            "Waiting for response from wrappee (timeout: {timeout_duration} seconds)...",
-        );
+    /// Send a request with a fresh id and wait for the response carrying the same id.
+    /// If the token is cancelled or the timeout expires, the wrappee is told to stop
+    /// via `notifications/cancelled` and its eventual late response is discarded.
+    pub async fn request_with_timeout(
+        &self,
+        method: &str,
+        params: Value,
+        timeout_duration: Duration,
+        cancellation: Option<&CancellationToken>,
+    ) -> Result<Value> {
+        let (id, response_rx) = self.dispatcher.register();
+
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params
+        });
+
+        let timeout_secs = timeout_duration.as_secs();
 
-        match timeout(self.timeout_duration, self.stdout_rx.recv()).await {
-            Ok(Some(line)) => {
-                tracing::debug!("Received response from wrappee: {line}");
-                let response: Value = serde_json::from_str(&line)?;
-                Ok(response)
+        // Sending counts against the timeout too, since a remote wrappee may never
+        // answer the request that carries the message
+        let exchange = async {
+            if let Err(e) = self.send_message(&request).await {
+                self.dispatcher.forget(id);
+                return Err(e);
             }
-            Ok(None) => {
-                tracing::error!("Channel closed - no more messages available");
-                anyhow::bail!("Wrappee stdout closed unexpectedly")
+
+            tracing::debug!(
+                "Waiting for response to {method} #{id} from wrappee (timeout: {timeout_secs} seconds)...",
+            );
+            match response_rx.await {
+                Ok(response) => {
+                    tracing::debug!("Received response to #{id} from wrappee: {response}");
+                    Ok(response)
+                }
+                Err(_) => {
+                    tracing::error!("Wrappee stdout closed while waiting for #{id}");
+                    anyhow::bail!("Wrappee stdout closed unexpectedly")
+                }
             }
-            Err(_) => {
-                tracing::error!("Tool call timed out after {timeout_duration} seconds",);
-                anyhow::bail!("Tool call timed out after {timeout_duration} seconds",)
+        };
+
+        let cancelled = async {
+            match cancellation {
+                Some(token) => token.cancelled().await,
+                None => std::future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            result = timeout(timeout_duration, exchange) => match result {
+                Ok(outcome) => outcome,
+                Err(_) => {
+                    tracing::error!("{method} #{id} timed out after {timeout_secs} seconds");
+                    let reason = format!("Timed out after {timeout_secs} seconds");
+                    self.cancel_request(id, &reason).await;
+                    anyhow::bail!("Request {method} timed out after {timeout_secs} seconds")
+                }
+            },
+            _ = cancelled => {
+                tracing::info!("{method} #{id} cancelled by client");
+                self.cancel_request(id, "Cancelled by client").await;
+                Err(RequestCancelled.into())
             }
         }
     }
 
-    pub async fn initialize(&mut self, protocol_version: &str) -> Result<Value> {
-        // Use provided protocol version
-
-        tracing::info!("Initializing wrappee with protocol version: {protocol_version}",);
+    /// Stop waiting for a request and tell the wrappee to abandon it
+    async fn cancel_request(&self, id: u64, reason: &str) {
+        // If the slot is already gone the response arrived first; nothing to cancel
+        if !self.dispatcher.forget(id) {
+            return;
+        }
 
-        let init_request = json!({
-            "jsonrpc": "2.0",
-            "id": 1,
-            "method": "initialize",
-            "params": {
-                "protocolVersion": protocol_version,
-                "capabilities": {},
-                "clientInfo": {
-                    "name": "wrap-mcp",
-                    "version": env!("CARGO_PKG_VERSION")
-                }
-            }
+        let params = json!({
+            "requestId": id,
+            "reason": reason
         });
+        // A wrappee that left the request unanswered may not take this message either
+        match timeout(
+            CANCEL_SEND_TIMEOUT,
+            self.notify("notifications/cancelled", Some(params)),
+        )
+        .await
+        {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => tracing::warn!("Failed to send cancellation for #{id} to wrappee: {e}"),
+            Err(_) => tracing::warn!("Timed out sending cancellation for #{id} to wrappee"),
+        }
+    }
 
-        self.send_request(init_request).await?;
-        let response = self.wait_for_response().await?;
-
-        // Send initialized notification
-        let initialized_notification = json!({
+    /// Send a notification to the wrappee
+    pub async fn notify(&self, method: &str, params: Option<Value>) -> Result<()> {
+        let mut notification = json!({
             "jsonrpc": "2.0",
-            "method": "notifications/initialized"
+            "method": method
         });
-        self.send_request(initialized_notification).await?;
+        if let Some(params) = params {
+            notification["params"] = params;
+        }
+        self.send_message(&notification).await
+    }
 
-        Ok(response)
+    /// Answer a request that the wrappee sent to us
+    pub async fn respond(&self, id: Value, outcome: Result<Value, Value>) -> Result<()> {
+        let response = match outcome {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            Err(error) => json!({"jsonrpc": "2.0", "id": id, "error": error}),
+        };
+        self.send_message(&response).await
     }
 
-    pub async fn list_tools(&mut self) -> Result<Value> {
-        let request = json!({
-            "jsonrpc": "2.0",
-            "id": 2,
-            "method": "tools/list",
-            "params": {}
-        });
+    /// Ping the wrappee and return how long it took to answer
+    pub async fn ping(&self, timeout_duration: Duration) -> Result<Duration> {
+        let started = Instant::now();
+        self.request_with_timeout("ping", json!({}), timeout_duration, None)
+            .await?;
+        Ok(started.elapsed())
+    }
 
-        self.send_request(request).await?;
-        self.wait_for_response().await
+    pub async fn list_tools(&self) -> Result<Value> {
+        self.request_with_timeout("tools/list", json!({}), self.discovery_timeout, None)
+            .await
     }
 
-    pub async fn call_tool(&mut self, name: &str, arguments: Value) -> Result<Value> {
+    /// Call a tool, passing a progress token from `watch_progress` in `_meta`
+    pub async fn call_tool(
+        &self,
+        name: &str,
+        arguments: Value,
+        progress_token: Option<&ProgressToken>,
+        timeout_duration: Duration,
+        cancellation: &CancellationToken,
+    ) -> Result<Value> {
         tracing::info!(
-            "Calling tool '{name}' with timeout {timeout_duration} seconds",
-            timeout_duration = self.timeout_duration.as_secs()
+            "Calling tool '{name}' with timeout {} seconds",
+            timeout_duration.as_secs()
         );
 
-        let request = json!({
-            "jsonrpc": "2.0",
-            "id": 3,
-            "method": "tools/call",
-            "params": {
-                "name": name,
-                "arguments": arguments
-            }
+        let mut params = json!({
+            "name": name,
+            "arguments": arguments
         });
+        if let Some(token) = progress_token {
+            params["_meta"] = json!({ "progressToken": token });
+        }
 
-        self.send_request(request).await?;
-
-        self.wait_for_response()
+        self.request_with_timeout("tools/call", params, timeout_duration, Some(cancellation))
             .await
             .with_context(|| format!("Tool '{name}' execution failed"))
     }
 
     pub async fn get_pid(&self) -> Option<u32> {
-        let child = self.child.lock().await;
-        Some(child.id())
+        self.transport.pid()
     }
 
-    pub async fn shutdown(self) -> Result<()> {
-        let mut child = self.child.lock().await;
-        child.kill()?;
-        child.wait()?;
-        Ok(())
+    /// Stop the wrappee, escalating from closing stdin to SIGTERM to SIGKILL
+    pub async fn shutdown(&self) -> Result<ShutdownOutcome> {
+        self.transport.shutdown().await
     }
 }
@@ -0,0 +1,426 @@
+use super::{ShutdownOutcome, ShutdownStage, WrappeeExit, WrappeeTransport};
+use crate::wrappee::{Dispatcher, Routes};
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use futures::future::BoxFuture;
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{ACCEPT, CONTENT_TYPE};
+use hyper::{Method, Request, Response, StatusCode, Uri};
+use hyper_util::client::legacy::{Client, connect::HttpConnector};
+use hyper_util::rt::TokioExecutor;
+use serde_json::Value;
+use sse_stream::SseStream;
+use std::sync::{Arc, Mutex as StdMutex};
+use tokio_util::sync::CancellationToken;
+
+const SESSION_HEADER: &str = "mcp-session-id";
+
+pub(super) type HttpClient = Client<HttpConnector, Full<Bytes>>;
+
+pub(super) fn http_client() -> HttpClient {
+    Client::builder(TokioExecutor::new()).build_http()
+}
+
+/// Parse a wrappee URL, rejecting schemes the plain HTTP client cannot speak
+pub(super) fn parse_url(url: &str) -> Result<Uri> {
+    let uri: Uri = url
+        .parse()
+        .with_context(|| format!("Invalid wrappee URL: {url}"))?;
+    match uri.scheme_str() {
+        Some("http") => Ok(uri),
+        _ => anyhow::bail!("Unsupported wrappee URL {url}: only http:// is supported"),
+    }
+}
+
+/// Turn a non-success HTTP response into an error carrying its body
+pub(super) async fn error_for_status(response: Response<Incoming>) -> anyhow::Error {
+    let status = response.status();
+    let body = match response.into_body().collect().await {
+        Ok(body) => String::from_utf8_lossy(&body.to_bytes()).into_owned(),
+        Err(_) => String::new(),
+    };
+    anyhow::anyhow!("Wrappee returned HTTP {status}: {body}")
+}
+
+/// Check whether a response carries an SSE stream
+pub(super) fn is_event_stream(response: &Response<Incoming>) -> bool {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"))
+}
+
+/// Feed every JSON-RPC message of an SSE stream into the dispatcher until it ends,
+/// failing if the stream broke off
+pub(super) async fn pump_events(
+    mut events: SseStream<Incoming>,
+    dispatcher: Dispatcher,
+    routes: Routes,
+    shutdown: CancellationToken,
+) -> Result<()> {
+    loop {
+        let event = tokio::select! {
+            event = events.next() => event,
+            _ = shutdown.cancelled() => break,
+        };
+
+        match event {
+            Some(Ok(sse)) => match (sse.event.as_deref(), sse.data) {
+                (None | Some("message"), Some(data)) => {
+                    tracing::debug!("Read event from wrappee: {data}");
+                    dispatcher.route_data(&data, &routes).await;
+                }
+                (event, _) => tracing::debug!("Ignoring wrappee SSE event {event:?}"),
+            },
+            Some(Err(e)) => {
+                return Err(anyhow::Error::new(e).context("Error reading wrappee event stream"));
+            }
+            None => break,
+        }
+    }
+    tracing::debug!("Wrappee event stream finished");
+    Ok(())
+}
+
+/// Give up on a connection that failed, failing its pending requests and
+/// resolving `closed` so the wrappee is treated as gone
+fn connection_lost(shutdown: &CancellationToken, dispatcher: &Dispatcher, error: &anyhow::Error) {
+    if shutdown.is_cancelled() {
+        return;
+    }
+    tracing::warn!("Lost connection to wrappee: {error:#}");
+    shutdown.cancel();
+    dispatcher.close();
+}
+
+/// Wrappee reachable over the Streamable HTTP transport
+#[derive(Debug)]
+pub struct StreamableHttpTransport {
+    client: HttpClient,
+    url: Uri,
+    session_id: Arc<StdMutex<Option<String>>>,
+    dispatcher: Dispatcher,
+    routes: Routes,
+    /// Cancelled on shutdown or once the connection is lost
+    shutdown: CancellationToken,
+}
+
+impl StreamableHttpTransport {
+    pub fn connect(url: &str, dispatcher: Dispatcher, routes: Routes) -> Result<Self> {
+        tracing::info!("Connecting to wrappee over streamable HTTP: {url}");
+
+        Ok(Self {
+            client: http_client(),
+            url: parse_url(url)?,
+            session_id: Arc::new(StdMutex::new(None)),
+            dispatcher,
+            routes,
+            shutdown: CancellationToken::new(),
+        })
+    }
+
+    fn session_id(&self) -> Option<String> {
+        self.session_id
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn request(&self, method: Method) -> hyper::http::request::Builder {
+        let mut request = Request::builder().method(method).uri(self.url.clone());
+        if let Some(session_id) = self.session_id() {
+            request = request.header(SESSION_HEADER, session_id);
+        }
+        request
+    }
+
+    /// Open the GET stream the server uses for messages outside of any request
+    fn open_event_stream(&self) {
+        let request = match self
+            .request(Method::GET)
+            .header(ACCEPT, "text/event-stream")
+            .body(Full::default())
+        {
+            Ok(request) => request,
+            Err(e) => return tracing::warn!("Failed to build event stream request: {e}"),
+        };
+
+        let client = self.client.clone();
+        let dispatcher = self.dispatcher.clone();
+        let routes = self.routes.clone();
+        let shutdown = self.shutdown.clone();
+        tokio::spawn(async move {
+            match client.request(request).await {
+                Ok(response) if response.status() == StatusCode::METHOD_NOT_ALLOWED => {
+                    tracing::debug!("Wrappee offers no standalone event stream");
+                }
+                Ok(response) if response.status().is_success() && is_event_stream(&response) => {
+                    let events = SseStream::new(response.into_body());
+                    // The server may end the stream, but not break it off
+                    if let Err(e) =
+                        pump_events(events, dispatcher.clone(), routes, shutdown.clone()).await
+                    {
+                        connection_lost(&shutdown, &dispatcher, &e);
+                    }
+                }
+                Ok(response) => {
+                    let e = error_for_status(response).await;
+                    tracing::warn!("Failed to open wrappee event stream: {e}");
+                }
+                Err(e) => {
+                    let e = anyhow::Error::new(e).context("Failed to open wrappee event stream");
+                    connection_lost(&shutdown, &dispatcher, &e);
+                }
+            }
+        });
+    }
+}
+
+impl WrappeeTransport for StreamableHttpTransport {
+    fn send<'a>(&'a self, message: &'a Value) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let body = serde_json::to_vec(message)?;
+            let request = self
+                .request(Method::POST)
+                .header(ACCEPT, "application/json, text/event-stream")
+                .header(CONTENT_TYPE, "application/json")
+                .body(Full::new(Bytes::from(body)))?;
+
+            let response = match self.client.request(request).await {
+                Ok(response) => response,
+                Err(e) => {
+                    let e = anyhow::Error::new(e)
+                        .context(format!("Failed to reach wrappee at {}", self.url));
+                    connection_lost(&self.shutdown, &self.dispatcher, &e);
+                    return Err(e);
+                }
+            };
+
+            if let Some(session_id) = response
+                .headers()
+                .get(SESSION_HEADER)
+                .and_then(|v| v.to_str().ok())
+            {
+                *self.session_id.lock().unwrap_or_else(|e| e.into_inner()) =
+                    Some(session_id.to_string());
+            }
+
+            let status = response.status();
+            if status == StatusCode::NOT_FOUND && self.session_id().is_some() {
+                // The server no longer knows our session, so it has to be set up afresh
+                let e = error_for_status(response)
+                    .await
+                    .context("Wrappee session expired");
+                connection_lost(&self.shutdown, &self.dispatcher, &e);
+                return Err(e);
+            }
+            if !status.is_success() {
+                return Err(error_for_status(response).await);
+            }
+
+            if status == StatusCode::ACCEPTED {
+                // Notifications and responses are only acknowledged
+            } else if is_event_stream(&response) {
+                // The answer arrives on a stream that may stay open for a long time
+                let events = SseStream::new(response.into_body());
+                let pump = pump_events(
+                    events,
+                    self.dispatcher.clone(),
+                    self.routes.clone(),
+                    self.shutdown.clone(),
+                );
+                tokio::spawn(async move {
+                    if let Err(e) = pump.await {
+                        tracing::warn!("{e:#}");
+                    }
+                });
+            } else {
+                let body = response.into_body().collect().await?.to_bytes();
+                if !body.is_empty() {
+                    let data = String::from_utf8_lossy(&body);
+                    self.dispatcher.route_data(&data, &self.routes).await;
+                }
+            }
+
+            // Once the session is established the server may start pushing messages
+            if message.get("method").and_then(|m| m.as_str()) == Some("notifications/initialized") {
+                self.open_event_stream();
+            }
+
+            Ok(())
+        })
+    }
+
+    fn pid(&self) -> Option<u32> {
+        None
+    }
+
+    fn shutdown(&self) -> BoxFuture<'_, Result<ShutdownOutcome>> {
+        Box::pin(async move {
+            self.shutdown.cancel();
+
+            // Tell the server the session is over (servers may refuse with 405)
+            if self.session_id().is_some() {
+                let request = self.request(Method::DELETE).body(Full::default())?;
+                match self.client.request(request).await {
+                    Ok(response) => tracing::debug!(
+                        "Wrappee answered session termination with {}",
+                        response.status()
+                    ),
+                    Err(e) => tracing::warn!("Failed to terminate wrappee session: {e}"),
+                }
+            }
+            Ok(ShutdownOutcome {
+                stage: ShutdownStage::Disconnected,
+                exit: WrappeeExit::disconnected(),
+            })
+        })
+    }
+
+    fn closed(&self) -> BoxFuture<'_, WrappeeExit> {
+        // Requests are independent, so the connection only ends when we shut it down
+        // or a request, or the event stream, can no longer reach the server
+        Box::pin(async move {
+            self.shutdown.cancelled().await;
+            WrappeeExit::disconnected()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WrappeeConfig;
+    use crate::wrappee::{RequestCancelled, WrappeeClient, WrappeeExit, WrappeeTarget};
+    use serde_json::json;
+    use tokio::time::Duration;
+
+    /// Connect to a server that accepts connections but never answers them
+    async fn silent_wrappee() -> (WrappeeClient, tokio::task::JoinHandle<()>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                connections.push(socket);
+            }
+        });
+
+        let target = WrappeeTarget::StreamableHttp { url };
+        let client = WrappeeClient::connect(&target, WrappeeConfig::default(), None)
+            .await
+            .unwrap();
+        (client, server)
+    }
+
+    #[test]
+    fn test_parse_url() {
+        assert!(parse_url("http://127.0.0.1:3000/mcp").is_ok());
+        assert!(parse_url("https://example.com/mcp").is_err());
+        assert!(parse_url("not a url").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_request_times_out_when_server_never_answers() {
+        let (client, server) = silent_wrappee().await;
+
+        let outcome = tokio::time::timeout(
+            Duration::from_secs(5),
+            client.request_with_timeout("ping", json!({}), Duration::from_millis(200), None),
+        )
+        .await
+        .expect("request must not outlive its timeout");
+        assert!(outcome.unwrap_err().to_string().contains("timed out"));
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_request_cancelled_when_server_never_answers() {
+        let (client, server) = silent_wrappee().await;
+
+        let cancellation = CancellationToken::new();
+        let cancel = cancellation.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            cancel.cancel();
+        });
+
+        let outcome = tokio::time::timeout(
+            Duration::from_secs(5),
+            client.request_with_timeout(
+                "tools/call",
+                json!({}),
+                Duration::from_secs(60),
+                Some(&cancellation),
+            ),
+        )
+        .await
+        .expect("request must stop once cancelled");
+        assert!(outcome.unwrap_err().is::<RequestCancelled>());
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_closed_when_server_is_unreachable() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        drop(listener);
+
+        let target = WrappeeTarget::StreamableHttp { url };
+        let client = WrappeeClient::connect(&target, WrappeeConfig::default(), None)
+            .await
+            .unwrap();
+        assert!(
+            client
+                .notify("notifications/initialized", None)
+                .await
+                .is_err()
+        );
+
+        let exit = tokio::time::timeout(Duration::from_secs(5), client.closed())
+            .await
+            .expect("a failed send must close the connection");
+        assert_eq!(exit, WrappeeExit::disconnected());
+    }
+
+    #[tokio::test]
+    async fn test_closed_when_event_stream_breaks_off() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Acknowledge every POST; answer the GET with an event stream that is cut short
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = [0; 4096];
+                    let n = socket.read(&mut buf).await.unwrap_or(0);
+                    let reply = if buf[..n].starts_with(b"GET") {
+                        "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\n\
+                         transfer-encoding: chunked\r\n\r\n"
+                    } else {
+                        "HTTP/1.1 202 Accepted\r\ncontent-length: 0\r\n\r\n"
+                    };
+                    let _ = socket.write_all(reply.as_bytes()).await;
+                    let _ = socket.shutdown().await;
+                });
+            }
+        });
+
+        let target = WrappeeTarget::StreamableHttp { url };
+        let client = WrappeeClient::connect(&target, WrappeeConfig::default(), None)
+            .await
+            .unwrap();
+        client
+            .notify("notifications/initialized", None)
+            .await
+            .unwrap();
+
+        tokio::time::timeout(Duration::from_secs(5), client.closed())
+            .await
+            .expect("a broken event stream must close the connection");
+        server.abort();
+    }
+}
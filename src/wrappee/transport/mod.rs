@@ -0,0 +1,261 @@
+mod http;
+mod replay;
+mod sse;
+mod stdio;
+
+pub use http::StreamableHttpTransport;
+pub use replay::{Recording, ReplayTransport};
+pub use sse::SseTransport;
+pub use stdio::StdioTransport;
+
+use anyhow::Result;
+use futures::future::BoxFuture;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use tokio::sync::watch;
+
+use crate::config::UnmatchedCall;
+
+/// Carries JSON-RPC messages to the wrappee.
+///
+/// Messages coming back are fed into the client's `Dispatcher` by the transport itself,
+/// so everything above this trait works the same for every kind of wrappee.
+pub trait WrappeeTransport: Send + Sync + fmt::Debug {
+    /// Send a single JSON-RPC message to the wrappee
+    fn send<'a>(&'a self, message: &'a Value) -> BoxFuture<'a, Result<()>>;
+
+    /// PID of the wrappee if it runs as a local child process
+    fn pid(&self) -> Option<u32>;
+
+    /// Stop the wrappee process or disconnect from the remote wrappee
+    fn shutdown(&self) -> BoxFuture<'_, Result<ShutdownOutcome>>;
+
+    /// Resolve once the wrappee has gone away, whether on its own or through `shutdown`
+    fn closed(&self) -> BoxFuture<'_, WrappeeExit>;
+}
+
+/// How a wrappee went away
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WrappeeExit {
+    /// Exit code of the process, if it exited normally
+    pub code: Option<i32>,
+    /// Signal that terminated the process, if any
+    pub signal: Option<i32>,
+}
+
+impl WrappeeExit {
+    /// A remote wrappee that dropped the connection (neither code nor signal)
+    pub fn disconnected() -> Self {
+        Self::default()
+    }
+
+    /// Whether the wrappee exited cleanly with code 0
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+/// Step of the shutdown sequence that made the wrappee go away
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownStage {
+    /// The wrappee had already exited
+    AlreadyExited,
+    /// The wrappee exited after its stdin was closed
+    StdinClosed,
+    /// The wrappee exited after SIGTERM
+    Terminated,
+    /// The wrappee had to be killed
+    Killed,
+    /// The connection to a remote wrappee was closed
+    Disconnected,
+}
+
+impl fmt::Display for ShutdownStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShutdownStage::AlreadyExited => write!(f, "already exited"),
+            ShutdownStage::StdinClosed => write!(f, "stdin closed"),
+            ShutdownStage::Terminated => write!(f, "SIGTERM"),
+            ShutdownStage::Killed => write!(f, "SIGKILL"),
+            ShutdownStage::Disconnected => write!(f, "disconnected"),
+        }
+    }
+}
+
+/// How a shutdown went: which stage ended the wrappee and how it exited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShutdownOutcome {
+    pub stage: ShutdownStage,
+    pub exit: WrappeeExit,
+}
+
+impl fmt::Display for ShutdownOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.stage {
+            ShutdownStage::AlreadyExited | ShutdownStage::Disconnected => {
+                write!(f, "{}", self.stage)
+            }
+            _ => write!(f, "{} after {}", self.exit, self.stage),
+        }
+    }
+}
+
+impl From<std::process::ExitStatus> for WrappeeExit {
+    fn from(status: std::process::ExitStatus) -> Self {
+        #[cfg(unix)]
+        let signal = std::os::unix::process::ExitStatusExt::signal(&status);
+        #[cfg(not(unix))]
+        let signal = None;
+
+        Self {
+            code: status.code(),
+            signal,
+        }
+    }
+}
+
+impl fmt::Display for WrappeeExit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.code, self.signal) {
+            (Some(code), _) => write!(f, "exit code {code}"),
+            (None, Some(signal)) => write!(f, "killed by signal {signal}"),
+            (None, None) => write!(f, "disconnected"),
+        }
+    }
+}
+
+/// Where the wrappee lives and how to talk to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WrappeeTarget {
+    /// Child process speaking JSON-RPC over stdin/stdout
+    Process {
+        command: String,
+        args: Vec<String>,
+        /// Variables added to the environment inherited from Wrap-MCP
+        env: BTreeMap<String, String>,
+        /// Working directory, Wrap-MCP's own when not set
+        cwd: Option<PathBuf>,
+        disable_colors: bool,
+    },
+    /// Server reachable over Streamable HTTP
+    StreamableHttp { url: String },
+    /// Server reachable over the legacy HTTP+SSE transport
+    Sse { url: String },
+    /// Recorded session played back in place of a server
+    Replay {
+        path: PathBuf,
+        unmatched: UnmatchedCall,
+    },
+}
+
+impl WrappeeTarget {
+    /// Build a target from the command line: URLs are connected to, anything else is spawned
+    pub fn from_command(
+        command: String,
+        args: Vec<String>,
+        disable_colors: bool,
+        legacy_sse: bool,
+    ) -> Self {
+        if !is_url(&command) {
+            return WrappeeTarget::Process {
+                command,
+                args,
+                env: BTreeMap::new(),
+                cwd: None,
+                disable_colors,
+            };
+        }
+
+        if !args.is_empty() {
+            tracing::warn!("Ignoring arguments {args:?} for HTTP wrappee {command}");
+        }
+
+        if legacy_sse {
+            WrappeeTarget::Sse { url: command }
+        } else {
+            WrappeeTarget::StreamableHttp { url: command }
+        }
+    }
+
+    /// Path of the wrappee binary, if the wrappee is a local process
+    pub fn binary_path(&self) -> Option<&str> {
+        match self {
+            WrappeeTarget::Process { command, .. } => Some(command),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for WrappeeTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WrappeeTarget::Process { command, args, .. } if args.is_empty() => {
+                write!(f, "{command}")
+            }
+            WrappeeTarget::Process { command, args, .. } => {
+                write!(f, "{command} {}", args.join(" "))
+            }
+            WrappeeTarget::StreamableHttp { url } => write!(f, "{url} (streamable HTTP)"),
+            WrappeeTarget::Sse { url } => write!(f, "{url} (SSE)"),
+            WrappeeTarget::Replay { path, .. } => write!(f, "{} (replay)", path.display()),
+        }
+    }
+}
+
+/// Wait until an exit has been published on the channel
+async fn wait_for_exit(mut exited: watch::Receiver<Option<WrappeeExit>>) -> WrappeeExit {
+    match exited.wait_for(|exit| exit.is_some()).await {
+        Ok(exit) => exit.unwrap_or_default(),
+        // The watcher is gone without reporting, so the wrappee is gone too
+        Err(_) => WrappeeExit::default(),
+    }
+}
+
+/// Whether the command names a remote wrappee. https:// counts as one so that it is
+/// refused by config validation (and `parse_url`) rather than spawned as a program.
+fn is_url(command: &str) -> bool {
+    command.starts_with("http://") || command.starts_with("https://")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrappee_exit_display() {
+        let exit = WrappeeExit {
+            code: Some(0),
+            signal: None,
+        };
+        assert!(exit.success());
+        assert_eq!(exit.to_string(), "exit code 0");
+
+        let exit = WrappeeExit {
+            code: None,
+            signal: Some(9),
+        };
+        assert!(!exit.success());
+        assert_eq!(exit.to_string(), "killed by signal 9");
+
+        assert!(!WrappeeExit::disconnected().success());
+        assert_eq!(WrappeeExit::disconnected().to_string(), "disconnected");
+    }
+
+    #[test]
+    fn test_target_from_command() {
+        let target = WrappeeTarget::from_command("cat".into(), vec!["-n".into()], true, false);
+        assert_eq!(target.binary_path(), Some("cat"));
+
+        let url = "http://127.0.0.1:3000/mcp".to_string();
+        assert_eq!(
+            WrappeeTarget::from_command(url.clone(), vec![], true, false),
+            WrappeeTarget::StreamableHttp { url: url.clone() }
+        );
+        assert_eq!(
+            WrappeeTarget::from_command(url.clone(), vec![], true, true),
+            WrappeeTarget::Sse { url }
+        );
+    }
+}
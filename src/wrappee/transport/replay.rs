@@ -0,0 +1,460 @@
+use super::{ShutdownOutcome, ShutdownStage, WrappeeExit, WrappeeTransport};
+use crate::config::UnmatchedCall;
+use crate::logging::{self, Direction, LogEntry, LogEntryContent};
+use crate::wrappee::{Dispatcher, MessageKind, Routes};
+use anyhow::{Context, Result};
+use futures::future::BoxFuture;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex as StdMutex;
+use tokio_util::sync::CancellationToken;
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+/// What the wrappee answered: a result or a JSON-RPC error object
+type Outcome = Result<Value, Value>;
+
+/// Tool calls made with the same arguments, answered in the order they were recorded
+#[derive(Debug)]
+struct RecordedCall {
+    arguments: Value,
+    outcomes: VecDeque<Outcome>,
+}
+
+impl RecordedCall {
+    /// The next recorded answer; the last one is repeated once the others are used up
+    fn next(&mut self) -> Outcome {
+        if self.outcomes.len() > 1 {
+            self.outcomes.pop_front().expect("outcomes are not empty")
+        } else {
+            self.outcomes[0].clone()
+        }
+    }
+}
+
+/// The traffic of a recorded session, indexed for answering requests like the
+/// wrappee did
+#[derive(Debug, Default)]
+pub struct Recording {
+    /// Result of the wrappee's `initialize`, if raw frames were captured
+    initialize: Option<Value>,
+    /// Tools from the wrappee's last `tools/list`, if raw frames were captured
+    tools: Option<Vec<Value>>,
+    /// Answered tool calls by tool name
+    calls: BTreeMap<String, Vec<RecordedCall>>,
+}
+
+impl Recording {
+    /// Read a recording: the JSON array `show_log format=json` returns, JSON Lines
+    /// of log entries, or a directory of persisted log segments
+    pub fn load(path: &Path) -> Result<Self> {
+        let entries = if path.is_dir() {
+            logging::load_recent(path, usize::MAX)
+                .with_context(|| format!("Failed to read log segments in {}", path.display()))?
+        } else {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read recording {}", path.display()))?;
+            parse_entries(&text)
+                .with_context(|| format!("Failed to parse recording {}", path.display()))?
+        };
+        Ok(Self::from_entries(entries))
+    }
+
+    /// Pair the recorded requests with their responses, in any order of entries
+    pub fn from_entries(mut entries: Vec<LogEntry>) -> Self {
+        // `show_log` lists the newest entries first
+        entries.sort_by_key(|entry| (entry.timestamp, entry.id.inner()));
+
+        let mut recording = Self::default();
+        let mut pending = HashMap::new();
+        for entry in entries {
+            match entry.content {
+                // Methods the proxy logs alongside tool calls, like `resources/read`
+                LogEntryContent::Request { tool_name, .. } if tool_name.as_str().contains('/') => {}
+                LogEntryContent::Request { tool_name, content } => {
+                    pending.insert(entry.id, (tool_name.as_str().to_string(), content));
+                }
+                LogEntryContent::Response {
+                    request_id,
+                    response,
+                    ..
+                } => {
+                    if let Some((name, arguments)) = pending.remove(&request_id) {
+                        let outcome = match (response.get("result"), response.get("error")) {
+                            (Some(result), _) => Ok(result.clone()),
+                            (None, Some(error)) => Err(error.clone()),
+                            (None, None) => Ok(response),
+                        };
+                        recording.record_call(name, arguments, outcome);
+                    }
+                }
+                // Errors that came without a response, such as timeouts
+                LogEntryContent::Error {
+                    request_id, error, ..
+                } => {
+                    if let Some((name, arguments)) = pending.remove(&request_id) {
+                        let error = json!({"code": INTERNAL_ERROR, "message": error});
+                        recording.record_call(name, arguments, Err(error));
+                    }
+                }
+                LogEntryContent::Raw {
+                    direction: Direction::WrappeeToProxy,
+                    method: Some(method),
+                    message,
+                } => match (method.as_str(), message.get("result")) {
+                    ("initialize", Some(result)) => recording.initialize = Some(result.clone()),
+                    ("tools/list", Some(result)) => {
+                        recording.tools = result.get("tools").and_then(Value::as_array).cloned();
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        recording
+    }
+
+    fn record_call(&mut self, name: String, arguments: Value, outcome: Outcome) {
+        let arguments = normalize(arguments);
+        let calls = self.calls.entry(name).or_default();
+        match calls.iter_mut().find(|call| call.arguments == arguments) {
+            Some(call) => call.outcomes.push_back(outcome),
+            None => calls.push(RecordedCall {
+                arguments,
+                outcomes: VecDeque::from([outcome]),
+            }),
+        }
+    }
+
+    /// Number of tool calls that can be answered from the recording
+    pub fn call_count(&self) -> usize {
+        self.calls
+            .values()
+            .flatten()
+            .map(|call| call.outcomes.len())
+            .sum()
+    }
+
+    /// The recorded tool list, or one made up from the tools that were called
+    pub fn tools(&self) -> Vec<Value> {
+        match &self.tools {
+            Some(tools) => tools.clone(),
+            None => self
+                .calls
+                .keys()
+                .map(|name| {
+                    json!({
+                        "name": name,
+                        "description": "Replayed from a recording",
+                        "inputSchema": {"type": "object"}
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    /// Answer a tool call with the recorded response for the same tool and arguments
+    pub fn call(&mut self, name: &str, arguments: Value, unmatched: UnmatchedCall) -> Outcome {
+        let arguments = normalize(arguments);
+        let calls = self.calls.get_mut(name);
+        if let Some(call) = calls
+            .into_iter()
+            .flatten()
+            .find(|call| call.arguments == arguments)
+        {
+            return call.next();
+        }
+
+        tracing::warn!("No recorded response for tool '{name}' with arguments {arguments}");
+        let same_tool = match unmatched {
+            UnmatchedCall::SameTool => self.calls.get_mut(name).and_then(|calls| calls.first_mut()),
+            _ => None,
+        };
+        match (unmatched, same_tool) {
+            (_, Some(call)) => call.next(),
+            (UnmatchedCall::Empty, None) => Ok(json!({"content": []})),
+            _ => Err(json!({
+                "code": INVALID_PARAMS,
+                "message": format!(
+                    "No recorded response for tool '{name}' with arguments {arguments}"
+                )
+            })),
+        }
+    }
+}
+
+/// Parse the JSON array `show_log` returns, or one entry per line
+fn parse_entries(text: &str) -> Result<Vec<LogEntry>> {
+    if text.trim_start().starts_with('[') {
+        return Ok(serde_json::from_str(text)?);
+    }
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).with_context(|| format!("Invalid entry on line {}", i + 1))
+        })
+        .collect()
+}
+
+/// Calls without arguments are logged with `null` or `{}` depending on the client
+fn normalize(arguments: Value) -> Value {
+    match arguments {
+        Value::Null => json!({}),
+        arguments => arguments,
+    }
+}
+
+/// Plays a recorded session back as the wrappee, without running anything
+#[derive(Debug)]
+pub struct ReplayTransport {
+    path: PathBuf,
+    recording: StdMutex<Recording>,
+    unmatched: UnmatchedCall,
+    dispatcher: Dispatcher,
+    routes: Routes,
+    shutdown: CancellationToken,
+}
+
+impl ReplayTransport {
+    /// Load the recording that answers the requests sent to this transport
+    pub fn open(
+        path: &Path,
+        unmatched: UnmatchedCall,
+        dispatcher: Dispatcher,
+        routes: Routes,
+    ) -> Result<Self> {
+        let recording = Recording::load(path)?;
+        tracing::info!(
+            "Replaying {} tool calls to {} tools from {}",
+            recording.call_count(),
+            recording.tools().len(),
+            path.display()
+        );
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            recording: StdMutex::new(recording),
+            unmatched,
+            dispatcher,
+            routes,
+            shutdown: CancellationToken::new(),
+        })
+    }
+
+    fn recording(&self) -> std::sync::MutexGuard<'_, Recording> {
+        self.recording.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// What the recorded wrappee answers to a request
+    fn answer(&self, method: &str, params: Option<&Value>) -> Outcome {
+        match method {
+            "initialize" => {
+                let mut result = self.recording().initialize.clone().unwrap_or_else(|| {
+                    json!({
+                        "serverInfo": {
+                            "name": "wrap-mcp-replay",
+                            "version": env!("CARGO_PKG_VERSION")
+                        }
+                    })
+                });
+                // Speak whatever the proxy asks for, and offer nothing but the recorded tools
+                result["protocolVersion"] = params
+                    .and_then(|params| params.get("protocolVersion"))
+                    .cloned()
+                    .unwrap_or(Value::Null);
+                result["capabilities"] = json!({"tools": {}});
+                Ok(result)
+            }
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({"tools": self.recording().tools()})),
+            "tools/call" => {
+                let name = params
+                    .and_then(|params| params.get("name"))
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                let arguments = params
+                    .and_then(|params| params.get("arguments"))
+                    .cloned()
+                    .unwrap_or(Value::Null);
+                self.recording().call(name, arguments, self.unmatched)
+            }
+            _ => Err(json!({
+                "code": METHOD_NOT_FOUND,
+                "message": format!("Method not found in recording: {method}")
+            })),
+        }
+    }
+}
+
+impl WrappeeTransport for ReplayTransport {
+    fn send<'a>(&'a self, message: &'a Value) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            if self.shutdown.is_cancelled() {
+                anyhow::bail!("Replay of {} has been stopped", self.path.display());
+            }
+            // Notifications and answers to our own requests need no reply
+            if MessageKind::of(message) != MessageKind::Request {
+                return Ok(());
+            }
+
+            let method = message["method"].as_str().unwrap_or_default();
+            let response = match self.answer(method, message.get("params")) {
+                Ok(result) => json!({"jsonrpc": "2.0", "id": message["id"], "result": result}),
+                Err(error) => json!({"jsonrpc": "2.0", "id": message["id"], "error": error}),
+            };
+            self.dispatcher
+                .route_data(&response.to_string(), &self.routes)
+                .await;
+            Ok(())
+        })
+    }
+
+    fn pid(&self) -> Option<u32> {
+        None
+    }
+
+    fn shutdown(&self) -> BoxFuture<'_, Result<ShutdownOutcome>> {
+        Box::pin(async move {
+            self.shutdown.cancel();
+            Ok(ShutdownOutcome {
+                stage: ShutdownStage::Disconnected,
+                exit: WrappeeExit::disconnected(),
+            })
+        })
+    }
+
+    fn closed(&self) -> BoxFuture<'_, WrappeeExit> {
+        // A recording never goes away on its own
+        Box::pin(async move {
+            self.shutdown.cancelled().await;
+            WrappeeExit::disconnected()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{RequestId, ToolName};
+
+    fn session() -> Vec<LogEntry> {
+        let mut entries = Vec::new();
+        let mut call = |id: usize, arguments: Value, text: &str| {
+            entries.push(LogEntry::new_request(
+                RequestId::new(id),
+                ToolName::new("echo"),
+                arguments,
+            ));
+            entries.push(LogEntry::new_response(
+                RequestId::new(id + 1),
+                ToolName::new("echo"),
+                RequestId::new(id),
+                json!({"jsonrpc": "2.0", "id": 1, "result": {"content": [{"type": "text", "text": text}]}}),
+                None,
+            ));
+        };
+        call(1, json!({"text": "a"}), "first a");
+        call(3, json!({"text": "b"}), "b");
+        call(5, json!({"text": "a"}), "second a");
+        entries
+    }
+
+    fn text(outcome: Outcome) -> String {
+        outcome.unwrap()["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_calls_are_answered_in_recorded_order() {
+        let mut recording = Recording::from_entries(session());
+        assert_eq!(recording.call_count(), 3);
+        assert_eq!(recording.tools()[0]["name"], "echo");
+
+        let a = json!({"text": "a"});
+        assert_eq!(
+            text(recording.call("echo", a.clone(), UnmatchedCall::Error)),
+            "first a"
+        );
+        assert_eq!(
+            text(recording.call("echo", a.clone(), UnmatchedCall::Error)),
+            "second a"
+        );
+        // The last answer is repeated
+        assert_eq!(
+            text(recording.call("echo", a, UnmatchedCall::Error)),
+            "second a"
+        );
+        assert_eq!(
+            text(recording.call("echo", json!({"text": "b"}), UnmatchedCall::Error)),
+            "b"
+        );
+    }
+
+    #[test]
+    fn test_unmatched_calls() {
+        let mut recording = Recording::from_entries(session());
+        let other = json!({"text": "c"});
+
+        let error = recording
+            .call("echo", other.clone(), UnmatchedCall::Error)
+            .unwrap_err();
+        assert_eq!(error["code"], INVALID_PARAMS);
+        assert_eq!(
+            text(recording.call("echo", other.clone(), UnmatchedCall::SameTool)),
+            "first a"
+        );
+        assert_eq!(
+            recording.call("echo", other, UnmatchedCall::Empty).unwrap(),
+            json!({"content": []})
+        );
+        assert!(
+            recording
+                .call("missing", json!({}), UnmatchedCall::SameTool)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_show_log_output() {
+        // show_log lists the newest entries first
+        let mut entries = session();
+        entries.reverse();
+        let json = serde_json::to_string_pretty(&entries).unwrap();
+
+        let mut recording = Recording::from_entries(parse_entries(&json).unwrap());
+        let outcome = recording.call("echo", json!({"text": "a"}), UnmatchedCall::Error);
+        assert_eq!(text(outcome), "first a");
+
+        let lines: Vec<String> = session()
+            .iter()
+            .map(|entry| serde_json::to_string(entry).unwrap())
+            .collect();
+        let recording = Recording::from_entries(parse_entries(&lines.join("\n")).unwrap());
+        assert_eq!(recording.call_count(), 3);
+    }
+
+    #[test]
+    fn test_raw_frames_provide_the_tool_list() {
+        let mut entries = session();
+        entries.push(LogEntry::new_raw(
+            RequestId::new(7),
+            Direction::WrappeeToProxy,
+            Some("tools/list".to_string()),
+            json!({"jsonrpc": "2.0", "id": 2, "result": {"tools": [
+                {"name": "echo", "inputSchema": {"type": "object", "required": ["text"]}},
+                {"name": "never_called", "inputSchema": {"type": "object"}}
+            ]}}),
+        ));
+
+        let recording = Recording::from_entries(entries);
+        let tools = recording.tools();
+        assert_eq!(tools.len(), 2);
+        assert_eq!(tools[1]["name"], "never_called");
+    }
+}
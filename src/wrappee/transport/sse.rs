@@ -0,0 +1,184 @@
+use super::http::{
+    HttpClient, error_for_status, http_client, is_event_stream, parse_url, pump_events,
+};
+use super::{ShutdownOutcome, ShutdownStage, WrappeeExit, WrappeeTransport, wait_for_exit};
+use crate::wrappee::{Dispatcher, Routes};
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use futures::future::BoxFuture;
+use http_body_util::Full;
+use hyper::body::Bytes;
+use hyper::header::{ACCEPT, CONTENT_TYPE};
+use hyper::{Method, Request, Uri};
+use serde_json::Value;
+use sse_stream::SseStream;
+use tokio::sync::watch;
+use tokio::time::{Duration, timeout};
+use tokio_util::sync::CancellationToken;
+
+/// Wrappee reachable over the legacy HTTP+SSE transport: messages arrive on a
+/// long-lived event stream and are sent by POSTing to the endpoint it announces
+#[derive(Debug)]
+pub struct SseTransport {
+    client: HttpClient,
+    endpoint: Uri,
+    shutdown: CancellationToken,
+    exited: watch::Receiver<Option<WrappeeExit>>,
+}
+
+impl SseTransport {
+    pub async fn connect(
+        url: &str,
+        dispatcher: Dispatcher,
+        routes: Routes,
+        connect_timeout: Duration,
+    ) -> Result<Self> {
+        tracing::info!("Connecting to wrappee over SSE: {url}");
+
+        let client = http_client();
+        let uri = parse_url(url)?;
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(uri.clone())
+            .header(ACCEPT, "text/event-stream")
+            .body(Full::default())?;
+
+        let response = client
+            .request(request)
+            .await
+            .with_context(|| format!("Failed to reach wrappee at {url}"))?;
+        if !response.status().is_success() {
+            return Err(error_for_status(response).await);
+        }
+        if !is_event_stream(&response) {
+            anyhow::bail!("Wrappee at {url} did not answer with an event stream");
+        }
+
+        // The first event tells us where to post messages
+        let mut events = SseStream::new(response.into_body());
+        let endpoint = timeout(connect_timeout, async {
+            while let Some(event) = events.next().await {
+                let sse = event.context("Failed to read wrappee event stream")?;
+                if sse.event.as_deref() == Some("endpoint") {
+                    let path = sse.data.unwrap_or_default();
+                    return resolve_endpoint(&uri, path.trim());
+                }
+            }
+            anyhow::bail!("Wrappee event stream closed before announcing its endpoint")
+        })
+        .await
+        .context("Timed out waiting for the wrappee to announce its endpoint")??;
+
+        tracing::info!("Wrappee message endpoint: {endpoint}");
+
+        // The wrappee is gone once its event stream ends
+        let shutdown = CancellationToken::new();
+        let (exited_tx, exited) = watch::channel(None);
+        let pump_shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            if let Err(e) = pump_events(events, dispatcher.clone(), routes, pump_shutdown).await {
+                tracing::warn!("{e:#}");
+            }
+            dispatcher.close();
+            exited_tx.send_replace(Some(WrappeeExit::disconnected()));
+        });
+
+        Ok(Self {
+            client,
+            endpoint,
+            shutdown,
+            exited,
+        })
+    }
+}
+
+/// Resolve the endpoint announced by the server against the URL of the event stream
+fn resolve_endpoint(base: &Uri, endpoint: &str) -> Result<Uri> {
+    if endpoint.starts_with("http://") || endpoint.starts_with("https://") {
+        return parse_url(endpoint);
+    }
+
+    let authority = base
+        .authority()
+        .context("Wrappee URL has no host")?
+        .as_str();
+    let path = if endpoint.starts_with('/') {
+        endpoint.to_string()
+    } else {
+        let dir = base
+            .path()
+            .rsplit_once('/')
+            .map(|(dir, _)| dir)
+            .unwrap_or("");
+        format!("{dir}/{endpoint}")
+    };
+
+    format!("http://{authority}{path}")
+        .parse()
+        .with_context(|| format!("Invalid endpoint announced by wrappee: {endpoint}"))
+}
+
+impl WrappeeTransport for SseTransport {
+    fn send<'a>(&'a self, message: &'a Value) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let body = serde_json::to_vec(message)?;
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri(self.endpoint.clone())
+                .header(CONTENT_TYPE, "application/json")
+                .body(Full::new(Bytes::from(body)))?;
+
+            let response = self
+                .client
+                .request(request)
+                .await
+                .with_context(|| format!("Failed to reach wrappee at {}", self.endpoint))?;
+
+            // Answers arrive on the event stream, the POST is only acknowledged
+            if !response.status().is_success() {
+                return Err(error_for_status(response).await);
+            }
+            Ok(())
+        })
+    }
+
+    fn pid(&self) -> Option<u32> {
+        None
+    }
+
+    fn shutdown(&self) -> BoxFuture<'_, Result<ShutdownOutcome>> {
+        Box::pin(async move {
+            self.shutdown.cancel();
+            Ok(ShutdownOutcome {
+                stage: ShutdownStage::Disconnected,
+                exit: WrappeeExit::disconnected(),
+            })
+        })
+    }
+
+    fn closed(&self) -> BoxFuture<'_, WrappeeExit> {
+        Box::pin(wait_for_exit(self.exited.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_endpoint() {
+        let base: Uri = "http://127.0.0.1:8080/mcp/sse".parse().unwrap();
+        assert_eq!(
+            resolve_endpoint(&base, "/messages?sessionId=1").unwrap(),
+            "http://127.0.0.1:8080/messages?sessionId=1"
+        );
+        assert_eq!(
+            resolve_endpoint(&base, "messages?sessionId=1").unwrap(),
+            "http://127.0.0.1:8080/mcp/messages?sessionId=1"
+        );
+        assert_eq!(
+            resolve_endpoint(&base, "http://localhost:9000/post").unwrap(),
+            "http://localhost:9000/post"
+        );
+    }
+}
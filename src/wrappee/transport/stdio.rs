@@ -0,0 +1,226 @@
+use super::{ShutdownOutcome, ShutdownStage, WrappeeExit, WrappeeTransport, wait_for_exit};
+use crate::wrappee::{Dispatcher, Routes};
+use anyhow::{Context, Result};
+use futures::future::BoxFuture;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::Arc;
+use tokio::sync::{Mutex, watch};
+use tokio::task;
+use tokio::time::{Duration, MissedTickBehavior, timeout};
+
+/// How often the child process is checked for having exited
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Wrappee running as a child process, speaking line-delimited JSON-RPC over stdio
+#[derive(Debug)]
+pub struct StdioTransport {
+    child: Arc<Mutex<Child>>,
+    /// Taken (and thereby closed) when shutting down
+    stdin: Mutex<Option<ChildStdin>>,
+    pid: u32,
+    exited: watch::Receiver<Option<WrappeeExit>>,
+    /// How long each shutdown stage waits for the process to exit
+    shutdown_grace: Duration,
+}
+
+impl StdioTransport {
+    /// Build the command that runs the wrappee process
+    pub fn command(
+        command: &str,
+        args: &[String],
+        env: &BTreeMap<String, String>,
+        cwd: Option<&Path>,
+        disable_colors: bool,
+    ) -> Command {
+        let mut cmd = Command::new(command);
+        cmd.args(args)
+            .envs(env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        if let Some(cwd) = cwd {
+            cmd.current_dir(cwd);
+        }
+
+        // Set environment variables to disable colors if requested
+        if disable_colors {
+            cmd.env("NO_COLOR", "1")
+                .env("CLICOLOR", "0")
+                .env("RUST_LOG_STYLE", "never");
+            tracing::debug!("Setting NO_COLOR=1, CLICOLOR=0, RUST_LOG_STYLE=never for wrappee");
+        }
+
+        cmd
+    }
+
+    pub fn spawn(
+        mut cmd: Command,
+        shutdown_grace: Duration,
+        dispatcher: Dispatcher,
+        routes: Routes,
+    ) -> Result<Self> {
+        tracing::info!("Spawning wrappee process: {cmd:?}");
+
+        let mut child = cmd.spawn().context("Failed to spawn wrappee process")?;
+
+        let stdin = child.stdin.take().context("Failed to get stdin")?;
+        let stdout = child.stdout.take().context("Failed to get stdout")?;
+        let stderr = child.stderr.take().context("Failed to get stderr")?;
+
+        // Stderr output ends up in the log alongside stray stdout output
+        let stderr_tx = routes.stray.clone();
+
+        // Spawn stdout reader that routes every message by its kind and id
+        task::spawn_blocking(move || {
+            let reader = BufReader::new(stdout);
+            tracing::debug!("Starting stdout reader");
+            for line in reader.lines() {
+                match line {
+                    Ok(line) if line.trim().is_empty() => {}
+                    Ok(line) => {
+                        tracing::debug!("Read line from wrappee stdout: {line}");
+                        dispatcher.route_line(line, &routes);
+                    }
+                    Err(e) => {
+                        tracing::error!("Error reading stdout: {e}");
+                        break;
+                    }
+                }
+            }
+            // Nobody will answer the outstanding requests anymore
+            dispatcher.close();
+            tracing::debug!("Stdout reader finished");
+        });
+
+        // Spawn stderr reader
+        task::spawn_blocking(move || {
+            let reader = BufReader::new(stderr);
+            tracing::debug!("Starting stderr reader");
+            for line in reader.lines() {
+                match line {
+                    Ok(line) => {
+                        tracing::info!("Wrappee stderr: {line}");
+                        if stderr_tx.blocking_send(line).is_err() {
+                            tracing::error!("Failed to send stderr line to channel");
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!("Error reading stderr: {e}");
+                        break;
+                    }
+                }
+            }
+            tracing::debug!("Stderr reader finished");
+        });
+
+        let pid = child.id();
+        let child = Arc::new(Mutex::new(child));
+        let (exited_tx, exited) = watch::channel(None);
+
+        // Watch for the process exiting, whether on its own or through shutdown
+        let watched = child.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(EXIT_POLL_INTERVAL);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            let exit = loop {
+                interval.tick().await;
+                match watched.lock().await.try_wait() {
+                    Ok(Some(status)) => break WrappeeExit::from(status),
+                    Ok(None) => {}
+                    Err(e) => {
+                        tracing::warn!("Failed to check wrappee process status: {e}");
+                        break WrappeeExit::default();
+                    }
+                }
+            };
+            tracing::info!("Wrappee process {pid} exited: {exit}");
+            exited_tx.send_replace(Some(exit));
+        });
+
+        Ok(Self {
+            pid,
+            child,
+            stdin: Mutex::new(Some(stdin)),
+            exited,
+            shutdown_grace,
+        })
+    }
+}
+
+impl StdioTransport {
+    /// Wait up to the given time for the process to exit
+    async fn wait_for_exit(&self, grace: Duration) -> Option<WrappeeExit> {
+        timeout(grace, wait_for_exit(self.exited.clone()))
+            .await
+            .ok()
+    }
+}
+
+impl WrappeeTransport for StdioTransport {
+    fn send<'a>(&'a self, message: &'a Value) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let message_str = serde_json::to_string(message)?;
+            let mut stdin = self.stdin.lock().await;
+            let stdin = stdin.as_mut().context("Wrappee stdin is closed")?;
+            writeln!(stdin, "{message_str}")?;
+            stdin.flush()?;
+            Ok(())
+        })
+    }
+
+    fn pid(&self) -> Option<u32> {
+        Some(self.pid)
+    }
+
+    fn shutdown(&self) -> BoxFuture<'_, Result<ShutdownOutcome>> {
+        Box::pin(async move {
+            let outcome = |stage, exit| Ok(ShutdownOutcome { stage, exit });
+            if let Some(exit) = *self.exited.borrow() {
+                return outcome(ShutdownStage::AlreadyExited, exit);
+            }
+
+            // A well-behaved server exits once its input ends
+            tracing::debug!("Closing stdin of wrappee process {}", self.pid);
+            drop(self.stdin.lock().await.take());
+            if let Some(exit) = self.wait_for_exit(self.shutdown_grace).await {
+                return outcome(ShutdownStage::StdinClosed, exit);
+            }
+
+            #[cfg(unix)]
+            {
+                // Holding the lock keeps the exit watcher from reaping the process, so its
+                // PID cannot be reused by another process before the signal is sent
+                let mut child = self.child.lock().await;
+                if let Some(status) = child.try_wait()? {
+                    return outcome(ShutdownStage::StdinClosed, WrappeeExit::from(status));
+                }
+                tracing::debug!("Sending SIGTERM to wrappee process {}", self.pid);
+                // SAFETY: kill(2) has no memory safety requirements
+                unsafe {
+                    libc::kill(self.pid as libc::pid_t, libc::SIGTERM);
+                }
+                drop(child);
+                if let Some(exit) = self.wait_for_exit(self.shutdown_grace).await {
+                    return outcome(ShutdownStage::Terminated, exit);
+                }
+            }
+
+            tracing::warn!("Killing wrappee process {}", self.pid);
+            self.child.lock().await.kill()?;
+            outcome(
+                ShutdownStage::Killed,
+                wait_for_exit(self.exited.clone()).await,
+            )
+        })
+    }
+
+    fn closed(&self) -> BoxFuture<'_, WrappeeExit> {
+        Box::pin(wait_for_exit(self.exited.clone()))
+    }
+}
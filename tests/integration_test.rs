@@ -1,8 +1,11 @@
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
-    use wrap_mcp::logging::LogStorage;
+    use wrap_mcp::config::{LogConfig, SinkConfig, SinkTarget, UnmatchedCall, WrappeeConfig};
+    use wrap_mcp::logging::{Direction, LogEntry, LogEntryContent, LogEntryType, LogStorage};
     use wrap_mcp::tools::ToolManager;
+    use wrap_mcp::types::RequestId;
+    use wrap_mcp::wrappee::{WrappeeClient, WrappeeTarget};
 
     #[tokio::test]
     async fn test_log_storage() {
@@ -38,6 +41,7 @@ mod tests {
             after: None,
             before: None,
             keyword: None,
+            request_id: None,
         };
 
         let filtered_logs = storage.get_logs(None, Some(filter)).await;
@@ -47,6 +51,12 @@ mod tests {
         storage.clear_logs().await;
         let count = storage.get_log_count().await;
         assert_eq!(count, 0);
+
+        // Ids continue after a clear rather than being reused
+        let id = storage
+            .add_request("test_tool".to_string(), serde_json::json!({}))
+            .await;
+        assert_eq!(id, RequestId::new(4));
     }
 
     #[tokio::test]
@@ -66,6 +76,266 @@ mod tests {
         assert_eq!(count, 5);
     }
 
+    #[tokio::test]
+    async fn test_progress_logging_is_opt_in() {
+        let storage = LogStorage::new_with_max_entries(1000);
+        storage
+            .add_progress(RequestId::new(1), "slow".to_string(), 1.0, None, None)
+            .await;
+        assert_eq!(storage.get_log_count().await, 0);
+
+        let config = LogConfig {
+            log_progress: true,
+            ..LogConfig::default()
+        };
+        let storage = LogStorage::new(&config);
+        storage
+            .add_progress(
+                RequestId::new(1),
+                "slow".to_string(),
+                1.0,
+                Some(4.0),
+                Some("step 1".to_string()),
+            )
+            .await;
+
+        let filter = wrap_mcp::logging::LogFilter {
+            tool_name: Some("slow".to_string()),
+            entry_type: Some("progress".to_string()),
+            after: None,
+            before: None,
+            keyword: None,
+            request_id: None,
+        };
+        assert_eq!(storage.get_logs(None, Some(filter)).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_persisted_logs_survive_restart() {
+        let dir = std::env::temp_dir().join(format!("wrap-mcp-persist-{}", std::process::id()));
+        _ = std::fs::remove_dir_all(&dir);
+        let mut config = LogConfig {
+            log_size: 3,
+            ..LogConfig::default()
+        };
+        config.persist.dir = Some(dir.clone());
+
+        let storage = LogStorage::open(&config).unwrap();
+        for i in 0..5 {
+            storage
+                .add_request(format!("tool_{i}"), serde_json::json!({"index": i}))
+                .await;
+        }
+        drop(storage);
+
+        // A new proxy process picks up the most recent entries and continues the ids
+        let storage = LogStorage::open(&config).unwrap();
+        assert_eq!(storage.get_log_count().await, 3);
+        let id = storage
+            .add_request("tool_5".to_string(), serde_json::json!({}))
+            .await;
+        assert_eq!(id, RequestId::new(6));
+
+        storage.clear_logs().await;
+        let storage = LogStorage::open(&config).unwrap();
+        assert_eq!(storage.get_log_count().await, 0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_log_store() {
+        let dir = std::env::temp_dir().join(format!("wrap-mcp-db-{}", std::process::id()));
+        _ = std::fs::remove_dir_all(&dir);
+        let config = LogConfig {
+            log_size: 100,
+            db: Some(dir.join("log.db")),
+            ..LogConfig::default()
+        };
+
+        let storage = LogStorage::open(&config).unwrap();
+        let id = storage
+            .add_request("search".to_string(), serde_json::json!({"q": "needle"}))
+            .await;
+        storage
+            .add_response(id, "search".to_string(), serde_json::json!({"hits": 1}))
+            .await;
+        storage.add_stderr("haystack".to_string()).await;
+        drop(storage);
+
+        let storage = LogStorage::open(&config).unwrap();
+        assert_eq!(storage.get_log_count().await, 3);
+        let filter = wrap_mcp::logging::LogFilter {
+            tool_name: None,
+            entry_type: None,
+            after: None,
+            before: None,
+            keyword: Some("needle".to_string()),
+            request_id: None,
+        };
+        let logs = storage.get_logs(None, Some(filter)).await;
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].id, id);
+
+        // Ids continue from the database
+        let next = storage
+            .add_request("search".to_string(), serde_json::json!({}))
+            .await;
+        assert_eq!(next, RequestId::new(4));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sinks_receive_filtered_entries() {
+        let path = std::env::temp_dir().join(format!("wrap-mcp-sink-{}.jsonl", std::process::id()));
+        _ = std::fs::remove_file(&path);
+        let config = LogConfig {
+            entry_types: vec![LogEntryType::Request],
+            sinks: vec![SinkConfig {
+                target: SinkTarget::File(path.clone()),
+                entry_types: vec![LogEntryType::Error],
+            }],
+            ..LogConfig::default()
+        };
+
+        let storage = LogStorage::open(&config).unwrap();
+        let id = storage
+            .add_request("fail".to_string(), serde_json::json!({}))
+            .await;
+        storage
+            .add_error(id, "fail".to_string(), "boom".to_string())
+            .await;
+        storage.add_stderr("noise".to_string()).await;
+
+        // The log keeps only requests, the file only errors
+        let logs = storage.get_logs(None, None).await;
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].id, id);
+        let written = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<LogEntry> = written
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 1);
+        assert!(matches!(lines[0].content, LogEntryContent::Error { .. }));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_raw_frames_are_captured() {
+        let storage = LogStorage::open(&LogConfig::default()).unwrap();
+        assert!(storage.wire_tap().is_none());
+
+        let config = LogConfig {
+            log_raw: true,
+            ..LogConfig::default()
+        };
+        let storage = LogStorage::open(&config).unwrap();
+        let tap = storage.wire_tap().unwrap();
+        tap.record(
+            Direction::ProxyToWrappee,
+            serde_json::json!({"jsonrpc": "2.0", "id": 3, "method": "tools/list"}),
+        );
+        tap.record(
+            Direction::WrappeeToProxy,
+            serde_json::json!({"jsonrpc": "2.0", "id": 3, "result": {"tools": []}}),
+        );
+
+        // Frames are logged in the background
+        for _ in 0..100 {
+            if storage.get_log_count().await == 2 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let filter = wrap_mcp::logging::LogFilter {
+            tool_name: None,
+            entry_type: Some("raw".to_string()),
+            after: None,
+            before: None,
+            keyword: None,
+            request_id: None,
+        };
+        let logs = storage.get_logs(None, Some(filter)).await;
+        assert_eq!(logs.len(), 2);
+        // The response is named after the request it answers
+        match &logs[0].content {
+            LogEntryContent::Raw {
+                direction, method, ..
+            } => {
+                assert_eq!(*direction, Direction::WrappeeToProxy);
+                assert_eq!(method.as_deref(), Some("tools/list"));
+            }
+            other => panic!("unexpected entry {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replayed_recording_answers_as_the_wrappee() {
+        // Record a session the way show_log format=json returns it
+        let storage = LogStorage::new_with_max_entries(1000);
+        let id = storage
+            .add_request("add".to_string(), serde_json::json!({"a": 1, "b": 2}))
+            .await;
+        let result = serde_json::json!({"content": [{"type": "text", "text": "3"}]});
+        storage
+            .add_response(
+                id,
+                "add".to_string(),
+                serde_json::json!({"jsonrpc": "2.0", "id": 5, "result": result}),
+            )
+            .await;
+        let logs = storage.get_logs(None, None).await;
+        let path =
+            std::env::temp_dir().join(format!("wrap-mcp-replay-{}.json", std::process::id()));
+        std::fs::write(&path, serde_json::to_string_pretty(&logs).unwrap()).unwrap();
+
+        let target = WrappeeTarget::Replay {
+            path: path.clone(),
+            unmatched: UnmatchedCall::Error,
+        };
+        let client = WrappeeClient::connect(&target, WrappeeConfig::default(), None)
+            .await
+            .unwrap();
+        let init = client
+            .initialize("2025-03-26", serde_json::json!({}))
+            .await
+            .unwrap();
+        assert_eq!(init["result"]["protocolVersion"], "2025-03-26");
+        let tools = client.list_tools().await.unwrap();
+        assert_eq!(tools["result"]["tools"][0]["name"], "add");
+
+        let cancellation = tokio_util::sync::CancellationToken::new();
+        let timeout = std::time::Duration::from_secs(5);
+        let response = client
+            .call_tool(
+                "add",
+                serde_json::json!({"b": 2, "a": 1}),
+                None,
+                timeout,
+                &cancellation,
+            )
+            .await
+            .unwrap();
+        assert_eq!(response["result"], result);
+        let response = client
+            .call_tool(
+                "add",
+                serde_json::json!({"a": 2, "b": 2}),
+                None,
+                timeout,
+                &cancellation,
+            )
+            .await
+            .unwrap();
+        assert!(response.get("error").is_some());
+
+        client.shutdown().await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
     #[test]
     fn test_proxy_handler_creation() {
         // Use test-specific constructor to avoid needing Config::global()